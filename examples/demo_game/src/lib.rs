@@ -4,8 +4,18 @@ use panpan::*;
 pub struct DemoGame {
     rectangles: Vec<AnimatedRect>,
     time: f32,
+    rng: Rng,
 }
 
+// Lets `panpan run --hot` reload the demo while it runs
+panpan::hot_reload!(DemoGame);
+
+/// Area the rectangles bounce around in
+const ARENA: Rect = Rect::new(0.0, 0.0, 800.0, 600.0);
+const RECT_SIZE: f32 = 50.0;
+/// Fixed so replays of the demo come out the same
+const SEED: u64 = 12345;
+
 struct AnimatedRect {
     x: f32,
     y: f32,
@@ -15,7 +25,11 @@ struct AnimatedRect {
 }
 
 impl Game for DemoGame {
-    fn new() -> Self {
+    fn conf() -> Conf {
+        Conf::new("PanPan Demo Game")
+    }
+
+    fn new(_ctx: &mut Context) -> Self {
         println!("DemoGame: Initializing...");
         
         let mut rectangles = Vec::new();
@@ -26,17 +40,18 @@ impl Game for DemoGame {
                 y: 300.0,
                 vx: angle.cos() * 100.0,
                 vy: angle.sin() * 100.0,
-                color: Self::hsv_to_color(i as f32 * 72.0, 0.8, 0.9),
+                color: Color::from_hsv(i as f32 * 72.0, 0.8, 0.9),
             });
         }
         
         Self {
             rectangles,
             time: 0.0,
+            rng: Rng::new(SEED),
         }
     }
     
-    fn update(&mut self, dt: f32) {
+    fn update(&mut self, _ctx: &mut Context, dt: f32) {
         self.time += dt;
         
         // Update rectangle positions
@@ -45,30 +60,35 @@ impl Game for DemoGame {
             rect.y += rect.vy * dt;
             
             // Bounce off walls
-            if rect.x < 0.0 || rect.x > 750.0 {
+            let push = Rect::new(rect.x, rect.y, RECT_SIZE, RECT_SIZE).offset_to_fit(&ARENA);
+            if push.x != 0.0 {
+                rect.x += push.x;
                 rect.vx = -rect.vx;
             }
-            if rect.y < 0.0 || rect.y > 550.0 {
+            if push.y != 0.0 {
+                rect.y += push.y;
                 rect.vy = -rect.vy;
             }
         }
     }
     
-    fn render(&self) {
+    fn render(&self, ctx: &mut Context) {
+        let fps_text = format!("FPS: {:.0}", ctx.timing().fps());
+        let gfx = ctx.graphics();
+
         // Clear screen
-        clear_screen(Color::new(0.1, 0.12, 0.15, 1.0));
+        gfx.clear_screen(Color::new(0.1, 0.12, 0.15, 1.0));
         
         // Draw animated rectangles
         for rect in &self.rectangles {
-            draw_rect(rect.x, rect.y, 50.0, 50.0, rect.color);
+            gfx.draw_rect(rect.x, rect.y, RECT_SIZE, RECT_SIZE, rect.color);
         }
         
         // Draw FPS indicator
-        let fps_text = format!("FPS: {:.0}", 1.0 / 0.016);
-        draw_text(&fps_text, 20.0, 20.0, 20.0, Color::GREEN);
+        gfx.draw_text(&fps_text, 20.0, 20.0, 20.0, Color::GREEN);
         
         // Draw title
-        draw_text("PanPan Demo Game", 300.0, 20.0, 24.0, Color::WHITE);
+        gfx.draw_text("PanPan Demo Game", 300.0, 20.0, 24.0, Color::WHITE);
     }
     
     fn on_touch_down(&mut self, id: i32, x: f32, y: f32) {
@@ -78,48 +98,49 @@ impl Game for DemoGame {
         self.rectangles.push(AnimatedRect {
             x,
             y,
-            vx: (rand() * 200.0) - 100.0,
-            vy: (rand() * 200.0) - 100.0,
-            color: Self::hsv_to_color(rand() * 360.0, 0.8, 0.9),
+            vx: self.rng.range(-100.0..100.0),
+            vy: self.rng.range(-100.0..100.0),
+            color: Color::from_hsv(self.rng.range(0.0..360.0), 0.8, 0.9),
         });
     }
     
     fn on_touch_up(&mut self, id: i32) {
         println!("Touch up: id={}", id);
     }
-}
 
-impl DemoGame {
-    fn hsv_to_color(h: f32, s: f32, v: f32) -> Color {
-        let c = v * s;
-        let h_prime = h / 60.0;
-        let x = c * (1.0 - ((h_prime % 2.0) - 1.0).abs());
-        
-        let (r1, g1, b1) = match h_prime as i32 {
-            0 => (c, x, 0.0),
-            1 => (x, c, 0.0),
-            2 => (0.0, c, x),
-            3 => (0.0, x, c),
-            4 => (x, 0.0, c),
-            _ => (c, 0.0, x),
-        };
-        
-        let m = v - c;
-        Color::new(r1 + m, g1 + m, b1 + m, 1.0)
+    fn serialize(&self) -> Vec<u8> {
+        // time, then x, y, vx, vy, r, g, b, a of every rectangle
+        let mut values = vec![self.time];
+        for rect in &self.rectangles {
+            let Color { r, g, b, a } = rect.color;
+            values.extend([rect.x, rect.y, rect.vx, rect.vy, r, g, b, a]);
+        }
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
     }
-}
 
-// Simple pseudo-random number generator
-fn rand() -> f32 {
-    use std::cell::Cell;
-    thread_local! {
-        static SEED: Cell<u32> = Cell::new(12345);
+    fn restore(_ctx: &mut Context, data: &[u8]) -> Self {
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        let rectangles = values
+            .get(1..)
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|v| AnimatedRect {
+                x: v[0],
+                y: v[1],
+                vx: v[2],
+                vy: v[3],
+                color: Color::new(v[4], v[5], v[6], v[7]),
+            })
+            .collect();
+
+        Self {
+            rectangles,
+            time: values.first().copied().unwrap_or(0.0),
+            // Starting the random sequence over is fine after a reload
+            rng: Rng::new(SEED),
+        }
     }
-    
-    SEED.with(|seed| {
-        let mut s = seed.get();
-        s = s.wrapping_mul(1103515245).wrapping_add(12345);
-        seed.set(s);
-        ((s / 65536) % 32768) as f32 / 32768.0
-    })
-}
\ No newline at end of file
+}
@@ -0,0 +1,340 @@
+// panpan/src/animation/aseprite.rs
+// Import of Aseprite's JSON sprite sheet export
+//
+// Export with File > Export Sprite Sheet, "JSON Data" checked, in either the
+// "Hash" or "Array" layout, with "Tags" included under Meta. Trimmed frames
+// keep their place in the full frame. Durations are in milliseconds.
+
+use super::{Frame, LoopMode, SpriteSheet, Tag};
+use crate::types::{Rect, Vec2};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Deserialize)]
+struct Document {
+    frames: Frames,
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frames {
+    Array(Vec<RawFrame>),
+    Hash(InOrder),
+}
+
+/// Frames of the "Hash" layout, in the order of the file (keys are file
+/// names, which do not sort by frame number)
+struct InOrder(Vec<RawFrame>);
+
+impl<'de> Deserialize<'de> for InOrder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct InOrderVisitor;
+
+        impl<'de> Visitor<'de> for InOrderVisitor {
+            type Value = InOrder;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of frames")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<InOrder, A::Error> {
+                let mut frames = Vec::new();
+                while let Some((_, frame)) = map.next_entry::<de::IgnoredAny, RawFrame>()? {
+                    frames.push(frame);
+                }
+                Ok(InOrder(frames))
+            }
+        }
+
+        deserializer.deserialize_map(InOrderVisitor)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawFrame {
+    frame: RawRect,
+    sprite_source_size: RawRect,
+    source_size: RawSize,
+    duration: f32,
+}
+
+#[derive(Deserialize)]
+struct RawRect {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+struct RawSize {
+    w: f32,
+    h: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    image: Option<String>,
+    #[serde(default)]
+    frame_tags: Vec<RawTag>,
+}
+
+#[derive(Deserialize)]
+struct RawTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: String,
+    /// A number in a string, missing or "0" for forever
+    repeat: Option<String>,
+}
+
+impl SpriteSheet {
+    /// Read the JSON Aseprite exports next to a sprite sheet. Load the
+    /// texture named by `image()` separately.
+    pub fn from_aseprite_json(json: &str) -> serde_json::Result<Self> {
+        let document: Document = serde_json::from_str(json)?;
+        let raw = match document.frames {
+            Frames::Array(frames) | Frames::Hash(InOrder(frames)) => frames,
+        };
+        let frames: Vec<Frame> = raw
+            .into_iter()
+            .map(|raw| Frame {
+                source: Rect::new(raw.frame.x, raw.frame.y, raw.frame.w, raw.frame.h),
+                offset: Vec2::new(raw.sprite_source_size.x, raw.sprite_source_size.y),
+                size: Vec2::new(raw.source_size.w, raw.source_size.h),
+                duration: raw.duration / 1000.0,
+            })
+            .collect();
+
+        let tags = document
+            .meta
+            .frame_tags
+            .into_iter()
+            .map(|raw| {
+                if raw.from > raw.to || raw.to >= frames.len() {
+                    return Err(de::Error::custom(format!(
+                        "tag \"{}\" covers frames {}..={} of {}",
+                        raw.name,
+                        raw.from,
+                        raw.to,
+                        frames.len()
+                    )));
+                }
+                let (loop_mode, reversed) = match raw.direction.as_str() {
+                    "reverse" => (LoopMode::Loop, true),
+                    "pingpong" => (LoopMode::PingPong, false),
+                    "pingpong_reverse" => (LoopMode::PingPong, true),
+                    _ => (LoopMode::Loop, false),
+                };
+                let cycles = raw
+                    .repeat
+                    .and_then(|repeat| repeat.parse::<u32>().ok())
+                    .filter(|&cycles| cycles > 0);
+                Ok(Tag {
+                    name: raw.name,
+                    from: raw.from,
+                    to: raw.to,
+                    loop_mode,
+                    reversed,
+                    cycles,
+                })
+            })
+            .collect::<serde_json::Result<_>>()?;
+
+        Ok(SpriteSheet {
+            frames,
+            tags,
+            image: document.meta.image,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "aseprite"))]
+mod tests {
+    use super::*;
+
+    /// File > Export Sprite Sheet with the "Hash" layout, the second frame
+    /// trimmed
+    const HASH: &str = r##"{ "frames": {
+   "player 0.aseprite": {
+    "frame": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 100
+   },
+   "player 1.aseprite": {
+    "frame": { "x": 16, "y": 0, "w": 10, "h": 14 },
+    "rotated": false,
+    "trimmed": true,
+    "spriteSourceSize": { "x": 3, "y": 2, "w": 10, "h": 14 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 150
+   },
+   "player 2.aseprite": {
+    "frame": { "x": 26, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 100
+   }
+ },
+ "meta": {
+  "app": "https://www.aseprite.org/",
+  "version": "1.3.7-x64",
+  "image": "player.png",
+  "format": "RGBA8888",
+  "size": { "w": 42, "h": 16 },
+  "scale": "1",
+  "frameTags": [
+   { "name": "idle", "from": 0, "to": 0, "direction": "forward", "color": "#000000ff" },
+   { "name": "walk", "from": 1, "to": 2, "direction": "pingpong", "color": "#000000ff", "repeat": "3" }
+  ],
+  "layers": [
+   { "name": "Layer 1", "opacity": 255, "blendMode": "normal" }
+  ],
+  "slices": [
+  ]
+ }
+}
+"##;
+
+    /// The same sprite with the "Array" layout
+    const ARRAY: &str = r##"{ "frames": [
+   {
+    "filename": "player 0.aseprite",
+    "frame": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 100
+   },
+   {
+    "filename": "player 1.aseprite",
+    "frame": { "x": 16, "y": 0, "w": 10, "h": 14 },
+    "rotated": false,
+    "trimmed": true,
+    "spriteSourceSize": { "x": 3, "y": 2, "w": 10, "h": 14 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 150
+   },
+   {
+    "filename": "player 2.aseprite",
+    "frame": { "x": 26, "y": 0, "w": 16, "h": 16 },
+    "rotated": false,
+    "trimmed": false,
+    "spriteSourceSize": { "x": 0, "y": 0, "w": 16, "h": 16 },
+    "sourceSize": { "w": 16, "h": 16 },
+    "duration": 100
+   }
+ ],
+ "meta": {
+  "app": "https://www.aseprite.org/",
+  "version": "1.3.7-x64",
+  "image": "player.png",
+  "format": "RGBA8888",
+  "size": { "w": 42, "h": 16 },
+  "scale": "1",
+  "frameTags": [
+   { "name": "idle", "from": 0, "to": 0, "direction": "forward", "color": "#000000ff" },
+   { "name": "walk", "from": 1, "to": 2, "direction": "pingpong", "color": "#000000ff", "repeat": "3" }
+  ],
+  "layers": [
+   { "name": "Layer 1", "opacity": 255, "blendMode": "normal" }
+  ],
+  "slices": [
+  ]
+ }
+}
+"##;
+
+    /// A sheet of `frames` one-second frames with `tags` spliced into the meta
+    fn with_tags(frames: usize, tags: &str) -> String {
+        let frame = r#"{ "frame": { "x": 0, "y": 0, "w": 8, "h": 8 },
+            "spriteSourceSize": { "x": 0, "y": 0, "w": 8, "h": 8 },
+            "sourceSize": { "w": 8, "h": 8 }, "duration": 1000 }"#;
+        format!(
+            r#"{{ "frames": [{}], "meta": {{ "frameTags": [{tags}] }} }}"#,
+            vec![frame; frames].join(",")
+        )
+    }
+
+    #[test]
+    fn reads_both_layouts() {
+        let sheet = SpriteSheet::from_aseprite_json(HASH).unwrap();
+        assert_eq!(SpriteSheet::from_aseprite_json(ARRAY).unwrap(), sheet);
+        assert_eq!(sheet.image(), Some("player.png"));
+
+        let frames = sheet.frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].source, Rect::new(0.0, 0.0, 16.0, 16.0));
+        assert_eq!(frames[0].offset, Vec2::ZERO);
+        assert_eq!(frames[2].source.x, 26.0);
+        // Trimmed, placed inside the full frame
+        assert_eq!(frames[1].source, Rect::new(16.0, 0.0, 10.0, 14.0));
+        assert_eq!(frames[1].offset, Vec2::new(3.0, 2.0));
+        assert_eq!(frames[1].size, Vec2::new(16.0, 16.0));
+        // Milliseconds to seconds
+        assert_eq!(frames[0].duration, 0.1);
+        assert_eq!(frames[1].duration, 0.15);
+
+        let walk = sheet.tag("walk").unwrap();
+        assert_eq!((walk.from, walk.to), (1, 2));
+        assert_eq!(walk.loop_mode, LoopMode::PingPong);
+        assert_eq!(walk.cycles, Some(3));
+        let idle = sheet.animation("idle").unwrap();
+        assert_eq!(idle.frames(), &frames[..1]);
+    }
+
+    #[test]
+    fn maps_direction_and_repeat() {
+        let json = with_tags(
+            2,
+            r#"{ "name": "forward", "from": 0, "to": 1, "direction": "forward" },
+            { "name": "reverse", "from": 0, "to": 1, "direction": "reverse", "repeat": "2" },
+            { "name": "pingpong", "from": 0, "to": 1, "direction": "pingpong", "repeat": "0" },
+            { "name": "pingpong_reverse", "from": 0, "to": 1, "direction": "pingpong_reverse" },
+            { "name": "old", "from": 1, "to": 1 }"#,
+        );
+        let sheet = SpriteSheet::from_aseprite_json(&json).unwrap();
+        let mapped: Vec<_> = sheet
+            .tags()
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.loop_mode, tag.reversed, tag.cycles))
+            .collect();
+        assert_eq!(
+            mapped,
+            [
+                ("forward", LoopMode::Loop, false, None),
+                ("reverse", LoopMode::Loop, true, Some(2)),
+                ("pingpong", LoopMode::PingPong, false, None),
+                ("pingpong_reverse", LoopMode::PingPong, true, None),
+                ("old", LoopMode::Loop, false, None),
+            ]
+        );
+        assert_eq!(sheet.frames()[0].duration, 1.0);
+        assert_eq!(sheet.image(), None);
+    }
+
+    #[test]
+    fn rejects_tags_outside_the_frames() {
+        for tag in [
+            r#"{ "name": "past_the_end", "from": 1, "to": 2 }"#,
+            r#"{ "name": "backwards", "from": 1, "to": 0 }"#,
+        ] {
+            let error = SpriteSheet::from_aseprite_json(&with_tags(2, tag)).unwrap_err();
+            let name = tag.split('"').nth(3).unwrap();
+            assert!(error.to_string().contains(name), "{error}");
+        }
+        assert!(SpriteSheet::from_aseprite_json("{}").is_err());
+    }
+}
@@ -0,0 +1,509 @@
+// panpan/src/animation/mod.rs
+// Sprite animation: frames of a texture shown one after another
+//
+// An `Animation` owns its frame list and playback state and is advanced by
+// the `dt` of `Game::update`. Frames usually come from a `SpriteSheet`, built
+// from an Aseprite JSON export (feature "aseprite") or a `Grid` of equal
+// cells, and are drawn from the sheet's `Texture`.
+
+#[cfg(feature = "aseprite")]
+mod aseprite;
+mod sheet;
+
+pub use sheet::{Grid, SpriteSheet, Tag};
+
+use crate::graphics::{Graphics, Texture};
+use crate::types::{Color, Rect, Vec2};
+
+/// One image of an animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    /// Region of the texture, in pixels
+    pub source: Rect,
+    /// Where `source` sits inside the full frame, non-zero for frames with
+    /// transparent borders trimmed off
+    pub offset: Vec2,
+    /// Size of the full frame before trimming
+    pub size: Vec2,
+    /// Seconds the frame is shown
+    pub duration: f32,
+}
+
+impl Frame {
+    pub fn new(source: Rect, duration: f32) -> Self {
+        Self {
+            source,
+            offset: Vec2::ZERO,
+            size: source.size(),
+            duration,
+        }
+    }
+
+    /// Draw the frame with the top-left corner of the full frame at
+    /// `(x, y)`. Flipping mirrors the full frame, so trimmed frames stay in
+    /// place.
+    pub fn draw(&self, gfx: &mut Graphics, texture: &Texture, x: f32, y: f32, params: DrawParams) {
+        let mut offset = self.offset;
+        if params.flip_x {
+            offset.x = self.size.x - self.offset.x - self.source.width;
+        }
+        if params.flip_y {
+            offset.y = self.size.y - self.offset.y - self.source.height;
+        }
+        let mut dest = Rect::new(
+            x + offset.x * params.scale,
+            y + offset.y * params.scale,
+            self.source.width * params.scale,
+            self.source.height * params.scale,
+        );
+        // A negative size draws the texture mirrored
+        if params.flip_x {
+            dest.x += dest.width;
+            dest.width = -dest.width;
+        }
+        if params.flip_y {
+            dest.y += dest.height;
+            dest.height = -dest.height;
+        }
+        gfx.draw_texture_region(texture, self.source, dest, params.tint);
+    }
+}
+
+/// How frames are drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawParams {
+    pub scale: f32,
+    /// Mirror horizontally, e.g. for a character facing left
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Multiplied with the texture; white leaves it unchanged
+    pub tint: Color,
+}
+
+impl Default for DrawParams {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            flip_x: false,
+            flip_y: false,
+            tint: Color::WHITE,
+        }
+    }
+}
+
+/// What happens after the last frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LoopMode {
+    /// Stop on the last frame
+    Once,
+    /// Start over from the first frame
+    #[default]
+    Loop,
+    /// Play backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// Something that happened during the last `Animation::update`
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnimationEvent {
+    /// A frame with a marker was shown, see `Animation::with_event`
+    Marker { frame: usize, name: String },
+    /// A cycle ended and the next one started
+    Looped,
+    /// The last cycle ended; the animation holds its final frame
+    Finished,
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: Vec<Frame>,
+    loop_mode: LoopMode,
+    reversed: bool,
+    /// Cycles to play, `None` forever
+    cycles: Option<u32>,
+    markers: Vec<(usize, String)>,
+    speed: f32,
+
+    current: usize,
+    /// Into the current frame
+    time: f32,
+    /// Playing towards the first frame
+    backwards: bool,
+    cycles_done: u32,
+    /// The current frame's markers have not fired yet
+    entered: bool,
+    finished: bool,
+    events: Vec<AnimationEvent>,
+}
+
+impl Animation {
+    /// Panics if `frames` is empty
+    pub fn new(frames: Vec<Frame>) -> Self {
+        assert!(!frames.is_empty(), "animation without frames");
+        Self {
+            frames,
+            loop_mode: LoopMode::Loop,
+            reversed: false,
+            cycles: None,
+            markers: Vec::new(),
+            speed: 1.0,
+            current: 0,
+            time: 0.0,
+            backwards: false,
+            cycles_done: 0,
+            entered: false,
+            finished: false,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_loop_mode(mut self, loop_mode: LoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self.reset();
+        self
+    }
+
+    /// Play from the last frame to the first
+    pub fn reversed(mut self) -> Self {
+        self.reversed = true;
+        self.reset();
+        self
+    }
+
+    /// Finish after `cycles` loops instead of playing forever. No effect on
+    /// `LoopMode::Once`.
+    pub fn with_cycles(mut self, cycles: u32) -> Self {
+        self.cycles = Some(cycles.max(1));
+        self
+    }
+
+    /// Report `AnimationEvent::Marker` whenever `frame` is shown, e.g. a
+    /// footstep sound or the moment a sword hit lands
+    pub fn with_event(mut self, frame: usize, name: impl Into<String>) -> Self {
+        self.markers.push((frame, name.into()));
+        self
+    }
+
+    /// Playback speed, 1.0 as authored, 0.0 paused
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// Seconds of one pass through every frame
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    /// The frame to draw now
+    pub fn frame(&self) -> &Frame {
+        &self.frames[self.current]
+    }
+
+    pub fn frame_index(&self) -> usize {
+        self.current
+    }
+
+    /// Jump to `index` (clamped), keeping the direction of play
+    pub fn set_frame(&mut self, index: usize) {
+        self.current = index.min(self.frames.len() - 1);
+        self.time = 0.0;
+        self.entered = false;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Back to the first frame, as if just created
+    pub fn reset(&mut self) {
+        self.backwards = self.reversed;
+        self.current = if self.reversed {
+            self.frames.len() - 1
+        } else {
+            0
+        };
+        self.time = 0.0;
+        self.cycles_done = 0;
+        self.entered = false;
+        self.finished = false;
+        self.events.clear();
+    }
+
+    /// Events from the last `update`
+    pub fn events(&self) -> &[AnimationEvent] {
+        &self.events
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+        if !self.entered {
+            self.enter();
+        }
+        if self.finished {
+            return;
+        }
+        self.time += dt * self.speed;
+        // Zero-length frames are skipped, but a whole pass of them would
+        // never use up any time
+        let mut skipped = 0;
+        while self.time >= self.frame().duration && skipped <= self.frames.len() * 2 {
+            if self.frame().duration <= 0.0 {
+                skipped += 1;
+            } else {
+                skipped = 0;
+            }
+            self.time -= self.frame().duration;
+            if !self.next_frame() {
+                self.time = 0.0;
+                break;
+            }
+            self.enter();
+        }
+    }
+
+    pub fn draw(&self, gfx: &mut Graphics, texture: &Texture, x: f32, y: f32, params: DrawParams) {
+        self.frame().draw(gfx, texture, x, y, params);
+    }
+
+    fn enter(&mut self) {
+        self.entered = true;
+        for (frame, name) in &self.markers {
+            if *frame == self.current {
+                self.events.push(AnimationEvent::Marker {
+                    frame: *frame,
+                    name: name.clone(),
+                });
+            }
+        }
+    }
+
+    /// Step to the next frame. Returns false once the animation finished.
+    fn next_frame(&mut self) -> bool {
+        let last = self.frames.len() - 1;
+        let at_end = if self.backwards {
+            self.current == 0
+        } else {
+            self.current == last
+        };
+        if !at_end {
+            self.step();
+            return true;
+        }
+
+        let cycle_ended = match self.loop_mode {
+            LoopMode::Once => {
+                self.finish();
+                return false;
+            }
+            LoopMode::Loop => true,
+            // A cycle goes there and back, ending where it started
+            LoopMode::PingPong => self.backwards != self.reversed,
+        };
+        if cycle_ended {
+            self.cycles_done += 1;
+            if self.cycles.is_some_and(|cycles| self.cycles_done >= cycles) {
+                self.finish();
+                return false;
+            }
+            self.events.push(AnimationEvent::Looped);
+        }
+        match self.loop_mode {
+            LoopMode::Loop => self.current = if self.backwards { last } else { 0 },
+            _ => {
+                self.backwards = !self.backwards;
+                if last > 0 {
+                    self.step();
+                }
+            }
+        }
+        true
+    }
+
+    fn step(&mut self) {
+        if self.backwards {
+            self.current -= 1;
+        } else {
+            self.current += 1;
+        }
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        self.events.push(AnimationEvent::Finished);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `durations.len()` frames side by side, 16 pixels wide
+    fn frames(durations: &[f32]) -> Vec<Frame> {
+        durations
+            .iter()
+            .enumerate()
+            .map(|(i, &duration)| Frame::new(Rect::new(i as f32 * 16.0, 0.0, 16.0, 16.0), duration))
+            .collect()
+    }
+
+    /// Frame index after each of `updates` steps of `dt`
+    fn play(animation: &mut Animation, updates: usize, dt: f32) -> Vec<usize> {
+        (0..updates)
+            .map(|_| {
+                animation.update(dt);
+                animation.frame_index()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loop_modes() {
+        let mut once = Animation::new(frames(&[0.25; 3])).with_loop_mode(LoopMode::Once);
+        assert_eq!(play(&mut once, 2, 0.25), [1, 2]);
+        assert!(!once.is_finished());
+        once.update(0.25);
+        assert_eq!(once.events(), [AnimationEvent::Finished]);
+        assert_eq!(play(&mut once, 2, 0.25), [2, 2]);
+        assert!(once.is_finished());
+        assert!(once.events().is_empty());
+
+        let mut looping = Animation::new(frames(&[0.25; 3]));
+        assert_eq!(play(&mut looping, 5, 0.25), [1, 2, 0, 1, 2]);
+        assert!(!looping.is_finished());
+
+        // Turns around on the ends without showing them twice
+        let mut ping_pong = Animation::new(frames(&[0.25; 3])).with_loop_mode(LoopMode::PingPong);
+        assert_eq!(play(&mut ping_pong, 4, 0.25), [1, 2, 1, 0]);
+        ping_pong.update(0.25);
+        assert_eq!(ping_pong.frame_index(), 1);
+        assert_eq!(ping_pong.events(), [AnimationEvent::Looped]);
+
+        // A single frame just repeats
+        let mut single = Animation::new(frames(&[0.25])).with_loop_mode(LoopMode::PingPong);
+        assert_eq!(play(&mut single, 3, 0.25), [0, 0, 0]);
+    }
+
+    #[test]
+    fn reversed_starts_from_the_last_frame() {
+        let mut looping = Animation::new(frames(&[0.25; 3])).reversed();
+        assert_eq!(looping.frame_index(), 2);
+        assert_eq!(play(&mut looping, 4, 0.25), [1, 0, 2, 1]);
+
+        let mut once = Animation::new(frames(&[0.25; 3]))
+            .with_loop_mode(LoopMode::Once)
+            .reversed();
+        assert_eq!(play(&mut once, 3, 0.25), [1, 0, 0]);
+        assert!(once.is_finished());
+
+        let mut ping_pong = Animation::new(frames(&[0.25; 3]))
+            .with_loop_mode(LoopMode::PingPong)
+            .reversed();
+        assert_eq!(play(&mut ping_pong, 5, 0.25), [1, 0, 1, 2, 1]);
+
+        ping_pong.reset();
+        assert_eq!(ping_pong.frame_index(), 2);
+    }
+
+    #[test]
+    fn cycles_end_on_the_last_frame_of_a_cycle() {
+        let mut looping = Animation::new(frames(&[0.25; 3])).with_cycles(2);
+        let mut events = Vec::new();
+        for _ in 0..8 {
+            looping.update(0.25);
+            events.extend_from_slice(looping.events());
+        }
+        assert_eq!(events, [AnimationEvent::Looped, AnimationEvent::Finished]);
+        assert_eq!(looping.frame_index(), 2);
+
+        // There and back again
+        let mut ping_pong = Animation::new(frames(&[0.25; 3]))
+            .with_loop_mode(LoopMode::PingPong)
+            .with_cycles(1);
+        assert_eq!(play(&mut ping_pong, 6, 0.25), [1, 2, 1, 0, 0, 0]);
+        assert!(ping_pong.is_finished());
+
+        // Zero counts as one
+        let mut once = Animation::new(frames(&[0.25; 2])).with_cycles(0);
+        assert_eq!(play(&mut once, 3, 0.25), [1, 1, 1]);
+        assert!(once.is_finished());
+
+        // Once stops after one pass whatever the cycles
+        let mut once = Animation::new(frames(&[0.25; 2]))
+            .with_loop_mode(LoopMode::Once)
+            .with_cycles(3);
+        assert_eq!(play(&mut once, 3, 0.25), [1, 1, 1]);
+        assert!(once.is_finished());
+    }
+
+    #[test]
+    fn markers_fire_when_their_frame_is_shown() {
+        let marker = |frame, name: &str| AnimationEvent::Marker {
+            frame,
+            name: name.to_string(),
+        };
+        let mut animation = Animation::new(frames(&[0.25; 3]))
+            .with_event(0, "start")
+            .with_event(2, "hit");
+        // The first frame is entered on the first update
+        animation.update(0.1);
+        assert_eq!(animation.events(), [marker(0, "start")]);
+        animation.update(0.1);
+        assert!(animation.events().is_empty());
+
+        // Every frame passed in one update reports its markers
+        animation.update(0.6);
+        assert_eq!(
+            animation.events(),
+            [marker(2, "hit"), AnimationEvent::Looped, marker(0, "start")]
+        );
+
+        animation.set_frame(2);
+        animation.update(0.0);
+        assert_eq!(animation.events(), [marker(2, "hit")]);
+    }
+
+    #[test]
+    fn zero_duration_frames_are_skipped() {
+        let mut animation = Animation::new(frames(&[0.25, 0.0, 0.25]));
+        animation.update(0.25);
+        assert_eq!(animation.frame_index(), 2);
+
+        // Nothing but zero-length frames must not spin forever
+        let mut animation = Animation::new(frames(&[0.0; 3]));
+        animation.update(1.0);
+        animation.update(0.0);
+        assert!(!animation.is_finished());
+
+        let mut once = Animation::new(frames(&[0.0; 3])).with_loop_mode(LoopMode::Once);
+        once.update(0.0);
+        assert!(once.is_finished());
+        assert_eq!(once.frame_index(), 2);
+    }
+
+    #[test]
+    fn speed_scales_time() {
+        let mut animation = Animation::new(frames(&[0.25; 4])).with_speed(2.0);
+        assert_eq!(animation.duration(), 1.0);
+        assert_eq!(play(&mut animation, 2, 0.25), [2, 0]);
+        animation.set_speed(0.0);
+        assert_eq!(play(&mut animation, 2, 0.25), [0, 0]);
+    }
+}
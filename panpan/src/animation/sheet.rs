@@ -0,0 +1,220 @@
+// panpan/src/animation/sheet.rs
+// Where frames come from: a grid of equal cells or a sheet with named tags
+
+use super::{Animation, Frame, LoopMode};
+use crate::graphics::Texture;
+use crate::types::Rect;
+
+/// Frames in cells of equal size, numbered left to right, then top to
+/// bottom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    pub cell_width: f32,
+    pub cell_height: f32,
+    pub columns: u32,
+    /// Empty pixels around the whole grid
+    pub margin: f32,
+    /// Empty pixels between neighboring cells
+    pub spacing: f32,
+}
+
+impl Grid {
+    pub fn new(cell_width: f32, cell_height: f32, columns: u32) -> Self {
+        Self {
+            cell_width,
+            cell_height,
+            columns: columns.max(1),
+            margin: 0.0,
+            spacing: 0.0,
+        }
+    }
+
+    /// As many columns as fit across `texture`, without margin or spacing
+    pub fn for_texture(texture: &Texture, cell_width: f32, cell_height: f32) -> Self {
+        let columns = (texture.width() as f32 / cell_width).floor() as u32;
+        Self::new(cell_width, cell_height, columns)
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Region of cell `index` in the texture
+    pub fn cell(&self, index: usize) -> Rect {
+        let columns = self.columns as usize;
+        let (column, row) = ((index % columns) as f32, (index / columns) as f32);
+        Rect::new(
+            self.margin + column * (self.cell_width + self.spacing),
+            self.margin + row * (self.cell_height + self.spacing),
+            self.cell_width,
+            self.cell_height,
+        )
+    }
+
+    /// Frames of `cells`, each shown for `duration` seconds
+    pub fn frames(&self, cells: impl IntoIterator<Item = usize>, duration: f32) -> Vec<Frame> {
+        cells
+            .into_iter()
+            .map(|cell| Frame::new(self.cell(cell), duration))
+            .collect()
+    }
+
+    /// Looping animation of `cells`, e.g. `grid.animation(8..12, 0.1)`.
+    /// Panics if `cells` is empty.
+    pub fn animation(&self, cells: impl IntoIterator<Item = usize>, duration: f32) -> Animation {
+        Animation::new(self.frames(cells, duration))
+    }
+}
+
+/// A named range of a sheet's frames, like an Aseprite tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    /// First frame
+    pub from: usize,
+    /// Last frame, included
+    pub to: usize,
+    pub loop_mode: LoopMode,
+    pub reversed: bool,
+    /// Cycles to play, `None` forever
+    pub cycles: Option<u32>,
+}
+
+impl Tag {
+    /// Looping forwards from `from` to `to`, both included
+    pub fn new(name: impl Into<String>, from: usize, to: usize) -> Self {
+        Self {
+            name: name.into(),
+            from,
+            to,
+            loop_mode: LoopMode::Loop,
+            reversed: false,
+            cycles: None,
+        }
+    }
+}
+
+/// Every frame of a texture, with tags naming the animations in it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpriteSheet {
+    pub(super) frames: Vec<Frame>,
+    pub(super) tags: Vec<Tag>,
+    pub(super) image: Option<String>,
+}
+
+impl SpriteSheet {
+    pub fn new(frames: Vec<Frame>) -> Self {
+        Self {
+            frames,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_tag(mut self, tag: Tag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&Tag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+
+    /// File name of the texture, if the sheet came from a format that
+    /// records it. Relative to the sheet's own file.
+    pub fn image(&self) -> Option<&str> {
+        self.image.as_deref()
+    }
+
+    /// Animation of the tag called `name`. `None` if there is no such tag
+    /// or its frames are out of range.
+    pub fn animation(&self, name: &str) -> Option<Animation> {
+        let tag = self.tag(name)?;
+        let frames = self.frames.get(tag.from..=tag.to)?;
+        if frames.is_empty() {
+            return None;
+        }
+        let mut animation = Animation::new(frames.to_vec()).with_loop_mode(tag.loop_mode);
+        if tag.reversed {
+            animation = animation.reversed();
+        }
+        if let Some(cycles) = tag.cycles {
+            animation = animation.with_cycles(cycles);
+        }
+        Some(animation)
+    }
+
+    /// Looping animation of every frame, `None` for an empty sheet
+    pub fn all_frames(&self) -> Option<Animation> {
+        (!self.frames.is_empty()).then(|| Animation::new(self.frames.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_cells_skip_margin_and_spacing() {
+        let grid = Grid::new(16.0, 24.0, 4).with_margin(2.0).with_spacing(1.0);
+        assert_eq!(grid.cell(0), Rect::new(2.0, 2.0, 16.0, 24.0));
+        assert_eq!(grid.cell(3), Rect::new(53.0, 2.0, 16.0, 24.0));
+        assert_eq!(grid.cell(5), Rect::new(19.0, 27.0, 16.0, 24.0));
+
+        let frames = grid.frames(4..6, 0.1);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].source, Rect::new(2.0, 27.0, 16.0, 24.0));
+        assert_eq!(frames[1].duration, 0.1);
+
+        // At least one column
+        assert_eq!(
+            Grid::new(8.0, 8.0, 0).cell(2),
+            Rect::new(0.0, 16.0, 8.0, 8.0)
+        );
+    }
+
+    #[test]
+    fn animations_from_tags() {
+        let sheet = SpriteSheet::new(Grid::new(16.0, 16.0, 8).frames(0..6, 0.1))
+            .with_tag(Tag::new("idle", 0, 1))
+            .with_tag(Tag {
+                loop_mode: LoopMode::PingPong,
+                reversed: true,
+                cycles: Some(2),
+                ..Tag::new("walk", 2, 5)
+            })
+            .with_tag(Tag::new("past_the_end", 4, 6))
+            .with_tag(Tag::new("backwards", 3, 2))
+            .with_tag(Tag::new("far_backwards", 5, 2));
+
+        let idle = sheet.animation("idle").unwrap();
+        assert_eq!(idle.frames(), &sheet.frames()[0..2]);
+        assert_eq!(idle.loop_mode(), LoopMode::Loop);
+
+        let walk = sheet.animation("walk").unwrap();
+        assert_eq!(walk.frames(), &sheet.frames()[2..6]);
+        assert_eq!(walk.loop_mode(), LoopMode::PingPong);
+        assert_eq!(walk.frame_index(), 3);
+
+        assert!(sheet.animation("past_the_end").is_none());
+        assert!(sheet.animation("backwards").is_none());
+        assert!(sheet.animation("far_backwards").is_none());
+        assert!(sheet.animation("run").is_none());
+
+        assert_eq!(sheet.all_frames().unwrap().frames().len(), 6);
+        assert!(SpriteSheet::default().all_frames().is_none());
+    }
+}
@@ -0,0 +1,195 @@
+// panpan/src/collision/broadphase.rs
+// Spatial hash for finding which of many objects might touch
+//
+// Objects are registered by index with their bounding box and stored in
+// every grid cell the box covers. A pair or query result is reported only
+// from the first cell both boxes share, so nothing is reported twice and no
+// scratch memory is needed. Cells should be about the size of a typical
+// object; much larger objects work but land in many cells.
+
+use crate::types::Rect;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CellRange {
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    /// Bounds and covered cells of each object, by id
+    objects: Vec<Option<(Rect, CellRange)>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "cell size must be positive");
+        Self {
+            cell_size,
+            ..Self::default()
+        }
+    }
+
+    /// Add object `id`, or move it if it is already there. Ids index a
+    /// vector, so use small numbers such as entity indices.
+    pub fn insert(&mut self, id: usize, bounds: Rect) {
+        let range = self.cell_range(&bounds);
+        if id >= self.objects.len() {
+            self.objects.resize(id + 1, None);
+        }
+        match self.objects[id] {
+            Some((_, old)) if old == range => {}
+            Some((_, old)) => {
+                self.unlink(id, old);
+                self.link(id, range);
+            }
+            None => self.link(id, range),
+        }
+        self.objects[id] = Some((bounds, range));
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        if let Some((_, range)) = self.objects.get_mut(id).and_then(Option::take) {
+            self.unlink(id, range);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.objects.clear();
+    }
+
+    pub fn bounds(&self, id: usize) -> Option<Rect> {
+        self.objects
+            .get(id)
+            .copied()
+            .flatten()
+            .map(|(bounds, _)| bounds)
+    }
+
+    /// Every object whose bounds intersect `area`, each once
+    pub fn query(&self, area: Rect, mut f: impl FnMut(usize)) {
+        let range = self.cell_range(&area);
+        for cell in cells(range) {
+            for &id in self.cells.get(&cell).into_iter().flatten() {
+                let (bounds, object) = self.objects[id].unwrap();
+                if first_shared_cell(range, object) == cell && bounds.intersects(&area) {
+                    f(id);
+                }
+            }
+        }
+    }
+
+    /// Every pair of objects whose bounds intersect, each once, with the
+    /// smaller id first
+    pub fn pairs(&self, mut f: impl FnMut(usize, usize)) {
+        for (&cell, ids) in &self.cells {
+            for (i, &a) in ids.iter().enumerate() {
+                let (bounds_a, range_a) = self.objects[a].unwrap();
+                for &b in &ids[i + 1..] {
+                    let (bounds_b, range_b) = self.objects[b].unwrap();
+                    if first_shared_cell(range_a, range_b) == cell && bounds_a.intersects(&bounds_b)
+                    {
+                        f(a.min(b), a.max(b));
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_range(&self, bounds: &Rect) -> CellRange {
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+        CellRange {
+            min: (cell(bounds.left()), cell(bounds.top())),
+            max: (cell(bounds.right()), cell(bounds.bottom())),
+        }
+    }
+
+    fn link(&mut self, id: usize, range: CellRange) {
+        for cell in cells(range) {
+            self.cells.entry(cell).or_default().push(id);
+        }
+    }
+
+    fn unlink(&mut self, id: usize, range: CellRange) {
+        for cell in cells(range) {
+            if let Some(ids) = self.cells.get_mut(&cell) {
+                ids.retain(|&other| other != id);
+                if ids.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+}
+
+fn cells(range: CellRange) -> impl Iterator<Item = (i32, i32)> {
+    (range.min.1..=range.max.1).flat_map(move |y| (range.min.0..=range.max.0).map(move |x| (x, y)))
+}
+
+fn first_shared_cell(a: CellRange, b: CellRange) -> (i32, i32) {
+    (a.min.0.max(b.min.0), a.min.1.max(b.min.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(hash: &SpatialHash) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        hash.pairs(|a, b| pairs.push((a, b)));
+        pairs.sort_unstable();
+        pairs
+    }
+
+    fn query(hash: &SpatialHash, area: Rect) -> Vec<usize> {
+        let mut ids = Vec::new();
+        hash.query(area, |id| ids.push(id));
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn reports_each_result_once() {
+        let mut hash = SpatialHash::new(10.0);
+        // Both span several cells, and share nine of them
+        hash.insert(0, Rect::new(0.0, 0.0, 25.0, 25.0));
+        hash.insert(1, Rect::new(5.0, 5.0, 25.0, 25.0));
+        hash.insert(2, Rect::new(100.0, 100.0, 5.0, 5.0));
+        assert_eq!(pairs(&hash), [(0, 1)]);
+        assert_eq!(query(&hash, Rect::new(-5.0, -5.0, 40.0, 40.0)), [0, 1]);
+        assert_eq!(query(&hash, Rect::new(26.0, 26.0, 2.0, 2.0)), [1]);
+
+        // Moved next to the others
+        hash.insert(2, Rect::new(20.0, 20.0, 15.0, 5.0));
+        assert_eq!(pairs(&hash), [(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(query(&hash, Rect::new(-5.0, -5.0, 40.0, 40.0)), [0, 1, 2]);
+        assert_eq!(hash.bounds(2), Some(Rect::new(20.0, 20.0, 15.0, 5.0)));
+
+        // Moved within the same cells
+        hash.insert(2, Rect::new(21.0, 21.0, 14.0, 4.0));
+        assert_eq!(pairs(&hash), [(0, 1), (0, 2), (1, 2)]);
+
+        hash.remove(0);
+        assert_eq!(pairs(&hash), [(1, 2)]);
+        assert_eq!(query(&hash, Rect::new(-5.0, -5.0, 40.0, 40.0)), [1, 2]);
+        assert_eq!(hash.bounds(0), None);
+
+        // Removing twice or something never inserted does nothing
+        hash.remove(0);
+        hash.remove(7);
+        assert_eq!(pairs(&hash), [(1, 2)]);
+    }
+
+    #[test]
+    fn sharing_cells_is_not_enough() {
+        let mut hash = SpatialHash::new(10.0);
+        hash.insert(0, Rect::new(0.0, 0.0, 2.0, 2.0));
+        hash.insert(1, Rect::new(5.0, 5.0, 2.0, 2.0));
+        assert!(pairs(&hash).is_empty());
+        assert!(query(&hash, Rect::new(3.0, 3.0, 1.0, 1.0)).is_empty());
+    }
+}
@@ -0,0 +1,725 @@
+// panpan/src/collision/mod.rs
+// Collision shapes, overlap tests with contact manifolds, and raycasts
+//
+// Shapes are in world coordinates; move them with `Shape::translate` or build
+// them from the object's position each frame. Boxes and polygons are tested
+// with the separating axis theorem, and contact points come from clipping the
+// incident edge against the reference face, as in Box2D. For many objects,
+// use `SpatialHash` to find the pairs worth testing.
+
+mod broadphase;
+
+pub use broadphase::SpatialHash;
+
+use crate::types::{Rect, Vec2};
+
+/// Penetration below this still counts as touching, keeps resting contacts
+/// from flickering
+const LINEAR_SLOP: f32 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub const fn new(center: Vec2, radius: f32) -> Self {
+        Self { center, radius }
+    }
+}
+
+/// Oriented box: a rectangle rotated by `angle` radians around its center
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Obb {
+    pub center: Vec2,
+    pub half_size: Vec2,
+    pub angle: f32,
+}
+
+impl Obb {
+    pub const fn new(center: Vec2, half_size: Vec2, angle: f32) -> Self {
+        Self {
+            center,
+            half_size,
+            angle,
+        }
+    }
+
+    pub fn corners(&self) -> [Vec2; 4] {
+        let x = Vec2::from_angle(self.angle) * self.half_size.x;
+        let y = Vec2::from_angle(self.angle).perp() * self.half_size.y;
+        let c = self.center;
+        [c - x - y, c + x - y, c + x + y, c - x + y]
+    }
+}
+
+/// Convex polygon. Points can be given in either winding order; they are
+/// stored so that `cross` of consecutive edges is positive.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Vec<Vec2>", into = "Vec<Vec2>")
+)]
+pub struct Polygon {
+    points: Vec<Vec2>,
+}
+
+impl Polygon {
+    /// `None` for fewer than three points or a polygon without area. The
+    /// points must form a convex shape; that is not checked.
+    pub fn new(points: impl Into<Vec<Vec2>>) -> Option<Self> {
+        let mut points = points.into();
+        if points.len() < 3 {
+            return None;
+        }
+        let area = signed_area(&points);
+        if area == 0.0 {
+            return None;
+        }
+        if area < 0.0 {
+            points.reverse();
+        }
+        Some(Self { points })
+    }
+
+    pub fn points(&self) -> &[Vec2] {
+        &self.points
+    }
+
+    pub fn translate(&mut self, offset: Vec2) {
+        for p in &mut self.points {
+            *p += offset;
+        }
+    }
+
+    pub fn centroid(&self) -> Vec2 {
+        let sum = self.points.iter().fold(Vec2::ZERO, |sum, p| sum + *p);
+        sum / self.points.len() as f32
+    }
+}
+
+/// Goes through `Polygon::new`, so deserialized polygons are checked too
+impl TryFrom<Vec<Vec2>> for Polygon {
+    type Error = &'static str;
+
+    fn try_from(points: Vec<Vec2>) -> Result<Self, Self::Error> {
+        Polygon::new(points).ok_or("a polygon needs at least three points and some area")
+    }
+}
+
+impl From<Polygon> for Vec<Vec2> {
+    fn from(polygon: Polygon) -> Self {
+        polygon.points
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape {
+    /// Axis-aligned box
+    Rect(Rect),
+    Circle(Circle),
+    Obb(Obb),
+    Polygon(Polygon),
+}
+
+impl Shape {
+    pub fn circle(center: Vec2, radius: f32) -> Self {
+        Shape::Circle(Circle::new(center, radius))
+    }
+
+    pub fn obb(center: Vec2, half_size: Vec2, angle: f32) -> Self {
+        Shape::Obb(Obb::new(center, half_size, angle))
+    }
+
+    /// Axis-aligned bounding box
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Shape::Rect(rect) => *rect,
+            Shape::Circle(c) => Rect::from_center(c.center, c.radius * 2.0, c.radius * 2.0),
+            Shape::Obb(obb) => Rect::from_points(obb.corners()).unwrap(),
+            Shape::Polygon(poly) => Rect::from_points(poly.points.iter().copied()).unwrap(),
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        match self {
+            Shape::Rect(rect) => rect.center(),
+            Shape::Circle(c) => c.center,
+            Shape::Obb(obb) => obb.center,
+            Shape::Polygon(poly) => poly.centroid(),
+        }
+    }
+
+    pub fn translate(&mut self, offset: Vec2) {
+        match self {
+            Shape::Rect(rect) => *rect = rect.translate(offset),
+            Shape::Circle(c) => c.center += offset,
+            Shape::Obb(obb) => obb.center += offset,
+            Shape::Polygon(poly) => poly.translate(offset),
+        }
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self {
+            Shape::Rect(rect) => rect.contains(point),
+            Shape::Circle(c) => c.center.distance_squared(point) <= c.radius * c.radius,
+            _ => {
+                let points = self.vertices();
+                let inside = edges(&points).all(|(v, _, normal)| normal.dot(point - v) <= 0.0);
+                inside
+            }
+        }
+    }
+
+    /// Corners in positive winding order; empty for circles
+    fn vertices(&self) -> Vec<Vec2> {
+        match self {
+            Shape::Rect(rect) => {
+                let (min, max) = (rect.min(), rect.max());
+                let points = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+                positive_winding(points.to_vec())
+            }
+            Shape::Circle(_) => Vec::new(),
+            Shape::Obb(obb) => positive_winding(obb.corners().to_vec()),
+            Shape::Polygon(poly) => poly.points.clone(),
+        }
+    }
+
+    /// First point where the ray from `origin` along `direction` (need not
+    /// be normalized) hits the shape within `max_distance`. A ray starting
+    /// inside hits at distance 0 with the normal pointing against it.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<RayHit> {
+        let direction = direction.normalize();
+        if direction == Vec2::ZERO {
+            return None;
+        }
+        let hit = match self {
+            Shape::Circle(c) => raycast_circle(c, origin, direction),
+            _ => raycast_polygon(&self.vertices(), origin, direction),
+        }?;
+        if hit.distance > max_distance {
+            return None;
+        }
+        Some(hit)
+    }
+}
+
+impl From<Rect> for Shape {
+    fn from(rect: Rect) -> Self {
+        Shape::Rect(rect)
+    }
+}
+
+impl From<Circle> for Shape {
+    fn from(circle: Circle) -> Self {
+        Shape::Circle(circle)
+    }
+}
+
+impl From<Obb> for Shape {
+    fn from(obb: Obb) -> Self {
+        Shape::Obb(obb)
+    }
+}
+
+impl From<Polygon> for Shape {
+    fn from(polygon: Polygon) -> Self {
+        Shape::Polygon(polygon)
+    }
+}
+
+/// How two overlapping shapes touch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Manifold {
+    /// Unit vector from the first shape towards the second. Moving the
+    /// second shape by `normal * depth` separates them.
+    pub normal: Vec2,
+    /// Deepest penetration along `normal`
+    pub depth: f32,
+    points: [Vec2; 2],
+    depths: [f32; 2],
+    count: usize,
+}
+
+impl Manifold {
+    fn new(normal: Vec2, depth: f32, points: &[Vec2]) -> Self {
+        let mut manifold = Self {
+            normal,
+            depth,
+            points: [Vec2::ZERO; 2],
+            depths: [depth; 2],
+            count: points.len().min(2),
+        };
+        manifold.points[..manifold.count].copy_from_slice(&points[..manifold.count]);
+        manifold
+    }
+
+    /// One or two contact points in world coordinates
+    pub fn points(&self) -> &[Vec2] {
+        &self.points[..self.count]
+    }
+
+    /// Penetration at each of `points`, at most `depth`
+    pub fn depths(&self) -> &[f32] {
+        &self.depths[..self.count]
+    }
+
+    /// Same contact seen from the other shape
+    pub fn flipped(mut self) -> Self {
+        self.normal = -self.normal;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub point: Vec2,
+    /// Surface normal at `point`, pointing out of the shape
+    pub normal: Vec2,
+    /// Distance from the ray origin
+    pub distance: f32,
+}
+
+/// True if the shapes overlap
+pub fn intersects(a: &Shape, b: &Shape) -> bool {
+    if let (Shape::Rect(a), Shape::Rect(b)) = (a, b) {
+        return a.intersects(b);
+    }
+    overlap(a, b).is_some()
+}
+
+/// Contact between `a` and `b`, `None` if they do not overlap
+pub fn overlap(a: &Shape, b: &Shape) -> Option<Manifold> {
+    match (a, b) {
+        (Shape::Rect(a), Shape::Rect(b)) => rect_rect(a, b),
+        (Shape::Circle(a), Shape::Circle(b)) => circle_circle(a, b),
+        (Shape::Circle(circle), other) => {
+            polygon_circle(&other.vertices(), circle).map(Manifold::flipped)
+        }
+        (other, Shape::Circle(circle)) => polygon_circle(&other.vertices(), circle),
+        _ => polygon_polygon(&a.vertices(), &b.vertices()),
+    }
+}
+
+/// Like `Rect::penetration`: on each axis the shorter way out, then the
+/// axis where that is shorter
+fn rect_rect(a: &Rect, b: &Rect) -> Option<Manifold> {
+    let overlap = a.intersection(b)?;
+    let push = |forward: f32, back: f32| {
+        if forward < back {
+            (1.0, forward)
+        } else {
+            (-1.0, back)
+        }
+    };
+    let (sign_x, depth_x) = push(a.right() - b.left(), b.right() - a.left());
+    let (sign_y, depth_y) = push(a.bottom() - b.top(), b.bottom() - a.top());
+    let (normal, depth, points) = if depth_x < depth_y {
+        let x = if sign_x < 0.0 {
+            overlap.left()
+        } else {
+            overlap.right()
+        };
+        let points = [Vec2::new(x, overlap.top()), Vec2::new(x, overlap.bottom())];
+        (Vec2::new(sign_x, 0.0), depth_x, points)
+    } else {
+        let y = if sign_y < 0.0 {
+            overlap.top()
+        } else {
+            overlap.bottom()
+        };
+        let points = [Vec2::new(overlap.left(), y), Vec2::new(overlap.right(), y)];
+        (Vec2::new(0.0, sign_y), depth_y, points)
+    };
+    Some(Manifold::new(normal, depth, &points))
+}
+
+fn circle_circle(a: &Circle, b: &Circle) -> Option<Manifold> {
+    let delta = b.center - a.center;
+    let radii = a.radius + b.radius;
+    let distance_squared = delta.length_squared();
+    if distance_squared >= radii * radii {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    // Concentric circles get pushed apart along an arbitrary axis
+    let normal = if distance > 0.0 {
+        delta / distance
+    } else {
+        Vec2::X
+    };
+    let point = a.center + normal * (a.radius - (radii - distance) * 0.5);
+    Some(Manifold::new(normal, radii - distance, &[point]))
+}
+
+/// Polygon `a` (positive winding) against circle `b`
+fn polygon_circle(a: &[Vec2], b: &Circle) -> Option<Manifold> {
+    // Edge the center is furthest outside of
+    let (index, separation) = edges(a)
+        .map(|(v, _, normal)| normal.dot(b.center - v))
+        .enumerate()
+        .fold(
+            (0, f32::MIN),
+            |best, (i, s)| if s > best.1 { (i, s) } else { best },
+        );
+    if separation > b.radius {
+        return None;
+    }
+    let (v1, v2, normal) = edges(a).nth(index).unwrap();
+
+    if separation <= 0.0 {
+        // Center inside the polygon
+        return Some(Manifold::new(
+            normal,
+            b.radius - separation,
+            &[b.center - normal * separation],
+        ));
+    }
+
+    // Closest feature: one of the edge's vertices, or the edge itself
+    let closest = if (b.center - v1).dot(v2 - v1) <= 0.0 {
+        v1
+    } else if (b.center - v2).dot(v1 - v2) <= 0.0 {
+        v2
+    } else {
+        return Some(Manifold::new(
+            normal,
+            b.radius - separation,
+            &[b.center - normal * separation],
+        ));
+    };
+    let delta = b.center - closest;
+    let distance_squared = delta.length_squared();
+    if distance_squared > b.radius * b.radius {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    Some(Manifold::new(
+        delta / distance,
+        b.radius - distance,
+        &[closest],
+    ))
+}
+
+/// Both polygons in positive winding
+fn polygon_polygon(a: &[Vec2], b: &[Vec2]) -> Option<Manifold> {
+    let (edge_a, separation_a) = max_separation(a, b);
+    if separation_a > 0.0 {
+        return None;
+    }
+    let (edge_b, separation_b) = max_separation(b, a);
+    if separation_b > 0.0 {
+        return None;
+    }
+
+    // Prefer `a` as the reference so the choice does not flip between frames
+    let flip = separation_b > separation_a + 0.1 * LINEAR_SLOP;
+    let (reference, incident, edge) = if flip { (b, a, edge_b) } else { (a, b, edge_a) };
+    let (v1, v2, normal) = edges(reference).nth(edge).unwrap();
+
+    // Edge of the incident polygon facing the reference face the most
+    let (i1, i2, _) = edges(incident)
+        .min_by(|x, y| normal.dot(x.2).total_cmp(&normal.dot(y.2)))
+        .unwrap();
+
+    // Clip it to the sides of the reference face
+    let tangent = (v2 - v1).normalize();
+    let clipped = clip(&[i1, i2], -tangent, -tangent.dot(v1))
+        .and_then(|points| clip(&points, tangent, tangent.dot(v2)))?;
+
+    let mut points = [Vec2::ZERO; 2];
+    let mut depths = [0.0; 2];
+    let mut count = 0;
+    for p in clipped {
+        let separation = normal.dot(p - v1);
+        if separation <= LINEAR_SLOP {
+            points[count] = p;
+            depths[count] = -separation;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+    let depth = depths[..count].iter().fold(0.0, |a: f32, &b| a.max(b));
+    let mut manifold = Manifold::new(normal, depth, &points[..count]);
+    manifold.depths = depths;
+    Some(if flip { manifold.flipped() } else { manifold })
+}
+
+/// The edge of `a` along which `b` sticks out the least, and by how much
+fn max_separation(a: &[Vec2], b: &[Vec2]) -> (usize, f32) {
+    edges(a)
+        .map(|(v, _, normal)| {
+            b.iter()
+                .map(|p| normal.dot(*p - v))
+                .fold(f32::MAX, f32::min)
+        })
+        .enumerate()
+        .fold(
+            (0, f32::MIN),
+            |best, (i, s)| if s > best.1 { (i, s) } else { best },
+        )
+}
+
+/// Keep the part of segment `points` where `normal.dot(p) <= offset`
+fn clip(points: &[Vec2; 2], normal: Vec2, offset: f32) -> Option<[Vec2; 2]> {
+    let d0 = normal.dot(points[0]) - offset;
+    let d1 = normal.dot(points[1]) - offset;
+    match (d0 <= 0.0, d1 <= 0.0) {
+        (true, true) => Some(*points),
+        (false, false) => None,
+        (inside0, _) => {
+            let crossing = points[0].lerp(points[1], d0 / (d0 - d1));
+            Some(if inside0 {
+                [points[0], crossing]
+            } else {
+                [crossing, points[1]]
+            })
+        }
+    }
+}
+
+fn raycast_circle(c: &Circle, origin: Vec2, direction: Vec2) -> Option<RayHit> {
+    let to_origin = origin - c.center;
+    let c_term = to_origin.length_squared() - c.radius * c.radius;
+    if c_term <= 0.0 {
+        return Some(RayHit {
+            point: origin,
+            normal: -direction,
+            distance: 0.0,
+        });
+    }
+    let b = to_origin.dot(direction);
+    let discriminant = b * b - c_term;
+    if b > 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    let point = origin + direction * distance;
+    Some(RayHit {
+        point,
+        normal: (point - c.center).normalize(),
+        distance,
+    })
+}
+
+/// Cyrus-Beck clipping of the ray against every edge
+fn raycast_polygon(points: &[Vec2], origin: Vec2, direction: Vec2) -> Option<RayHit> {
+    let mut enter = 0.0f32;
+    let mut exit = f32::MAX;
+    let mut normal = None;
+    for (v, _, n) in edges(points) {
+        let numerator = n.dot(v - origin);
+        let denominator = n.dot(direction);
+        if denominator == 0.0 {
+            if numerator < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = numerator / denominator;
+        if denominator < 0.0 {
+            if t > enter {
+                enter = t;
+                normal = Some(n);
+            }
+        } else {
+            exit = exit.min(t);
+        }
+        if exit < enter {
+            return None;
+        }
+    }
+    Some(RayHit {
+        point: origin + direction * enter,
+        normal: normal.unwrap_or(-direction),
+        distance: enter,
+    })
+}
+
+/// Each edge as (start, end, outward unit normal)
+fn edges(points: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2, Vec2)> + '_ {
+    points.iter().enumerate().map(|(i, &v1)| {
+        let v2 = points[(i + 1) % points.len()];
+        let edge = v2 - v1;
+        (v1, v2, Vec2::new(edge.y, -edge.x).normalize())
+    })
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].cross(points[(i + 1) % n]))
+        .sum::<f32>()
+        * 0.5
+}
+
+fn positive_winding(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.distance(expected) < 1e-4,
+            "{actual:?} != {expected:?}"
+        );
+    }
+
+    /// Checks `overlap` both ways round and returns the manifold for (a, b)
+    fn check(a: &Shape, b: &Shape, normal: Vec2, depth: f32, count: usize) -> Manifold {
+        let forward = overlap(a, b).expect("shapes overlap");
+        let backward = overlap(b, a).expect("shapes overlap");
+        assert_near(forward.normal, normal);
+        assert_near(backward.normal, -normal);
+        for manifold in [forward, backward] {
+            assert!((manifold.depth - depth).abs() < 1e-4, "{manifold:?}");
+            assert_eq!(manifold.points().len(), count, "{manifold:?}");
+            assert!(intersects(a, b) && intersects(b, a));
+        }
+        forward
+    }
+
+    #[test]
+    fn rect_rect_takes_the_shorter_way_out() {
+        let a = Shape::Rect(Rect::new(0.0, 0.0, 10.0, 10.0));
+        let b = Shape::Rect(Rect::new(8.0, 2.0, 10.0, 6.0));
+        check(&a, &b, Vec2::X, 2.0, 2);
+
+        // Inside, the depth is how far it has to go to get out, not the
+        // size of the overlap
+        let a = Shape::Rect(Rect::new(0.0, 0.0, 100.0, 100.0));
+        let mut b = Shape::Rect(Rect::new(40.0, 40.0, 10.0, 10.0));
+        let manifold = check(&a, &b, Vec2::new(0.0, -1.0), 50.0, 2);
+        b.translate(manifold.normal * (manifold.depth + 0.01));
+        assert!(overlap(&a, &b).is_none());
+    }
+
+    #[test]
+    fn circle_overlaps() {
+        let a = Shape::circle(Vec2::ZERO, 1.0);
+        let b = Shape::circle(Vec2::new(1.5, 0.0), 1.0);
+        check(&a, &b, Vec2::X, 0.5, 1);
+
+        let rect = Shape::Rect(Rect::new(0.0, 0.0, 10.0, 10.0));
+        let above = Shape::circle(Vec2::new(5.0, -0.5), 1.0);
+        check(&rect, &above, Vec2::new(0.0, -1.0), 0.5, 1);
+
+        let corner = Shape::circle(Vec2::new(-1.0, -1.0), 2.0);
+        let manifold = check(
+            &rect,
+            &corner,
+            Vec2::new(-1.0, -1.0).normalize(),
+            2.0 - 2f32.sqrt(),
+            1,
+        );
+        assert_near(manifold.points()[0], Vec2::ZERO);
+
+        let triangle = Shape::Polygon(
+            Polygon::new([Vec2::ZERO, Vec2::new(4.0, 0.0), Vec2::new(2.0, 3.0)]).unwrap(),
+        );
+        let below = Shape::circle(Vec2::new(2.0, -0.5), 1.0);
+        check(&triangle, &below, Vec2::new(0.0, -1.0), 0.5, 1);
+
+        assert!(overlap(&a, &Shape::circle(Vec2::new(3.0, 0.0), 1.0)).is_none());
+        assert!(overlap(&rect, &Shape::circle(Vec2::new(-2.0, -2.0), 2.0)).is_none());
+    }
+
+    #[test]
+    fn box_overlaps() {
+        // A flat face resting on another touches at two points
+        let ground = Shape::obb(Vec2::ZERO, Vec2::new(2.0, 1.0), 0.0);
+        let top = Shape::obb(Vec2::new(0.0, -1.9), Vec2::new(1.0, 1.0), 0.0);
+        let manifold = check(&ground, &top, Vec2::new(0.0, -1.0), 0.1, 2);
+        for &depth in manifold.depths() {
+            assert!((depth - 0.1).abs() < 1e-4);
+        }
+
+        // A corner touches at one
+        let rect = Shape::Rect(Rect::new(0.0, 0.0, 10.0, 10.0));
+        let tilted = Shape::obb(
+            Vec2::new(5.0, -1.9),
+            Vec2::new(2.0, 2.0),
+            std::f32::consts::FRAC_PI_4,
+        );
+        let depth = 2.0 * 2f32.sqrt() - 1.9;
+        let manifold = check(&rect, &tilted, Vec2::new(0.0, -1.0), depth, 1);
+        assert_near(manifold.points()[0], Vec2::new(5.0, depth));
+
+        let square = Polygon::new([
+            Vec2::new(9.0, 4.0),
+            Vec2::new(9.0, 6.0),
+            Vec2::new(11.0, 6.0),
+            Vec2::new(11.0, 4.0),
+        ])
+        .unwrap();
+        check(&rect, &Shape::Polygon(square), Vec2::X, 1.0, 2);
+
+        let apart = Shape::obb(Vec2::new(0.0, -3.5), Vec2::new(1.0, 1.0), 0.3);
+        assert!(overlap(&ground, &apart).is_none());
+        assert!(!intersects(&apart, &ground));
+    }
+
+    #[test]
+    fn polygon_checks_its_points() {
+        assert!(Polygon::new([Vec2::ZERO, Vec2::X]).is_none());
+        assert!(Polygon::new([Vec2::ZERO, Vec2::X, Vec2::X * 2.0]).is_none());
+        let clockwise = [Vec2::ZERO, Vec2::Y, Vec2::X];
+        let polygon = Polygon::new(clockwise).unwrap();
+        assert!(signed_area(polygon.points()) > 0.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialized_polygons_are_checked() {
+        let polygon = Polygon::new([Vec2::ZERO, Vec2::Y, Vec2::X]).unwrap();
+        let json = serde_json::to_string(&polygon).unwrap();
+        assert!(json.starts_with('['), "{json}");
+        assert_eq!(serde_json::from_str::<Polygon>(&json).unwrap(), polygon);
+
+        let line = r#"[{"x":0.0,"y":0.0},{"x":1.0,"y":0.0},{"x":2.0,"y":0.0}]"#;
+        assert!(serde_json::from_str::<Polygon>(line).is_err());
+        assert!(serde_json::from_str::<Shape>(&format!(r#"{{"Polygon":{line}}}"#)).is_err());
+    }
+
+    #[test]
+    fn raycasts() {
+        let shapes = [
+            Shape::Rect(Rect::new(-2.0, -2.0, 4.0, 4.0)),
+            Shape::obb(Vec2::ZERO, Vec2::new(2.0, 2.0), 0.0),
+            Shape::circle(Vec2::ZERO, 2.0),
+        ];
+        for shape in &shapes {
+            let hit = shape
+                .raycast(Vec2::new(-5.0, 0.0), Vec2::new(2.0, 0.0), 10.0)
+                .unwrap();
+            assert_near(hit.point, Vec2::new(-2.0, 0.0));
+            assert_near(hit.normal, Vec2::new(-1.0, 0.0));
+            assert!((hit.distance - 3.0).abs() < 1e-4);
+
+            // Too short, pointing away, passing by
+            assert!(shape.raycast(Vec2::new(-5.0, 0.0), Vec2::X, 2.5).is_none());
+            assert!(shape
+                .raycast(Vec2::new(-5.0, 0.0), -Vec2::X, 10.0)
+                .is_none());
+            assert!(shape.raycast(Vec2::new(-5.0, 3.0), Vec2::X, 10.0).is_none());
+
+            let inside = shape.raycast(Vec2::new(0.5, 0.0), Vec2::Y, 10.0).unwrap();
+            assert_near(inside.point, Vec2::new(0.5, 0.0));
+            assert_near(inside.normal, -Vec2::Y);
+            assert_eq!(inside.distance, 0.0);
+        }
+    }
+}
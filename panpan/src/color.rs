@@ -0,0 +1,528 @@
+// panpan/src/color.rs
+// RGBA colors: conversions between representations and the CSS palette
+//
+// Re-exported from `panpan::types`. Components are floats in 0..=1 in sRGB
+// space, straight (not premultiplied) alpha, which is what the renderer
+// expects.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+    pub const RED: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+    pub const GREEN: Color = Color::new(0.0, 1.0, 0.0, 1.0);
+    pub const BLUE: Color = Color::new(0.0, 0.0, 1.0, 1.0);
+    pub const YELLOW: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+    pub const CYAN: Color = Color::new(0.0, 1.0, 1.0, 1.0);
+    pub const MAGENTA: Color = Color::new(1.0, 0.0, 1.0, 1.0);
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Opaque color
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.0)
+    }
+
+    pub const fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::new(
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        )
+    }
+
+    /// Opaque color from `0xRRGGBB`
+    pub const fn from_rgb_u32(rgb: u32) -> Self {
+        Self::from_rgba8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255)
+    }
+
+    /// Components rounded to bytes, clamped to 0..=255
+    pub fn to_rgba8(self) -> [u8; 4] {
+        let byte = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [byte(self.r), byte(self.g), byte(self.b), byte(self.a)]
+    }
+
+    /// Parse `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`; the `#` is optional
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // from_str_radix alone would also take a sign, e.g. "+f+f+f"
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+        let (r, g, b, a) = match hex.len() {
+            3 | 4 => {
+                // Each digit is doubled: "f80" is "ff8800"
+                let short = |i| digit(i, 1).map(|v| v * 17);
+                let a = if hex.len() == 4 { short(3)? } else { 255 };
+                (short(0)?, short(1)?, short(2)?, a)
+            }
+            6 | 8 => {
+                let a = if hex.len() == 8 { digit(6, 2)? } else { 255 };
+                (digit(0, 2)?, digit(2, 2)?, digit(4, 2)?, a)
+            }
+            _ => return None,
+        };
+        Some(Self::from_rgba8(r, g, b, a))
+    }
+
+    /// `#rrggbb`, or `#rrggbbaa` if the color is not opaque
+    pub fn to_hex(self) -> String {
+        let [r, g, b, a] = self.to_rgba8();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    /// Opaque color from hue in degrees, saturation and value in 0..=1
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let c = v * s;
+        let (r, g, b) = hue_to_rgb(h, c);
+        let m = v - c;
+        Self::rgb(r + m, g + m, b + m)
+    }
+
+    /// Hue in degrees (0..360), saturation and value
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (max, min, hue) = self.hue();
+        let s = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (hue, s, max)
+    }
+
+    /// Opaque color from hue in degrees, saturation and lightness in 0..=1
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let (r, g, b) = hue_to_rgb(h, c);
+        let m = l - c * 0.5;
+        Self::rgb(r + m, g + m, b + m)
+    }
+
+    /// Hue in degrees (0..360), saturation and lightness
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (max, min, hue) = self.hue();
+        let l = (max + min) * 0.5;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        (hue, s, l)
+    }
+
+    /// Largest and smallest component and the hue in degrees
+    fn hue(self) -> (f32, f32, f32) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let d = max - min;
+        let hue = if d == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / d).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / d + 2.0
+        } else {
+            (self.r - self.g) / d + 4.0
+        };
+        (max, min, hue * 60.0)
+    }
+
+    /// Convert sRGB components to linear light, e.g. before blending or
+    /// lighting math. Alpha is unchanged.
+    pub fn to_linear(self) -> Self {
+        let f = |c: f32| {
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        Self::new(f(self.r), f(self.g), f(self.b), self.a)
+    }
+
+    /// Inverse of `to_linear`
+    pub fn to_srgb(self) -> Self {
+        let f = |c: f32| {
+            if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        Self::new(f(self.r), f(self.g), f(self.b), self.a)
+    }
+
+    /// Component-wise blend, `t` = 0 gives `self` and 1 gives `other`
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    pub const fn with_alpha(self, a: f32) -> Self {
+        Self::new(self.r, self.g, self.b, a)
+    }
+
+    /// Multiply the color components by alpha
+    pub fn premultiply(self) -> Self {
+        Self::new(self.r * self.a, self.g * self.a, self.b * self.a, self.a)
+    }
+
+    /// Inverse of `premultiply`; fully transparent colors stay black
+    pub fn unpremultiply(self) -> Self {
+        if self.a == 0.0 {
+            return Color::TRANSPARENT;
+        }
+        Self::new(self.r / self.a, self.g / self.a, self.b / self.a, self.a)
+    }
+
+    /// CSS color name such as `"cornflowerblue"`, case-insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        css::NAMED
+            .iter()
+            .find(|(css, _)| css.eq_ignore_ascii_case(name))
+            .map(|&(_, color)| color)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Color::new(r, g, b, a)
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(c: Color) -> Self {
+        [c.r, c.g, c.b, c.a]
+    }
+}
+
+// Human-readable formats get a hex string, `"#ff8800"`, rounded to 8 bits
+// per channel; CSS names are accepted when reading. Binary formats store the
+// four floats.
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::Color;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for Color {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                serializer.serialize_str(&self.to_hex())
+            } else {
+                [self.r, self.g, self.b, self.a].serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Color {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let s = String::deserialize(deserializer)?;
+                Color::from_hex(&s)
+                    .or_else(|| Color::from_name(&s))
+                    .ok_or_else(|| D::Error::custom(format!("invalid color {:?}", s)))
+            } else {
+                <[f32; 4]>::deserialize(deserializer).map(Color::from)
+            }
+        }
+    }
+}
+
+/// r, g, b before adding the lightness offset, for chroma `c`
+fn hue_to_rgb(h: f32, c: f32) -> (f32, f32, f32) {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    }
+}
+
+macro_rules! palette {
+    ($($name:ident = $css:literal $rgb:literal,)*) => {
+        $(pub const $name: Color = Color::from_rgb_u32($rgb);)*
+
+        /// Every color with its CSS name, for `Color::from_name`
+        pub const NAMED: &[(&str, Color)] = &[$(($css, $name)),*];
+    };
+}
+
+/// The CSS named colors. Note that CSS `GREEN` is a darker green than
+/// `Color::GREEN`, which is CSS `LIME`.
+pub mod css {
+    use super::Color;
+
+    palette! {
+        ALICE_BLUE = "aliceblue" 0xf0f8ff,
+        ANTIQUE_WHITE = "antiquewhite" 0xfaebd7,
+        AQUA = "aqua" 0x00ffff,
+        AQUAMARINE = "aquamarine" 0x7fffd4,
+        AZURE = "azure" 0xf0ffff,
+        BEIGE = "beige" 0xf5f5dc,
+        BISQUE = "bisque" 0xffe4c4,
+        BLACK = "black" 0x000000,
+        BLANCHED_ALMOND = "blanchedalmond" 0xffebcd,
+        BLUE = "blue" 0x0000ff,
+        BLUE_VIOLET = "blueviolet" 0x8a2be2,
+        BROWN = "brown" 0xa52a2a,
+        BURLYWOOD = "burlywood" 0xdeb887,
+        CADET_BLUE = "cadetblue" 0x5f9ea0,
+        CHARTREUSE = "chartreuse" 0x7fff00,
+        CHOCOLATE = "chocolate" 0xd2691e,
+        CORAL = "coral" 0xff7f50,
+        CORNFLOWER_BLUE = "cornflowerblue" 0x6495ed,
+        CORNSILK = "cornsilk" 0xfff8dc,
+        CRIMSON = "crimson" 0xdc143c,
+        CYAN = "cyan" 0x00ffff,
+        DARK_BLUE = "darkblue" 0x00008b,
+        DARK_CYAN = "darkcyan" 0x008b8b,
+        DARK_GOLDENROD = "darkgoldenrod" 0xb8860b,
+        DARK_GRAY = "darkgray" 0xa9a9a9,
+        DARK_GREEN = "darkgreen" 0x006400,
+        DARK_GREY = "darkgrey" 0xa9a9a9,
+        DARK_KHAKI = "darkkhaki" 0xbdb76b,
+        DARK_MAGENTA = "darkmagenta" 0x8b008b,
+        DARK_OLIVE_GREEN = "darkolivegreen" 0x556b2f,
+        DARK_ORANGE = "darkorange" 0xff8c00,
+        DARK_ORCHID = "darkorchid" 0x9932cc,
+        DARK_RED = "darkred" 0x8b0000,
+        DARK_SALMON = "darksalmon" 0xe9967a,
+        DARK_SEA_GREEN = "darkseagreen" 0x8fbc8f,
+        DARK_SLATE_BLUE = "darkslateblue" 0x483d8b,
+        DARK_SLATE_GRAY = "darkslategray" 0x2f4f4f,
+        DARK_SLATE_GREY = "darkslategrey" 0x2f4f4f,
+        DARK_TURQUOISE = "darkturquoise" 0x00ced1,
+        DARK_VIOLET = "darkviolet" 0x9400d3,
+        DEEP_PINK = "deeppink" 0xff1493,
+        DEEP_SKY_BLUE = "deepskyblue" 0x00bfff,
+        DIM_GRAY = "dimgray" 0x696969,
+        DIM_GREY = "dimgrey" 0x696969,
+        DODGER_BLUE = "dodgerblue" 0x1e90ff,
+        FIREBRICK = "firebrick" 0xb22222,
+        FLORAL_WHITE = "floralwhite" 0xfffaf0,
+        FOREST_GREEN = "forestgreen" 0x228b22,
+        FUCHSIA = "fuchsia" 0xff00ff,
+        GAINSBORO = "gainsboro" 0xdcdcdc,
+        GHOST_WHITE = "ghostwhite" 0xf8f8ff,
+        GOLD = "gold" 0xffd700,
+        GOLDENROD = "goldenrod" 0xdaa520,
+        GRAY = "gray" 0x808080,
+        GREEN = "green" 0x008000,
+        GREEN_YELLOW = "greenyellow" 0xadff2f,
+        GREY = "grey" 0x808080,
+        HONEYDEW = "honeydew" 0xf0fff0,
+        HOT_PINK = "hotpink" 0xff69b4,
+        INDIAN_RED = "indianred" 0xcd5c5c,
+        INDIGO = "indigo" 0x4b0082,
+        IVORY = "ivory" 0xfffff0,
+        KHAKI = "khaki" 0xf0e68c,
+        LAVENDER = "lavender" 0xe6e6fa,
+        LAVENDER_BLUSH = "lavenderblush" 0xfff0f5,
+        LAWN_GREEN = "lawngreen" 0x7cfc00,
+        LEMON_CHIFFON = "lemonchiffon" 0xfffacd,
+        LIGHT_BLUE = "lightblue" 0xadd8e6,
+        LIGHT_CORAL = "lightcoral" 0xf08080,
+        LIGHT_CYAN = "lightcyan" 0xe0ffff,
+        LIGHT_GOLDENROD_YELLOW = "lightgoldenrodyellow" 0xfafad2,
+        LIGHT_GRAY = "lightgray" 0xd3d3d3,
+        LIGHT_GREEN = "lightgreen" 0x90ee90,
+        LIGHT_GREY = "lightgrey" 0xd3d3d3,
+        LIGHT_PINK = "lightpink" 0xffb6c1,
+        LIGHT_SALMON = "lightsalmon" 0xffa07a,
+        LIGHT_SEA_GREEN = "lightseagreen" 0x20b2aa,
+        LIGHT_SKY_BLUE = "lightskyblue" 0x87cefa,
+        LIGHT_SLATE_GRAY = "lightslategray" 0x778899,
+        LIGHT_SLATE_GREY = "lightslategrey" 0x778899,
+        LIGHT_STEEL_BLUE = "lightsteelblue" 0xb0c4de,
+        LIGHT_YELLOW = "lightyellow" 0xffffe0,
+        LIME = "lime" 0x00ff00,
+        LIME_GREEN = "limegreen" 0x32cd32,
+        LINEN = "linen" 0xfaf0e6,
+        MAGENTA = "magenta" 0xff00ff,
+        MAROON = "maroon" 0x800000,
+        MEDIUM_AQUAMARINE = "mediumaquamarine" 0x66cdaa,
+        MEDIUM_BLUE = "mediumblue" 0x0000cd,
+        MEDIUM_ORCHID = "mediumorchid" 0xba55d3,
+        MEDIUM_PURPLE = "mediumpurple" 0x9370db,
+        MEDIUM_SEA_GREEN = "mediumseagreen" 0x3cb371,
+        MEDIUM_SLATE_BLUE = "mediumslateblue" 0x7b68ee,
+        MEDIUM_SPRING_GREEN = "mediumspringgreen" 0x00fa9a,
+        MEDIUM_TURQUOISE = "mediumturquoise" 0x48d1cc,
+        MEDIUM_VIOLET_RED = "mediumvioletred" 0xc71585,
+        MIDNIGHT_BLUE = "midnightblue" 0x191970,
+        MINT_CREAM = "mintcream" 0xf5fffa,
+        MISTY_ROSE = "mistyrose" 0xffe4e1,
+        MOCCASIN = "moccasin" 0xffe4b5,
+        NAVAJO_WHITE = "navajowhite" 0xffdead,
+        NAVY = "navy" 0x000080,
+        OLD_LACE = "oldlace" 0xfdf5e6,
+        OLIVE = "olive" 0x808000,
+        OLIVE_DRAB = "olivedrab" 0x6b8e23,
+        ORANGE = "orange" 0xffa500,
+        ORANGE_RED = "orangered" 0xff4500,
+        ORCHID = "orchid" 0xda70d6,
+        PALE_GOLDENROD = "palegoldenrod" 0xeee8aa,
+        PALE_GREEN = "palegreen" 0x98fb98,
+        PALE_TURQUOISE = "paleturquoise" 0xafeeee,
+        PALE_VIOLET_RED = "palevioletred" 0xdb7093,
+        PAPAYA_WHIP = "papayawhip" 0xffefd5,
+        PEACH_PUFF = "peachpuff" 0xffdab9,
+        PERU = "peru" 0xcd853f,
+        PINK = "pink" 0xffc0cb,
+        PLUM = "plum" 0xdda0dd,
+        POWDER_BLUE = "powderblue" 0xb0e0e6,
+        PURPLE = "purple" 0x800080,
+        REBECCA_PURPLE = "rebeccapurple" 0x663399,
+        RED = "red" 0xff0000,
+        ROSY_BROWN = "rosybrown" 0xbc8f8f,
+        ROYAL_BLUE = "royalblue" 0x4169e1,
+        SADDLE_BROWN = "saddlebrown" 0x8b4513,
+        SALMON = "salmon" 0xfa8072,
+        SANDY_BROWN = "sandybrown" 0xf4a460,
+        SEA_GREEN = "seagreen" 0x2e8b57,
+        SEASHELL = "seashell" 0xfff5ee,
+        SIENNA = "sienna" 0xa0522d,
+        SILVER = "silver" 0xc0c0c0,
+        SKY_BLUE = "skyblue" 0x87ceeb,
+        SLATE_BLUE = "slateblue" 0x6a5acd,
+        SLATE_GRAY = "slategray" 0x708090,
+        SLATE_GREY = "slategrey" 0x708090,
+        SNOW = "snow" 0xfffafa,
+        SPRING_GREEN = "springgreen" 0x00ff7f,
+        STEEL_BLUE = "steelblue" 0x4682b4,
+        TAN = "tan" 0xd2b48c,
+        TEAL = "teal" 0x008080,
+        THISTLE = "thistle" 0xd8bfd8,
+        TOMATO = "tomato" 0xff6347,
+        TURQUOISE = "turquoise" 0x40e0d0,
+        VIOLET = "violet" 0xee82ee,
+        WHEAT = "wheat" 0xf5deb3,
+        WHITE = "white" 0xffffff,
+        WHITE_SMOKE = "whitesmoke" 0xf5f5f5,
+        YELLOW = "yellow" 0xffff00,
+        YELLOW_GREEN = "yellowgreen" 0x9acd32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex_lengths() {
+        assert_eq!(
+            Color::from_hex("#f80"),
+            Some(Color::from_rgba8(255, 136, 0, 255))
+        );
+        assert_eq!(
+            Color::from_hex("f808"),
+            Some(Color::from_rgba8(255, 136, 0, 136))
+        );
+        assert_eq!(
+            Color::from_hex("#FF8800"),
+            Some(Color::from_rgba8(255, 136, 0, 255))
+        );
+        assert_eq!(
+            Color::from_hex("ff880080"),
+            Some(Color::from_rgba8(255, 136, 0, 128))
+        );
+    }
+
+    #[test]
+    fn from_hex_rejects_non_digits() {
+        for bad in [
+            "",
+            "#",
+            "ff",
+            "fff88",
+            "#fffffffff",
+            "#gg0000",
+            "+f+f+f",
+            "-f0",
+            "#ff 000",
+            "ffé0",
+        ] {
+            assert_eq!(Color::from_hex(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let color = Color::from_rgba8(18, 52, 86, 120);
+        assert_eq!(color.to_hex(), "#12345678");
+        assert_eq!(Color::from_hex(&color.to_hex()), Some(color));
+        assert_eq!(Color::from_rgb_u32(0x123456).to_hex(), "#123456");
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn json_uses_hex() {
+        let color = Color::from_rgba8(255, 136, 0, 255);
+        let json = serde_json::to_string(&color).unwrap();
+        assert_eq!(json, r##""#ff8800""##);
+        assert_eq!(serde_json::from_str::<Color>(&json).unwrap(), color);
+
+        let translucent = Color::from_rgba8(1, 2, 3, 4);
+        let json = serde_json::to_string(&translucent).unwrap();
+        assert_eq!(json, r##""#01020304""##);
+        assert_eq!(serde_json::from_str::<Color>(&json).unwrap(), translucent);
+    }
+
+    #[test]
+    fn json_reads_css_names() {
+        let color: Color = serde_json::from_str(r#""CornflowerBlue""#).unwrap();
+        assert_eq!(color, css::CORNFLOWER_BLUE);
+    }
+
+    #[test]
+    fn json_rejects_bad_strings() {
+        for bad in [
+            r#""notacolor""#,
+            r#""+f+f+f""#,
+            r##""#12""##,
+            "[1, 0, 0, 1]",
+        ] {
+            assert!(serde_json::from_str::<Color>(bad).is_err(), "{bad}");
+        }
+        let error = serde_json::from_str::<Color>(r#""notacolor""#).unwrap_err();
+        assert!(error.to_string().contains("invalid color"), "{error}");
+    }
+
+    #[test]
+    fn binary_keeps_the_floats() {
+        // Not representable in 8 bits per channel
+        let color = Color::new(0.1, 0.2, 0.3, 0.4);
+        let bytes = bincode::serialize(&color).unwrap();
+        assert_eq!(
+            bincode::deserialize::<[f32; 4]>(&bytes).unwrap(),
+            [0.1, 0.2, 0.3, 0.4]
+        );
+        assert_eq!(bincode::deserialize::<Color>(&bytes).unwrap(), color);
+    }
+}
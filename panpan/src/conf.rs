@@ -0,0 +1,76 @@
+// panpan/src/conf.rs
+// Window and app settings chosen by the game, honored by every runner
+
+use crate::window::CoordinateSpace;
+
+/// Returned from `Game::conf` before the window is created.
+///
+/// Settings that make no sense on a platform are ignored there: the title,
+/// size, resizing, icon and MSAA only apply on desktop, and the orientation
+/// lock only on Android.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conf {
+    pub title: String,
+    /// Initial window size, in logical pixels when `high_dpi` is set
+    pub width: u32,
+    pub height: u32,
+    pub resizable: bool,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Multisample anti-aliasing samples, 0 to disable
+    pub msaa_samples: u8,
+    /// Size the window in logical pixels and render at the native
+    /// resolution. When off, sizes are taken as physical pixels.
+    pub high_dpi: bool,
+    /// Units the game draws and receives input in
+    pub coordinate_space: CoordinateSpace,
+    pub icon: Option<Icon>,
+    pub orientation: Orientation,
+    /// Cap the frame rate, `None` to run as fast as vsync allows
+    pub target_fps: Option<u32>,
+}
+
+impl Conf {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Conf {
+    fn default() -> Self {
+        Self {
+            title: "PanPan Game".to_string(),
+            width: 800,
+            height: 600,
+            resizable: true,
+            fullscreen: false,
+            vsync: true,
+            msaa_samples: 4,
+            high_dpi: true,
+            coordinate_space: CoordinateSpace::Logical,
+            icon: None,
+            orientation: Orientation::Any,
+            target_fps: None,
+        }
+    }
+}
+
+/// Window icon as raw RGBA8 pixels, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Screen orientation lock on mobile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    #[default]
+    Any,
+    Portrait,
+    Landscape,
+}
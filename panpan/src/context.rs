@@ -0,0 +1,254 @@
+// panpan/src/context.rs
+// Engine state owned by one game: renderer, input state, timing and the
+// window state shared with the runner
+//
+// Each runner creates one `Context` and passes it to `Game::new`, `update`
+// and `render`, so several engines can live in one process (e.g. headless
+// replays in parallel tests). There is no audio backend yet; it will live
+// here too once there is one.
+
+use crate::graphics::Graphics;
+use crate::input::{InputEvent, InputState};
+use crate::platform::{Platform, PlatformRequest};
+use crate::time::Time;
+use crate::types::Rect;
+use crate::window::{CoordinateSpace, WindowMode};
+
+#[derive(Default)]
+pub struct Context {
+    graphics: Graphics,
+    input: InputState,
+    time: Time,
+    platform: Platform,
+}
+
+impl Context {
+    /// A context without graphics, for headless replays and tests. Runners
+    /// attach the renderer with `__internal_init_graphics`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn graphics(&mut self) -> &mut Graphics {
+        &mut self.graphics
+    }
+
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    /// Length of the current update step in seconds
+    pub fn delta_time(&self) -> f32 {
+        self.time.delta()
+    }
+
+    /// Simulated seconds since the game started, the sum of all update steps
+    pub fn time(&self) -> f64 {
+        self.time.elapsed()
+    }
+
+    /// Frames rendered so far
+    pub fn frame_count(&self) -> u64 {
+        self.time.frame_count()
+    }
+
+    /// FPS, smoothed frame time and per-phase timings
+    pub fn timing(&self) -> &Time {
+        &self.time
+    }
+
+    /// Physical pixels per logical point of the display the game is on
+    pub fn scale_factor(&self) -> f32 {
+        self.platform.with(|state| state.scale_factor())
+    }
+
+    pub fn coordinate_space(&self) -> CoordinateSpace {
+        self.platform.with(|state| state.coordinate_space())
+    }
+
+    /// Physical pixels per game coordinate unit, see `window::pixel_ratio`
+    pub fn pixel_ratio(&self) -> f32 {
+        self.platform.with(|state| state.pixel_ratio())
+    }
+
+    /// Start receiving `Game::on_text_input` and IME composition events.
+    /// Shows the soft keyboard on touch devices.
+    pub fn start_text_input(&mut self) {
+        self.request(PlatformRequest::StartTextInput);
+    }
+
+    /// Stop receiving text events and hide the soft keyboard
+    pub fn stop_text_input(&mut self) {
+        self.request(PlatformRequest::StopTextInput);
+    }
+
+    pub fn is_text_input_active(&self) -> bool {
+        self.platform.with(|state| state.is_text_input_active())
+    }
+
+    /// Hint where the edited text is on screen, so the IME candidate window
+    /// can be placed next to it instead of covering it
+    pub fn set_text_input_area(&mut self, area: Rect) {
+        self.request(PlatformRequest::SetTextInputArea(area));
+    }
+
+    pub fn set_window_mode(&mut self, mode: WindowMode) {
+        self.request(PlatformRequest::SetWindowMode(mode));
+    }
+
+    /// Toggle borderless fullscreen
+    pub fn set_fullscreen(&mut self, fullscreen: bool) {
+        self.set_window_mode(WindowMode::fullscreen(fullscreen));
+    }
+
+    /// Wait for the display refresh before presenting. Desktop only.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.request(PlatformRequest::SetVsync(vsync));
+    }
+
+    /// Cap the frame rate, `None` to run as fast as vsync allows
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        self.request(PlatformRequest::SetTargetFps(fps));
+    }
+
+    /// Resize the window, in the same units as `Conf::width`. Desktop only.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.request(PlatformRequest::SetWindowSize(width, height));
+    }
+
+    /// Queue a request for the runner, which carries it out after the
+    /// current frame
+    fn request(&mut self, request: PlatformRequest) {
+        self.platform.with_mut(|state| state.push(request));
+    }
+
+    /// Make this context the one the free functions (`draw_rect`,
+    /// `start_text_input`, `set_fullscreen` and friends) use on this thread
+    /// while `f` runs. `GameLoop` does this around every update and render
+    /// and `dispatch_event` around input; runners use it for `Game::new`
+    /// and the lifecycle callbacks.
+    pub fn scope<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let lent_graphics = self.graphics.lend();
+        let lent_platform = self.platform.lend();
+        let result = f(self);
+        if lent_platform {
+            self.platform.reclaim();
+        }
+        if lent_graphics {
+            self.graphics.reclaim();
+        }
+        result
+    }
+
+    /// Internal: run `f` with the renderer taken back from the free drawing
+    /// functions, for hot reload to hand it to the game library's own copy
+    #[doc(hidden)]
+    pub fn __internal_unscoped<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let lent_graphics = self.graphics.is_lent();
+        let lent_platform = self.platform.is_lent();
+        if lent_platform {
+            self.platform.reclaim();
+        }
+        if lent_graphics {
+            self.graphics.reclaim();
+        }
+        let result = f(self);
+        if lent_graphics {
+            self.graphics.lend();
+        }
+        if lent_platform {
+            self.platform.lend();
+        }
+        result
+    }
+
+    pub(crate) fn handle_event(&mut self, event: &InputEvent) {
+        self.input.handle_event(event);
+    }
+
+    pub(crate) fn time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    pub(crate) fn init_graphics(&mut self, gl: glow::Context) {
+        self.graphics.init(gl);
+        self.graphics.set_pixel_ratio(self.pixel_ratio());
+    }
+
+    pub(crate) fn set_scale_factor(&mut self, scale: f32) {
+        self.platform.with_mut(|state| state.set_scale_factor(scale));
+        self.graphics.set_pixel_ratio(self.pixel_ratio());
+    }
+
+    pub(crate) fn set_coordinate_space(&mut self, space: CoordinateSpace) {
+        self.platform.with_mut(|state| state.set_coordinate_space(space));
+        self.graphics.set_pixel_ratio(self.pixel_ratio());
+    }
+
+    pub(crate) fn take_platform_requests(&mut self) -> Vec<PlatformRequest> {
+        self.platform.with_mut(|state| state.take())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::PlatformRequest;
+    use crate::{input, window};
+
+    #[test]
+    fn contexts_keep_their_own_platform_state() {
+        let mut a = Context::new();
+        let mut b = Context::new();
+        a.set_scale_factor(2.0);
+        b.set_coordinate_space(CoordinateSpace::Physical);
+
+        a.scope(|a| {
+            input::start_text_input();
+            assert_eq!(window::pixel_ratio(), 2.0);
+            // A nested context is current until its scope ends
+            b.scope(|_| {
+                assert_eq!(window::pixel_ratio(), 1.0);
+                assert!(!input::is_text_input_active());
+                window::set_vsync(false);
+            });
+            assert!(a.is_text_input_active());
+        });
+
+        assert!(a.is_text_input_active());
+        assert!(!b.is_text_input_active());
+        assert_eq!(a.take_platform_requests(), [PlatformRequest::StartTextInput]);
+        assert_eq!(b.take_platform_requests(), [PlatformRequest::SetVsync(false)]);
+
+        // Outside of any context the free functions do nothing
+        input::start_text_input();
+        assert!(!input::is_text_input_active());
+        assert!(a.take_platform_requests().is_empty());
+    }
+
+    #[test]
+    fn methods_and_free_functions_queue_the_same_requests() {
+        let mut ctx = Context::new();
+        ctx.start_text_input();
+        ctx.set_fullscreen(true);
+        ctx.scope(|ctx| {
+            // Lent to the free functions, which share the queue
+            ctx.set_target_fps(Some(30));
+            window::set_window_size(640, 480);
+            input::stop_text_input();
+            assert!(!ctx.is_text_input_active());
+        });
+        ctx.set_text_input_area(Rect::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(
+            ctx.take_platform_requests(),
+            [
+                PlatformRequest::StartTextInput,
+                PlatformRequest::SetWindowMode(WindowMode::BorderlessFullscreen),
+                PlatformRequest::SetTargetFps(Some(30)),
+                PlatformRequest::SetWindowSize(640, 480),
+                PlatformRequest::StopTextInput,
+                PlatformRequest::SetTextInputArea(Rect::new(1.0, 2.0, 3.0, 4.0)),
+            ]
+        );
+    }
+}
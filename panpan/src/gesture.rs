@@ -0,0 +1,645 @@
+// panpan/src/gesture.rs
+// Gesture recognition on top of the raw touch stream
+
+use crate::input::{Touch, TouchPhase};
+use crate::types::Vec2;
+use std::collections::VecDeque;
+
+/// Thresholds used by the recognizer. Distances are in logical pixels,
+/// durations in seconds and velocities in logical pixels per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// Maximum distance a finger may travel and still count as a tap
+    pub tap_max_distance: f32,
+    /// Maximum time a finger may stay down and still count as a tap
+    pub tap_max_duration: f32,
+    /// Maximum time between two taps to report a double tap
+    pub double_tap_interval: f32,
+    /// Maximum distance between two taps to report a double tap
+    pub double_tap_max_distance: f32,
+    /// Time a finger must stay still before a long press is reported
+    pub long_press_duration: f32,
+    /// Distance a finger must travel before a pan starts
+    pub pan_min_distance: f32,
+    /// Minimum travel distance for a swipe
+    pub swipe_min_distance: f32,
+    /// Minimum average velocity for a swipe
+    pub swipe_min_velocity: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_max_distance: 10.0,
+            tap_max_duration: 0.3,
+            double_tap_interval: 0.3,
+            double_tap_max_distance: 40.0,
+            long_press_duration: 0.5,
+            pan_min_distance: 10.0,
+            swipe_min_distance: 50.0,
+            swipe_min_velocity: 400.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A recognized gesture. Continuous gestures (pan, pinch, rotate) reuse
+/// `TouchPhase` to report when they start, change and end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    Tap {
+        position: Vec2,
+    },
+    /// Reported right after the `Tap` that completes it
+    DoubleTap {
+        position: Vec2,
+    },
+    LongPress {
+        position: Vec2,
+    },
+    Swipe {
+        direction: SwipeDirection,
+        start: Vec2,
+        end: Vec2,
+        velocity: Vec2,
+    },
+    Pan {
+        phase: TouchPhase,
+        position: Vec2,
+        delta: Vec2,
+    },
+    /// `scale` is relative to the finger distance when the pinch started
+    Pinch {
+        phase: TouchPhase,
+        center: Vec2,
+        scale: f32,
+    },
+    /// `angle` is in radians, relative to the finger angle when the rotation
+    /// started. It keeps counting past a half turn in either direction.
+    Rotate {
+        phase: TouchPhase,
+        center: Vec2,
+        angle: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TrackedTouch {
+    id: i32,
+    start: Vec2,
+    position: Vec2,
+    start_time: f32,
+    /// Moved further than the tap distance at some point
+    moved: bool,
+    long_pressed: bool,
+    panning: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TwoFinger {
+    ids: (i32, i32),
+    start_distance: f32,
+    /// Angle between the fingers at the last update
+    last_angle: f32,
+    /// Rotation since the start, summed up move by move
+    angle: f32,
+}
+
+impl TwoFinger {
+    /// Center, scale and rotation for the fingers now at `a` and `b`
+    fn track(&mut self, a: Vec2, b: Vec2) -> (Vec2, f32, f32) {
+        // Summing the small steps keeps the angle going past half a turn
+        // instead of jumping from +pi to -pi
+        let finger_angle = (b - a).angle();
+        self.angle += wrap_angle(finger_angle - self.last_angle);
+        self.last_angle = finger_angle;
+        (
+            a.lerp(b, 0.5),
+            a.distance(b) / self.start_distance,
+            self.angle,
+        )
+    }
+}
+
+/// Turns raw touch events into high level gestures.
+///
+/// Feed it from `Game::on_touch_*`, call `update` once per frame so long
+/// presses can be detected, then drain the recognized gestures with `poll`.
+#[derive(Debug, Clone, Default)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    time: f32,
+    touches: Vec<TrackedTouch>,
+    two_finger: Option<TwoFinger>,
+    /// Set once a second finger joins, cleared when all fingers are lifted.
+    /// Suppresses single finger gestures for the rest of the interaction.
+    multi_touch: bool,
+    last_tap: Option<(f32, Vec2)>,
+    gestures: VecDeque<Gesture>,
+}
+
+impl GestureRecognizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: GestureConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut GestureConfig {
+        &mut self.config
+    }
+
+    /// Feed a raw touch event
+    pub fn handle_touch(&mut self, touch: Touch) {
+        match touch.phase {
+            TouchPhase::Started => self.touch_down(touch.id, touch.x, touch.y),
+            TouchPhase::Moved => self.touch_move(touch.id, touch.x, touch.y),
+            TouchPhase::Ended => self.touch_up(touch.id),
+        }
+    }
+
+    pub fn touch_down(&mut self, id: i32, x: f32, y: f32) {
+        let position = Vec2::new(x, y);
+        self.touches.retain(|t| t.id != id);
+        self.touches.push(TrackedTouch {
+            id,
+            start: position,
+            position,
+            start_time: self.time,
+            moved: false,
+            long_pressed: false,
+            panning: false,
+        });
+
+        if self.touches.len() == 2 && !self.multi_touch {
+            // A second finger turns the interaction into a two finger gesture
+            self.multi_touch = true;
+            let first = self.touches[0];
+            if first.panning {
+                self.gestures.push_back(Gesture::Pan {
+                    phase: TouchPhase::Ended,
+                    position: first.position,
+                    delta: Vec2::new(0.0, 0.0),
+                });
+            }
+            self.begin_two_finger();
+        }
+    }
+
+    pub fn touch_move(&mut self, id: i32, x: f32, y: f32) {
+        let config = self.config;
+        let multi_touch = self.multi_touch;
+        let Some(touch) = self.touches.iter_mut().find(|t| t.id == id) else {
+            return;
+        };
+
+        let previous = touch.position;
+        touch.position = Vec2::new(x, y);
+        let travelled = touch.start.distance(touch.position);
+        if travelled > config.tap_max_distance {
+            touch.moved = true;
+        }
+
+        if multi_touch {
+            self.update_two_finger(TouchPhase::Moved);
+            return;
+        }
+
+        if !touch.panning && !touch.long_pressed && travelled > config.pan_min_distance {
+            touch.panning = true;
+            let delta = touch.position - touch.start;
+            let position = touch.position;
+            self.gestures.push_back(Gesture::Pan {
+                phase: TouchPhase::Started,
+                position,
+                delta,
+            });
+        } else if touch.panning {
+            let delta = touch.position - previous;
+            let position = touch.position;
+            self.gestures.push_back(Gesture::Pan {
+                phase: TouchPhase::Moved,
+                position,
+                delta,
+            });
+        }
+    }
+
+    pub fn touch_up(&mut self, id: i32) {
+        let Some(index) = self.touches.iter().position(|t| t.id == id) else {
+            return;
+        };
+        let touch = self.touches.remove(index);
+
+        if self.multi_touch {
+            if let Some(two) = self.two_finger {
+                if two.ids.0 == id || two.ids.1 == id {
+                    self.end_two_finger(touch.position);
+                }
+            }
+            if self.touches.is_empty() {
+                self.multi_touch = false;
+            }
+            return;
+        }
+
+        if touch.panning {
+            self.gestures.push_back(Gesture::Pan {
+                phase: TouchPhase::Ended,
+                position: touch.position,
+                delta: Vec2::new(0.0, 0.0),
+            });
+        }
+
+        let duration = self.time - touch.start_time;
+        let travelled = touch.start.distance(touch.position);
+
+        if touch.long_pressed {
+            return;
+        }
+
+        if !touch.moved && duration <= self.config.tap_max_duration {
+            self.recognize_tap(touch.position);
+            return;
+        }
+
+        if travelled >= self.config.swipe_min_distance {
+            let elapsed = duration.max(1.0 / 240.0);
+            let offset = touch.position - touch.start;
+            let velocity = Vec2::new(offset.x / elapsed, offset.y / elapsed);
+            if travelled / elapsed >= self.config.swipe_min_velocity {
+                let direction = if offset.x.abs() >= offset.y.abs() {
+                    if offset.x > 0.0 {
+                        SwipeDirection::Right
+                    } else {
+                        SwipeDirection::Left
+                    }
+                } else if offset.y > 0.0 {
+                    SwipeDirection::Down
+                } else {
+                    SwipeDirection::Up
+                };
+                self.gestures.push_back(Gesture::Swipe {
+                    direction,
+                    start: touch.start,
+                    end: touch.position,
+                    velocity,
+                });
+            }
+        }
+    }
+
+    /// Advance the recognizer clock. Needed for long press and for the
+    /// duration based thresholds.
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+
+        if self.multi_touch {
+            return;
+        }
+
+        for touch in &mut self.touches {
+            if !touch.moved
+                && !touch.long_pressed
+                && self.time - touch.start_time >= self.config.long_press_duration
+            {
+                touch.long_pressed = true;
+                self.gestures.push_back(Gesture::LongPress {
+                    position: touch.position,
+                });
+            }
+        }
+    }
+
+    /// Take the next recognized gesture
+    pub fn poll(&mut self) -> Option<Gesture> {
+        self.gestures.pop_front()
+    }
+
+    /// Take all recognized gestures
+    pub fn drain(&mut self) -> impl Iterator<Item = Gesture> + '_ {
+        self.gestures.drain(..)
+    }
+
+    /// Forget all tracked touches, e.g. when the app loses focus
+    pub fn reset(&mut self) {
+        self.touches.clear();
+        self.two_finger = None;
+        self.multi_touch = false;
+        self.last_tap = None;
+        self.gestures.clear();
+    }
+
+    fn recognize_tap(&mut self, position: Vec2) {
+        self.gestures.push_back(Gesture::Tap { position });
+
+        match self.last_tap {
+            Some((time, last))
+                if self.time - time <= self.config.double_tap_interval
+                    && last.distance(position) <= self.config.double_tap_max_distance =>
+            {
+                self.gestures.push_back(Gesture::DoubleTap { position });
+                // A third tap starts a new sequence instead of a second double tap
+                self.last_tap = None;
+            }
+            _ => self.last_tap = Some((self.time, position)),
+        }
+    }
+
+    fn begin_two_finger(&mut self) {
+        let (a, b) = (self.touches[0], self.touches[1]);
+        let start_distance = a.position.distance(b.position).max(1.0);
+        self.two_finger = Some(TwoFinger {
+            ids: (a.id, b.id),
+            start_distance,
+            last_angle: (b.position - a.position).angle(),
+            angle: 0.0,
+        });
+        self.update_two_finger(TouchPhase::Started);
+    }
+
+    fn update_two_finger(&mut self, phase: TouchPhase) {
+        let Some(two) = &mut self.two_finger else {
+            return;
+        };
+        let a = self.touches.iter().find(|t| t.id == two.ids.0);
+        let b = self.touches.iter().find(|t| t.id == two.ids.1);
+        let (Some(a), Some(b)) = (a, b) else {
+            return;
+        };
+        let (center, scale, angle) = two.track(a.position, b.position);
+
+        self.gestures.push_back(Gesture::Pinch {
+            phase,
+            center,
+            scale,
+        });
+        self.gestures.push_back(Gesture::Rotate {
+            phase,
+            center,
+            angle,
+        });
+    }
+
+    fn end_two_finger(&mut self, lifted: Vec2) {
+        let Some(mut two) = self.two_finger.take() else {
+            return;
+        };
+        // The lifted finger is already gone, so use its last position
+        let position = |id| {
+            self.touches
+                .iter()
+                .find(|t| t.id == id)
+                .map(|t| t.position)
+                .unwrap_or(lifted)
+        };
+        let (a, b) = (position(two.ids.0), position(two.ids.1));
+        let (center, scale, angle) = two.track(a, b);
+
+        self.gestures.push_back(Gesture::Pinch {
+            phase: TouchPhase::Ended,
+            center,
+            scale,
+        });
+        self.gestures.push_back(Gesture::Rotate {
+            phase: TouchPhase::Ended,
+            center,
+            angle,
+        });
+    }
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    use std::f32::consts::{PI, TAU};
+    let wrapped = (angle + PI).rem_euclid(TAU) - PI;
+    if wrapped <= -PI {
+        wrapped + TAU
+    } else {
+        wrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn gestures(recognizer: &mut GestureRecognizer) -> Vec<Gesture> {
+        recognizer.drain().collect()
+    }
+
+    fn tap(recognizer: &mut GestureRecognizer, x: f32, y: f32) {
+        recognizer.touch_down(0, x, y);
+        recognizer.update(0.05);
+        recognizer.touch_up(0);
+    }
+
+    #[test]
+    fn taps_and_double_taps() {
+        let mut recognizer = GestureRecognizer::new();
+        tap(&mut recognizer, 10.0, 10.0);
+        let first = Vec2::new(10.0, 10.0);
+        assert_eq!(
+            gestures(&mut recognizer),
+            [Gesture::Tap { position: first }]
+        );
+
+        recognizer.update(0.1);
+        tap(&mut recognizer, 20.0, 10.0);
+        let second = Vec2::new(20.0, 10.0);
+        assert_eq!(
+            gestures(&mut recognizer),
+            [
+                Gesture::Tap { position: second },
+                Gesture::DoubleTap { position: second },
+            ]
+        );
+
+        // A third tap starts over, and a late one does not pair up
+        tap(&mut recognizer, 20.0, 10.0);
+        recognizer.update(0.5);
+        tap(&mut recognizer, 20.0, 10.0);
+        assert_eq!(
+            gestures(&mut recognizer),
+            [
+                Gesture::Tap { position: second },
+                Gesture::Tap { position: second }
+            ]
+        );
+
+        // Held too long or moved too far is not a tap
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.update(0.4);
+        recognizer.touch_up(0);
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.touch_move(0, 12.0, 0.0);
+        recognizer.touch_move(0, 0.0, 0.0);
+        recognizer.touch_up(0);
+        assert!(!gestures(&mut recognizer)
+            .iter()
+            .any(|g| matches!(g, Gesture::Tap { .. })));
+    }
+
+    #[test]
+    fn long_press() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.touch_down(0, 5.0, 5.0);
+        recognizer.update(0.3);
+        assert!(gestures(&mut recognizer).is_empty());
+        recognizer.update(0.3);
+        recognizer.update(0.3);
+        let position = Vec2::new(5.0, 5.0);
+        assert_eq!(gestures(&mut recognizer), [Gesture::LongPress { position }]);
+        recognizer.touch_up(0);
+        assert!(gestures(&mut recognizer).is_empty());
+    }
+
+    #[test]
+    fn pans_after_the_threshold() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.touch_move(0, 5.0, 0.0);
+        assert!(gestures(&mut recognizer).is_empty());
+
+        recognizer.touch_move(0, 15.0, 0.0);
+        recognizer.touch_move(0, 20.0, 5.0);
+        recognizer.update(1.0);
+        recognizer.touch_up(0);
+        assert_eq!(
+            gestures(&mut recognizer),
+            [
+                Gesture::Pan {
+                    phase: TouchPhase::Started,
+                    position: Vec2::new(15.0, 0.0),
+                    delta: Vec2::new(15.0, 0.0),
+                },
+                Gesture::Pan {
+                    phase: TouchPhase::Moved,
+                    position: Vec2::new(20.0, 5.0),
+                    delta: Vec2::new(5.0, 5.0),
+                },
+                Gesture::Pan {
+                    phase: TouchPhase::Ended,
+                    position: Vec2::new(20.0, 5.0),
+                    delta: Vec2::ZERO,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn swipes() {
+        let mut recognizer = GestureRecognizer::new();
+        let cases = [
+            (Vec2::new(100.0, 10.0), SwipeDirection::Right),
+            (Vec2::new(-100.0, 10.0), SwipeDirection::Left),
+            (Vec2::new(10.0, -100.0), SwipeDirection::Up),
+            (Vec2::new(10.0, 100.0), SwipeDirection::Down),
+        ];
+        for (offset, direction) in cases {
+            recognizer.touch_down(0, 0.0, 0.0);
+            recognizer.update(0.1);
+            recognizer.touch_move(0, offset.x, offset.y);
+            recognizer.touch_up(0);
+            let swipe = gestures(&mut recognizer).into_iter().last();
+            let Some(Gesture::Swipe {
+                direction: found,
+                start,
+                end,
+                velocity,
+            }) = swipe
+            else {
+                panic!("no swipe for {offset:?}");
+            };
+            assert_eq!(found, direction);
+            assert_eq!((start, end), (Vec2::ZERO, offset));
+            assert!(velocity.distance(offset * 10.0) < 0.1, "{velocity:?}");
+        }
+
+        // Too slow
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.update(1.0);
+        recognizer.touch_move(0, 100.0, 0.0);
+        recognizer.touch_up(0);
+        // Too short
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.update(0.05);
+        recognizer.touch_move(0, 40.0, 0.0);
+        recognizer.touch_up(0);
+        assert!(!gestures(&mut recognizer)
+            .iter()
+            .any(|g| matches!(g, Gesture::Swipe { .. })));
+    }
+
+    #[test]
+    fn pinch_scale() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.touch_down(1, 100.0, 0.0);
+        recognizer.touch_move(1, 200.0, 0.0);
+        recognizer.touch_up(1);
+        recognizer.touch_up(0);
+        let pinches: Vec<_> = gestures(&mut recognizer)
+            .into_iter()
+            .filter_map(|g| match g {
+                Gesture::Pinch {
+                    phase,
+                    center,
+                    scale,
+                } => Some((phase, center, scale)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            pinches,
+            [
+                (TouchPhase::Started, Vec2::new(50.0, 0.0), 1.0),
+                (TouchPhase::Moved, Vec2::new(100.0, 0.0), 2.0),
+                (TouchPhase::Ended, Vec2::new(100.0, 0.0), 2.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotation_keeps_counting_past_half_a_turn() {
+        let mut recognizer = GestureRecognizer::new();
+        recognizer.touch_down(0, 0.0, 0.0);
+        recognizer.touch_down(1, 100.0, 0.0);
+        // Three quarters of a turn, in 30 degree steps
+        for step in 1..=9 {
+            let angle = step as f32 * PI / 6.0;
+            recognizer.touch_move(1, 100.0 * angle.cos(), 100.0 * angle.sin());
+        }
+        recognizer.touch_up(0);
+        let angles: Vec<(TouchPhase, f32)> = gestures(&mut recognizer)
+            .into_iter()
+            .filter_map(|g| match g {
+                Gesture::Rotate { phase, angle, .. } => Some((phase, angle)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(angles.len(), 11);
+        for (i, &(_, angle)) in angles[..10].iter().enumerate() {
+            assert!((angle - i as f32 * PI / 6.0).abs() < 1e-4, "{angles:?}");
+        }
+        let (phase, angle) = angles[10];
+        assert_eq!(phase, TouchPhase::Ended);
+        assert!((angle - 1.5 * PI).abs() < 1e-4, "{angle}");
+    }
+}
@@ -0,0 +1,535 @@
+// panpan/src/graphics/mod.rs - Cross-platform graphics (GLES2 compatible)
+mod resources;
+
+use crate::types::{Color, Mat3, Rect, Vec2, Vec3};
+use glow::HasContext;
+use resources::{ProgramId, Resources, TextureId, VertexArrayId, VertexAttrib};
+use std::cell::RefCell;
+
+thread_local! {
+    /// Renderer of the context that is currently updating or rendering on
+    /// this thread, used by the free drawing functions
+    static CURRENT: RefCell<Option<Renderer>> = const { RefCell::new(None) };
+}
+
+struct Renderer {
+    gl: glow::Context,
+    width: i32,
+    height: i32,
+    /// Bumped every time the GL context is replaced
+    generation: u32,
+    /// Physical pixels per game unit, from the owning context
+    pixel_ratio: f32,
+    /// Added to every drawing position, in game units
+    offset: (f32, f32),
+    resources: Resources,
+    rect_program: ProgramId,
+    texture_program: ProgramId,
+    rect_vao: VertexArrayId,
+}
+
+/// How a texture is sampled when drawn larger or smaller than its pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    /// Sharp pixels, for pixel art
+    #[default]
+    Nearest,
+    /// Smooth, for photos and high resolution art
+    Linear,
+}
+
+/// An image uploaded with `Graphics::create_texture`. Cheap to copy; the
+/// pixels are also kept in memory so they survive a lost GL context, until
+/// `Graphics::delete_texture` frees both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Texture {
+    /// `None` if created without a GL context; drawing it does nothing
+    id: Option<TextureId>,
+    width: u32,
+    height: u32,
+}
+
+impl Texture {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Size in pixels
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width as f32, self.height as f32)
+    }
+}
+
+/// Drawing API of a `Context`. Without a GL context (headless replays)
+/// every call does nothing.
+#[derive(Default)]
+pub struct Graphics {
+    renderer: Option<Renderer>,
+    /// The renderer is lent to `CURRENT` while the context is current
+    lent: bool,
+    /// What `CURRENT` held before lending, put back afterwards
+    outer: Option<Renderer>,
+}
+
+impl Graphics {
+    /// Initialize graphics backend (called by runner).
+    ///
+    /// Calling it again means the previous context is gone (Android destroys
+    /// it in the background), so every tracked resource is rebuilt in the
+    /// new one.
+    pub(crate) fn init(&mut self, gl: glow::Context) {
+        self.with_slot(|slot| {
+            *slot = Some(match slot.take() {
+                Some(old) => old.restore(gl),
+                None => Renderer::new(gl),
+            })
+        });
+    }
+
+    /// Set viewport (called by runner on resize)
+    pub(crate) fn set_viewport(&mut self, width: i32, height: i32) {
+        self.with_renderer(|renderer| renderer.set_viewport(width, height));
+    }
+
+    pub(crate) fn set_pixel_ratio(&mut self, ratio: f32) {
+        self.with_renderer(|renderer| renderer.pixel_ratio = ratio);
+    }
+
+    /// How many times the GL context has been recreated since startup. Games
+    /// that issue their own GL calls can watch this to rebuild their objects.
+    pub fn context_generation(&mut self) -> u32 {
+        self.with_renderer(|renderer| renderer.generation)
+            .unwrap_or(0)
+    }
+
+    /// Size of the screen in game units (see `CoordinateSpace`)
+    pub fn screen_size(&mut self) -> (f32, f32) {
+        self.with_renderer(|renderer| renderer.screen_size())
+            .unwrap_or((0.0, 0.0))
+    }
+
+    /// Shift everything drawn afterwards by `(x, y)`, e.g. to slide a whole
+    /// scene. Stays in effect until changed.
+    pub fn set_offset(&mut self, x: f32, y: f32) {
+        self.with_renderer(|renderer| renderer.offset = (x, y));
+    }
+
+    pub fn offset(&mut self) -> (f32, f32) {
+        self.with_renderer(|renderer| renderer.offset)
+            .unwrap_or((0.0, 0.0))
+    }
+
+    /// Clear the screen with a color
+    pub fn clear_screen(&mut self, color: Color) {
+        self.with_renderer(|renderer| renderer.clear(color));
+    }
+
+    /// Draw a filled rectangle
+    pub fn draw_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.with_renderer(|renderer| renderer.draw_rect_impl(x, y, width, height, color));
+    }
+
+    /// Draw a line `thickness` wide from `(x1, y1)` to `(x2, y2)`
+    pub fn draw_line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
+        self.with_renderer(|renderer| {
+            renderer.draw_line(Vec2::new(x1, y1), Vec2::new(x2, y2), thickness, color)
+        });
+    }
+
+    /// Upload `rgba` pixels (4 bytes each, rows top to bottom) as a texture.
+    /// Panics if `rgba` is not `width * height * 4` bytes.
+    pub fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+        filter: FilterMode,
+    ) -> Texture {
+        assert_eq!(
+            rgba.len(),
+            width as usize * height as usize * 4,
+            "texture data does not match its size"
+        );
+        let id =
+            self.with_renderer(|renderer| renderer.create_texture(width, height, rgba, filter));
+        Texture { id, width, height }
+    }
+
+    /// Free the texture's GL object and the pixels kept for it. Drawing it,
+    /// or any copy of it, afterwards does nothing.
+    pub fn delete_texture(&mut self, texture: Texture) {
+        let Some(id) = texture.id else { return };
+        self.with_renderer(|renderer| renderer.resources.delete_texture(&renderer.gl, id));
+    }
+
+    /// Draw the whole texture at its size in pixels, top-left corner at
+    /// `(x, y)`, multiplied by `tint` (`Color::WHITE` leaves it unchanged)
+    pub fn draw_texture(&mut self, texture: &Texture, x: f32, y: f32, tint: Color) {
+        let size = texture.size();
+        let source = Rect::new(0.0, 0.0, size.x, size.y);
+        self.draw_texture_region(texture, source, Rect::new(x, y, size.x, size.y), tint);
+    }
+
+    /// Draw the `source` rectangle of the texture, in pixels, stretched over
+    /// `dest`. A negative `dest` width or height mirrors the image.
+    pub fn draw_texture_region(
+        &mut self,
+        texture: &Texture,
+        source: Rect,
+        dest: Rect,
+        tint: Color,
+    ) {
+        self.with_renderer(|renderer| renderer.draw_texture(texture, source, dest, tint));
+    }
+
+    /// Draw a circle (approximated with triangles)
+    pub fn draw_circle(&mut self, x: f32, y: f32, radius: f32, color: Color) {
+        self.with_renderer(|renderer| renderer.draw_circle(x, y, radius, color));
+    }
+
+    /// Draw text (simple bitmap font)
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32, color: Color) {
+        self.with_renderer(|renderer| renderer.draw_text(text, x, y, size, color));
+    }
+
+    /// Hand the renderer to the free drawing functions on this thread.
+    /// Returns false if it already was.
+    pub(crate) fn lend(&mut self) -> bool {
+        if self.lent {
+            return false;
+        }
+        let renderer = self.renderer.take();
+        self.outer = CURRENT.with(|current| current.replace(renderer));
+        self.lent = true;
+        true
+    }
+
+    pub(crate) fn is_lent(&self) -> bool {
+        self.lent
+    }
+
+    /// Take the renderer back after `lend`
+    pub(crate) fn reclaim(&mut self) {
+        let outer = self.outer.take();
+        self.renderer = CURRENT.with(|current| current.replace(outer));
+        self.lent = false;
+    }
+
+    fn with_slot<R>(&mut self, f: impl FnOnce(&mut Option<Renderer>) -> R) -> R {
+        if self.lent {
+            CURRENT.with(|current| f(&mut current.borrow_mut()))
+        } else {
+            f(&mut self.renderer)
+        }
+    }
+
+    fn with_renderer<R>(&mut self, f: impl FnOnce(&mut Renderer) -> R) -> Option<R> {
+        self.with_slot(|slot| slot.as_mut().map(f))
+    }
+}
+
+/// Run `f` with the current context's renderer, if there is one
+fn with_current(f: impl FnOnce(&mut Renderer)) {
+    CURRENT.with(|current| {
+        if let Some(renderer) = current.borrow_mut().as_mut() {
+            f(renderer);
+        }
+    });
+}
+
+// The free functions below are kept for code written before `Context`. They
+// draw with the context that is currently updating or rendering on this
+// thread and do nothing outside of that.
+
+/// How many times the GL context has been recreated since startup
+pub fn context_generation() -> u32 {
+    let mut generation = 0;
+    with_current(|renderer| generation = renderer.generation);
+    generation
+}
+
+/// Clear the screen with a color
+pub fn clear_screen(color: Color) {
+    with_current(|renderer| renderer.clear(color));
+}
+
+/// Draw a filled rectangle
+pub fn draw_rect(x: f32, y: f32, width: f32, height: f32, color: Color) {
+    with_current(|renderer| renderer.draw_rect_impl(x, y, width, height, color));
+}
+
+/// Draw a line `thickness` wide from `(x1, y1)` to `(x2, y2)`
+pub fn draw_line(x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
+    with_current(|renderer| {
+        renderer.draw_line(Vec2::new(x1, y1), Vec2::new(x2, y2), thickness, color)
+    });
+}
+
+/// Draw a circle (approximated with triangles)
+pub fn draw_circle(x: f32, y: f32, radius: f32, color: Color) {
+    with_current(|renderer| renderer.draw_circle(x, y, radius, color));
+}
+
+/// Draw text (simple bitmap font)
+pub fn draw_text(text: &str, x: f32, y: f32, size: f32, color: Color) {
+    with_current(|renderer| renderer.draw_text(text, x, y, size, color));
+}
+
+impl Renderer {
+    fn new(gl: glow::Context) -> Self {
+        // Vertex shader - GLES 2.0 compatible (no version directive = max compatibility)
+        let vs_src = r#"
+attribute vec2 aPos;
+uniform mat4 projection;
+void main() {
+    gl_Position = projection * vec4(aPos, 0.0, 1.0);
+}
+"#;
+
+        // Fragment shader - GLES 2.0 compatible
+        let fs_src = r#"
+precision mediump float;
+uniform vec4 color;
+void main() {
+    gl_FragColor = color;
+}
+"#;
+
+        // Same quad, colored by a region of a texture
+        let texture_vs_src = r#"
+attribute vec2 aPos;
+uniform mat4 projection;
+uniform vec4 uvRect;
+varying vec2 vUv;
+void main() {
+    vUv = uvRect.xy + aPos * uvRect.zw;
+    gl_Position = projection * vec4(aPos, 0.0, 1.0);
+}
+"#;
+
+        let texture_fs_src = r#"
+precision mediump float;
+uniform sampler2D tex;
+uniform vec4 color;
+varying vec2 vUv;
+void main() {
+    gl_FragColor = texture2D(tex, vUv) * color;
+}
+"#;
+
+        let mut resources = Resources::default();
+        let rect_program = resources.create_program(&gl, vs_src, fs_src, &[(0, "aPos")]);
+        let texture_program =
+            resources.create_program(&gl, texture_vs_src, texture_fs_src, &[(0, "aPos")]);
+
+        // Unit square vertices (will be transformed by model matrix)
+        let vertices: [f32; 12] = [0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0];
+        let bytes = vertices.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        let rect_vbo = resources.create_buffer(&gl, glow::ARRAY_BUFFER, bytes, glow::STATIC_DRAW);
+        let rect_vao = resources.create_vertex_array(
+            &gl,
+            rect_vbo,
+            &[VertexAttrib {
+                location: 0,
+                components: 2,
+                stride: 8,
+                offset: 0,
+            }],
+        );
+
+        Self {
+            gl,
+            width: 800,
+            height: 600,
+            generation: 0,
+            pixel_ratio: 1.0,
+            offset: (0.0, 0.0),
+            resources,
+            rect_program,
+            texture_program,
+            rect_vao,
+        }
+    }
+
+    /// Move everything over to a freshly created context
+    fn restore(mut self, gl: glow::Context) -> Self {
+        self.resources.restore(&gl);
+        self.gl = gl;
+        self.generation += 1;
+        unsafe {
+            self.gl.viewport(0, 0, self.width, self.height);
+        }
+        self
+    }
+
+    fn set_viewport(&mut self, width: i32, height: i32) {
+        unsafe {
+            self.gl.viewport(0, 0, width, height);
+        }
+        self.width = width;
+        self.height = height;
+    }
+
+    fn screen_size(&self) -> (f32, f32) {
+        let ratio = self.pixel_ratio;
+        (self.width as f32 / ratio, self.height as f32 / ratio)
+    }
+
+    fn clear(&self, color: Color) {
+        unsafe {
+            self.gl.clear_color(color.r, color.g, color.b, color.a);
+            self.gl
+                .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    fn draw_circle(&self, x: f32, y: f32, radius: f32, color: Color) {
+        // Simple implementation using draw_rect for now
+        self.draw_rect_impl(x - radius, y - radius, radius * 2.0, radius * 2.0, color);
+    }
+
+    fn draw_text(&self, text: &str, x: f32, y: f32, size: f32, color: Color) {
+        let char_width = size * 0.6;
+        for (i, _ch) in text.chars().enumerate() {
+            let char_x = x + (i as f32) * char_width;
+            self.draw_rect_impl(char_x, y, char_width * 0.8, size, color);
+        }
+    }
+
+    fn draw_rect_impl(&self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        let model = Mat3::from_translation(Vec2::new(x, y)) * Mat3::from_scale(Vec2::new(w, h));
+        self.draw_quad(model, color);
+    }
+
+    fn draw_line(&self, from: Vec2, to: Vec2, thickness: f32, color: Color) {
+        let delta = to - from;
+        // The unit quad stretched along the line, centered on it
+        let model = Mat3::from_translation(from)
+            * Mat3::from_angle(delta.angle())
+            * Mat3::from_translation(Vec2::new(0.0, -thickness * 0.5))
+            * Mat3::from_scale(Vec2::new(delta.length(), thickness));
+        self.draw_quad(model, color);
+    }
+
+    fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+        filter: FilterMode,
+    ) -> TextureId {
+        let filter = match filter {
+            FilterMode::Nearest => glow::NEAREST,
+            FilterMode::Linear => glow::LINEAR,
+        };
+        self.resources
+            .create_texture(&self.gl, width as i32, height as i32, rgba, filter)
+    }
+
+    fn draw_texture(&self, texture: &Texture, source: Rect, dest: Rect, tint: Color) {
+        let Some(native) = texture.id.and_then(|id| self.resources.texture(id)) else {
+            return;
+        };
+        let model = Mat3::from_translation(Vec2::new(dest.x, dest.y))
+            * Mat3::from_scale(Vec2::new(dest.width, dest.height));
+        let size = texture.size();
+        let uv = [
+            source.x / size.x,
+            source.y / size.y,
+            source.width / size.x,
+            source.height / size.y,
+        ];
+        self.draw_unit_quad(self.texture_program, model, tint, Some((native, uv)));
+    }
+
+    /// Draw the unit quad transformed by `model` (game units)
+    fn draw_quad(&self, model: Mat3, color: Color) {
+        self.draw_unit_quad(self.rect_program, model, color, None);
+    }
+
+    /// Draw the unit quad with `program`, sampling `texture` over the `uv`
+    /// rectangle (x, y, width, height) if given
+    fn draw_unit_quad(
+        &self,
+        program: ProgramId,
+        model: Mat3,
+        color: Color,
+        texture: Option<(glow::NativeTexture, [f32; 4])>,
+    ) {
+        let (Some(program), Some(vao)) = (
+            self.resources.program(program),
+            self.resources.vertex_array(self.rect_vao),
+        ) else {
+            return;
+        };
+
+        unsafe {
+            self.gl.use_program(Some(program));
+
+            if let Some((native, [u, v, width, height])) = texture {
+                self.gl.active_texture(glow::TEXTURE0);
+                self.gl.bind_texture(glow::TEXTURE_2D, Some(native));
+                let tex_loc = self.gl.get_uniform_location(program, "tex");
+                self.gl.uniform_1_i32(tex_loc.as_ref(), 0);
+                let uv_loc = self.gl.get_uniform_location(program, "uvRect");
+                self.gl.uniform_4_f32(uv_loc.as_ref(), u, v, width, height);
+            }
+
+            // Unit quad -> game units -> clip space
+            let offset = Mat3::from_translation(Vec2::new(self.offset.0, self.offset.1));
+            let mvp = mat3_to_mat4(&(self.ortho_matrix() * offset * model));
+
+            // Set uniforms
+            let proj_loc = self.gl.get_uniform_location(program, "projection");
+            self.gl
+                .uniform_matrix_4_f32_slice(proj_loc.as_ref(), false, &mvp);
+
+            let color_loc = self.gl.get_uniform_location(program, "color");
+            self.gl
+                .uniform_4_f32(color_loc.as_ref(), color.r, color.g, color.b, color.a);
+
+            // Enable blending
+            self.gl.enable(glow::BLEND);
+            self.gl
+                .blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+
+            // Draw
+            self.gl.bind_vertex_array(Some(vao));
+            self.gl.draw_arrays(glow::TRIANGLES, 0, 6);
+            self.gl.bind_vertex_array(None);
+            if texture.is_some() {
+                self.gl.bind_texture(glow::TEXTURE_2D, None);
+            }
+
+            self.gl.disable(glow::BLEND);
+        }
+    }
+
+    fn ortho_matrix(&self) -> Mat3 {
+        // The viewport covers every physical pixel; the projection maps game
+        // coordinates onto it, so logical units still draw at full resolution
+        let (right, bottom) = self.screen_size();
+        Mat3::from_cols(
+            Vec3::new(2.0 / right, 0.0, 0.0),
+            Vec3::new(0.0, -2.0 / bottom, 0.0),
+            Vec3::new(-1.0, 1.0, 1.0),
+        )
+    }
+}
+
+/// Embed a 2D transform in the column-major 4x4 layout of a GL `mat4`
+fn mat3_to_mat4(m: &Mat3) -> [f32; 16] {
+    let (x, y, z) = (m.x_axis, m.y_axis, m.z_axis);
+    #[rustfmt::skip]
+    let matrix = [
+        x.x, x.y, 0.0, x.z,
+        y.x, y.y, 0.0, y.z,
+        0.0, 0.0, 1.0, 0.0,
+        z.x, z.y, 0.0, z.z,
+    ];
+    matrix
+}
@@ -0,0 +1,329 @@
+// panpan/src/graphics/resources.rs
+// GPU resources tracked together with their CPU-side data
+//
+// Android destroys the EGL context when the app goes to the background, which
+// invalidates every GL object. Everything the renderer creates goes through
+// `Resources`, which keeps what is needed to build the object again, so the
+// whole set can be recreated in a fresh context with `restore`.
+
+use glow::HasContext;
+
+/// Handle to a shader program. Stays valid across context loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ProgramId(usize);
+
+/// Handle to a GPU buffer. Stays valid across context loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct BufferId(usize);
+
+/// Handle to a vertex array. Stays valid across context loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct VertexArrayId(usize);
+
+/// Handle to a texture. Stays valid across context loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct TextureId(usize);
+
+/// One float vertex attribute read from a buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct VertexAttrib {
+    pub location: u32,
+    pub components: i32,
+    pub stride: i32,
+    pub offset: i32,
+}
+
+#[derive(Debug, Clone)]
+enum Desc {
+    Program {
+        vertex: String,
+        fragment: String,
+        /// Attribute locations bound before linking
+        attributes: Vec<(u32, String)>,
+    },
+    Buffer {
+        target: u32,
+        data: Vec<u8>,
+        usage: u32,
+    },
+    VertexArray {
+        buffer: BufferId,
+        attributes: Vec<VertexAttrib>,
+    },
+    Texture {
+        width: i32,
+        height: i32,
+        /// RGBA, 4 bytes per pixel, rows top to bottom
+        pixels: Vec<u8>,
+        /// `glow::NEAREST` or `glow::LINEAR`
+        filter: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Native {
+    Program(glow::NativeProgram),
+    Buffer(glow::NativeBuffer),
+    VertexArray(glow::NativeVertexArray),
+    Texture(glow::NativeTexture),
+}
+
+#[derive(Debug)]
+struct Entry {
+    desc: Desc,
+    native: Native,
+}
+
+/// Registry of every GL object the renderer owns
+#[derive(Debug, Default)]
+pub(crate) struct Resources {
+    /// Indexed by handle. Creation order is kept, so objects that depend on
+    /// others (vertex arrays on buffers) are always rebuilt after them.
+    entries: Vec<Option<Entry>>,
+}
+
+impl Resources {
+    pub fn create_program(
+        &mut self,
+        gl: &glow::Context,
+        vertex: &str,
+        fragment: &str,
+        attributes: &[(u32, &str)],
+    ) -> ProgramId {
+        let desc = Desc::Program {
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(location, name)| (*location, name.to_string()))
+                .collect(),
+        };
+        ProgramId(self.insert(gl, desc))
+    }
+
+    pub fn create_buffer(
+        &mut self,
+        gl: &glow::Context,
+        target: u32,
+        data: Vec<u8>,
+        usage: u32,
+    ) -> BufferId {
+        BufferId(self.insert(
+            gl,
+            Desc::Buffer {
+                target,
+                data,
+                usage,
+            },
+        ))
+    }
+
+    pub fn create_vertex_array(
+        &mut self,
+        gl: &glow::Context,
+        buffer: BufferId,
+        attributes: &[VertexAttrib],
+    ) -> VertexArrayId {
+        VertexArrayId(self.insert(
+            gl,
+            Desc::VertexArray {
+                buffer,
+                attributes: attributes.to_vec(),
+            },
+        ))
+    }
+
+    pub fn create_texture(
+        &mut self,
+        gl: &glow::Context,
+        width: i32,
+        height: i32,
+        pixels: Vec<u8>,
+        filter: u32,
+    ) -> TextureId {
+        TextureId(self.insert(
+            gl,
+            Desc::Texture {
+                width,
+                height,
+                pixels,
+                filter,
+            },
+        ))
+    }
+
+    /// Delete the texture and forget its pixels. The handle is not reused,
+    /// so stale copies of it find nothing.
+    pub fn delete_texture(&mut self, gl: &glow::Context, id: TextureId) {
+        let Some(entry) = self.entries.get_mut(id.0).and_then(Option::take) else {
+            return;
+        };
+        if let Native::Texture(texture) = entry.native {
+            unsafe { gl.delete_texture(texture) };
+        }
+    }
+
+    pub fn program(&self, id: ProgramId) -> Option<glow::NativeProgram> {
+        match self.native(id.0)? {
+            Native::Program(program) => Some(program),
+            _ => None,
+        }
+    }
+
+    pub fn buffer(&self, id: BufferId) -> Option<glow::NativeBuffer> {
+        match self.native(id.0)? {
+            Native::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    pub fn vertex_array(&self, id: VertexArrayId) -> Option<glow::NativeVertexArray> {
+        match self.native(id.0)? {
+            Native::VertexArray(vao) => Some(vao),
+            _ => None,
+        }
+    }
+
+    pub fn texture(&self, id: TextureId) -> Option<glow::NativeTexture> {
+        match self.native(id.0)? {
+            Native::Texture(texture) => Some(texture),
+            _ => None,
+        }
+    }
+
+    /// Rebuild every object in a new context. The old GL names belong to the
+    /// lost context and are simply forgotten, never deleted.
+    pub fn restore(&mut self, gl: &glow::Context) {
+        for index in 0..self.entries.len() {
+            let Some(desc) = self.entries[index].as_ref().map(|e| e.desc.clone()) else {
+                continue;
+            };
+            let native = self.build(gl, &desc);
+            self.entries[index] = Some(Entry { desc, native });
+        }
+    }
+
+    fn insert(&mut self, gl: &glow::Context, desc: Desc) -> usize {
+        let native = self.build(gl, &desc);
+        self.entries.push(Some(Entry { desc, native }));
+        self.entries.len() - 1
+    }
+
+    fn native(&self, index: usize) -> Option<Native> {
+        self.entries.get(index)?.as_ref().map(|e| e.native)
+    }
+
+    fn build(&self, gl: &glow::Context, desc: &Desc) -> Native {
+        unsafe {
+            match desc {
+                Desc::Program {
+                    vertex,
+                    fragment,
+                    attributes,
+                } => Native::Program(link_program(gl, vertex, fragment, attributes)),
+                Desc::Buffer {
+                    target,
+                    data,
+                    usage,
+                } => {
+                    let buffer = gl.create_buffer().expect("Cannot create buffer");
+                    gl.bind_buffer(*target, Some(buffer));
+                    gl.buffer_data_u8_slice(*target, data, *usage);
+                    gl.bind_buffer(*target, None);
+                    Native::Buffer(buffer)
+                }
+                Desc::VertexArray { buffer, attributes } => {
+                    let vao = gl.create_vertex_array().expect("Cannot create VAO");
+                    gl.bind_vertex_array(Some(vao));
+                    gl.bind_buffer(glow::ARRAY_BUFFER, self.buffer(*buffer));
+                    for attrib in attributes {
+                        gl.enable_vertex_attrib_array(attrib.location);
+                        gl.vertex_attrib_pointer_f32(
+                            attrib.location,
+                            attrib.components,
+                            glow::FLOAT,
+                            false,
+                            attrib.stride,
+                            attrib.offset,
+                        );
+                    }
+                    gl.bind_vertex_array(None);
+                    gl.bind_buffer(glow::ARRAY_BUFFER, None);
+                    Native::VertexArray(vao)
+                }
+                Desc::Texture {
+                    width,
+                    height,
+                    pixels,
+                    filter,
+                } => {
+                    let texture = gl.create_texture().expect("Cannot create texture");
+                    gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                    // Rows of any width, not just multiples of 4 bytes
+                    gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+                    gl.tex_image_2d(
+                        glow::TEXTURE_2D,
+                        0,
+                        glow::RGBA as i32,
+                        *width,
+                        *height,
+                        0,
+                        glow::RGBA,
+                        glow::UNSIGNED_BYTE,
+                        glow::PixelUnpackData::Slice(Some(pixels)),
+                    );
+                    // GLES2 only repeats power-of-two textures, so clamp
+                    for (parameter, value) in [
+                        (glow::TEXTURE_MIN_FILTER, *filter),
+                        (glow::TEXTURE_MAG_FILTER, *filter),
+                        (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                        (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                    ] {
+                        gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+                    }
+                    gl.bind_texture(glow::TEXTURE_2D, None);
+                    Native::Texture(texture)
+                }
+            }
+        }
+    }
+}
+
+unsafe fn link_program(
+    gl: &glow::Context,
+    vertex: &str,
+    fragment: &str,
+    attributes: &[(u32, String)],
+) -> glow::NativeProgram {
+    let vs = compile_shader(gl, vertex, glow::VERTEX_SHADER);
+    let fs = compile_shader(gl, fragment, glow::FRAGMENT_SHADER);
+
+    let program = gl.create_program().expect("Cannot create program");
+    gl.attach_shader(program, vs);
+    gl.attach_shader(program, fs);
+    for (location, name) in attributes {
+        gl.bind_attrib_location(program, *location, name);
+    }
+    gl.link_program(program);
+
+    if !gl.get_program_link_status(program) {
+        panic!("Program link error: {}", gl.get_program_info_log(program));
+    }
+
+    gl.delete_shader(vs);
+    gl.delete_shader(fs);
+
+    program
+}
+
+unsafe fn compile_shader(gl: &glow::Context, src: &str, ty: u32) -> glow::NativeShader {
+    let shader = gl.create_shader(ty).expect("Cannot create shader");
+    gl.shader_source(shader, src);
+    gl.compile_shader(shader);
+
+    if !gl.get_shader_compile_status(shader) {
+        panic!("Shader compile error: {}", gl.get_shader_info_log(shader));
+    }
+
+    shader
+}
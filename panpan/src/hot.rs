@@ -0,0 +1,141 @@
+// panpan/src/hot.rs
+// Entry points a game exports for hot reloading on desktop
+//
+// With `panpan run --hot` the game crate is also built as a cdylib. The
+// desktop runner loads it, looks up `PANPAN_HOT_API` and drives the game only
+// through these functions, so a rebuilt library can be swapped in while the
+// window and GL context stay alive.
+//
+// The library carries its own copy of panpan, including its thread-locals.
+// Every function below therefore runs the game inside the library's copy
+// (e.g. `Context::scope` there), and callbacks without a `Context` get the
+// host's platform state handed over explicitly. Host and library must be
+// built by the same compiler from the same panpan, which `panpan run --hot`
+// takes care of.
+
+use crate::input::InputEvent;
+use crate::platform::PlatformState;
+use crate::timestep::TimestepConfig;
+use crate::{Conf, Context, Game};
+use std::ffi::c_void;
+
+/// Exports the hot reload entry points for `$game`. Put it once in the game
+/// crate's lib.rs.
+#[macro_export]
+macro_rules! hot_reload {
+    ($game:ty) => {
+        #[doc(hidden)]
+        #[no_mangle]
+        pub static PANPAN_HOT_API: $crate::hot::HotApi = $crate::hot::HotApi::new::<$game>();
+    };
+}
+
+/// Lifecycle calls, bundled to keep the table short
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotEvent {
+    Pause,
+    Resume,
+    FocusChanged(bool),
+    LowMemory,
+    CloseRequested,
+    Quit,
+}
+
+/// Function table exported by the game library. The game itself is an
+/// opaque pointer owned by the library.
+pub struct HotApi {
+    /// panpan version the library was built against
+    pub version: &'static str,
+    /// Layout check for the `Context` shared between host and library
+    pub context_size: usize,
+    pub conf: fn() -> Conf,
+    pub timestep: fn() -> TimestepConfig,
+    pub new: fn(&mut Context) -> *mut c_void,
+    pub restore: fn(&mut Context, &[u8]) -> *mut c_void,
+    pub serialize: unsafe fn(*const c_void) -> Vec<u8>,
+    pub drop: unsafe fn(*mut c_void),
+    pub update: unsafe fn(*mut c_void, &mut Context, f32),
+    pub render: unsafe fn(*const c_void, &mut Context, f32),
+    pub input: unsafe fn(*mut c_void, &mut PlatformState, &InputEvent),
+    /// Returns the answer to `CloseRequested`, true otherwise
+    pub event: unsafe fn(*mut c_void, &mut PlatformState, HotEvent) -> bool,
+}
+
+impl HotApi {
+    pub const fn new<G: Game>() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            context_size: std::mem::size_of::<Context>(),
+            conf: G::conf,
+            timestep: G::timestep,
+            new: new_game::<G>,
+            restore: restore_game::<G>,
+            serialize: serialize_game::<G>,
+            drop: drop_game::<G>,
+            update: update_game::<G>,
+            render: render_game::<G>,
+            input: input_game::<G>,
+            event: event_game::<G>,
+        }
+    }
+
+    /// True if the library can share a `Context` with this build of panpan
+    pub fn is_compatible(&self) -> bool {
+        self.version == env!("CARGO_PKG_VERSION")
+            && self.context_size == std::mem::size_of::<Context>()
+    }
+}
+
+fn new_game<G: Game>(ctx: &mut Context) -> *mut c_void {
+    Box::into_raw(Box::new(ctx.scope(G::new))).cast()
+}
+
+fn restore_game<G: Game>(ctx: &mut Context, data: &[u8]) -> *mut c_void {
+    Box::into_raw(Box::new(ctx.scope(|ctx| G::restore(ctx, data)))).cast()
+}
+
+unsafe fn serialize_game<G: Game>(game: *const c_void) -> Vec<u8> {
+    (*game.cast::<G>()).serialize()
+}
+
+unsafe fn drop_game<G: Game>(game: *mut c_void) {
+    drop(Box::from_raw(game.cast::<G>()));
+}
+
+unsafe fn update_game<G: Game>(game: *mut c_void, ctx: &mut Context, dt: f32) {
+    let game = &mut *game.cast::<G>();
+    ctx.scope(|ctx| game.update(ctx, dt));
+}
+
+unsafe fn render_game<G: Game>(game: *const c_void, ctx: &mut Context, alpha: f32) {
+    let game = &*game.cast::<G>();
+    ctx.scope(|ctx| game.render_interpolated(ctx, alpha));
+}
+
+unsafe fn input_game<G: Game>(
+    game: *mut c_void,
+    platform: &mut PlatformState,
+    event: &InputEvent,
+) {
+    let game = &mut *game.cast::<G>();
+    platform.__internal_scope(|| game.on_input(event));
+}
+
+unsafe fn event_game<G: Game>(
+    game: *mut c_void,
+    platform: &mut PlatformState,
+    event: HotEvent,
+) -> bool {
+    let game = &mut *game.cast::<G>();
+    platform.__internal_scope(|| {
+        match event {
+            HotEvent::Pause => game.on_pause(),
+            HotEvent::Resume => game.on_resume(),
+            HotEvent::FocusChanged(focused) => game.on_focus_changed(focused),
+            HotEvent::LowMemory => game.on_low_memory(),
+            HotEvent::CloseRequested => return game.on_close_requested(),
+            HotEvent::Quit => game.on_quit(),
+        }
+        true
+    })
+}
@@ -1,11 +1,17 @@
+use crate::platform::{self, PlatformRequest};
+use crate::types::Rect;
+use std::collections::{HashMap, HashSet};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TouchPhase {
     Started,
     Moved,
     Ended,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Touch {
     pub id: i32,
     pub x: f32,
@@ -13,17 +19,327 @@ pub struct Touch {
     pub phase: TouchPhase,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Key {
     W, A, S, D,
+    Up, Down, Left, Right,
     Space,
+    Enter,
+    Backspace,
     Escape,
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InputEvent {
     Touch(Touch),
     KeyDown(Key),
     KeyUp(Key),
-}
\ No newline at end of file
+    /// Text committed while text input is active
+    Text(String),
+    /// Text being composed by an IME, not committed yet. `cursor` is the
+    /// selected byte range inside `text`. An empty `text` ends composition.
+    Composition {
+        text: String,
+        cursor: Option<(usize, usize)>,
+    },
+}
+
+/// `Context::start_text_input` on the context that is current on this
+/// thread. Like the other text input functions it does nothing outside of
+/// a context.
+pub fn start_text_input() {
+    platform::push(PlatformRequest::StartTextInput);
+}
+
+/// `Context::stop_text_input` on the current context
+pub fn stop_text_input() {
+    platform::push(PlatformRequest::StopTextInput);
+}
+
+/// `Context::is_text_input_active` of the current context
+pub fn is_text_input_active() -> bool {
+    platform::with_current(|state| state.is_text_input_active()).unwrap_or(false)
+}
+
+/// `Context::set_text_input_area` on the current context
+pub fn set_text_input_area(area: Rect) {
+    platform::push(PlatformRequest::SetTextInputArea(area));
+}
+
+/// Touches and keys currently held. The `Context` keeps one up to date
+/// from every dispatched event.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputState {
+    touches: Vec<Touch>,
+    keys: HashSet<Key>,
+}
+
+impl InputState {
+    /// Fingers currently on the screen, oldest first
+    pub fn touches(&self) -> &[Touch] {
+        &self.touches
+    }
+
+    pub fn touch(&self, id: i32) -> Option<&Touch> {
+        self.touches.iter().find(|t| t.id == id)
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn handle_event(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::Touch(touch) => match touch.phase {
+                TouchPhase::Started => {
+                    self.touches.retain(|t| t.id != touch.id);
+                    self.touches.push(*touch);
+                }
+                TouchPhase::Moved => {
+                    if let Some(held) = self.touches.iter_mut().find(|t| t.id == touch.id) {
+                        *held = *touch;
+                    }
+                }
+                TouchPhase::Ended => self.touches.retain(|t| t.id != touch.id),
+            },
+            InputEvent::KeyDown(key) => {
+                self.keys.insert(*key);
+            }
+            InputEvent::KeyUp(key) => {
+                self.keys.remove(key);
+            }
+            InputEvent::Text(_) | InputEvent::Composition { .. } => {}
+        }
+    }
+}
+
+/// Maps keys and virtual controls to named actions and axes, so game code
+/// can poll `is_down("jump")` or `axis("move_x")` regardless of the device.
+///
+/// Call `end_frame` once per frame after the game has read the state.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Actions {
+    // Only the bindings are saved, not what is currently held
+    #[cfg_attr(feature = "serde", serde(rename = "keys"))]
+    key_bindings: HashMap<Key, Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(rename = "axes"))]
+    axis_bindings: HashMap<String, Vec<(Key, Key)>>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    held_keys: HashSet<Key>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    virtual_buttons: HashMap<String, u32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    virtual_axes: HashMap<String, f32>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    down: HashSet<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pressed: HashSet<String>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    released: HashSet<String>,
+}
+
+impl Actions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trigger `action` while `key` is held
+    pub fn bind_key(&mut self, key: Key, action: &str) -> &mut Self {
+        self.key_bindings
+            .entry(key)
+            .or_default()
+            .push(action.to_string());
+        self
+    }
+
+    /// Drive `axis` to -1.0 while `negative` is held and 1.0 while `positive` is held
+    pub fn bind_axis(&mut self, axis: &str, negative: Key, positive: Key) -> &mut Self {
+        self.axis_bindings
+            .entry(axis.to_string())
+            .or_default()
+            .push((negative, positive));
+        self
+    }
+
+    pub fn handle_event(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::KeyDown(key) => self.key_down(*key),
+            InputEvent::KeyUp(key) => self.key_up(*key),
+            _ => {}
+        }
+    }
+
+    pub fn key_down(&mut self, key: Key) {
+        if self.held_keys.insert(key) {
+            self.refresh_key(key);
+        }
+    }
+
+    pub fn key_up(&mut self, key: Key) {
+        if self.held_keys.remove(&key) {
+            self.refresh_key(key);
+        }
+    }
+
+    /// Press or release `action` from a virtual control. Presses are counted,
+    /// so two controls bound to the same action both have to let go.
+    pub fn set_button(&mut self, action: &str, down: bool) {
+        let count = self.virtual_buttons.entry(action.to_string()).or_insert(0);
+        if down {
+            *count += 1;
+        } else {
+            *count = count.saturating_sub(1);
+        }
+        self.refresh(action);
+    }
+
+    /// Set the value of `axis` from a virtual control, in -1.0..=1.0
+    pub fn set_axis(&mut self, axis: &str, value: f32) {
+        if value == 0.0 {
+            self.virtual_axes.remove(axis);
+        } else {
+            self.virtual_axes
+                .insert(axis.to_string(), value.clamp(-1.0, 1.0));
+        }
+    }
+
+    pub fn is_down(&self, action: &str) -> bool {
+        self.down.contains(action)
+    }
+
+    /// True only on the frame the action went down
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    /// True only on the frame the action went up
+    pub fn just_released(&self, action: &str) -> bool {
+        self.released.contains(action)
+    }
+
+    /// Combined value of keyboard and virtual input for `axis`, in -1.0..=1.0
+    pub fn axis(&self, axis: &str) -> f32 {
+        let mut value = self.virtual_axes.get(axis).copied().unwrap_or(0.0);
+        if let Some(bindings) = self.axis_bindings.get(axis) {
+            for (negative, positive) in bindings {
+                if self.held_keys.contains(negative) {
+                    value -= 1.0;
+                }
+                if self.held_keys.contains(positive) {
+                    value += 1.0;
+                }
+            }
+        }
+        value.clamp(-1.0, 1.0)
+    }
+
+    /// Clear the per-frame pressed/released state
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    /// Release everything, e.g. when the app loses focus
+    pub fn reset(&mut self) {
+        let down: Vec<String> = self.down.drain().collect();
+        self.released.extend(down);
+        self.held_keys.clear();
+        self.virtual_buttons.clear();
+        self.virtual_axes.clear();
+    }
+
+    fn refresh_key(&mut self, key: Key) {
+        if let Some(actions) = self.key_bindings.get(&key).cloned() {
+            for action in actions {
+                self.refresh(&action);
+            }
+        }
+    }
+
+    fn refresh(&mut self, action: &str) {
+        let from_keys = self.key_bindings.iter().any(|(key, actions)| {
+            self.held_keys.contains(key) && actions.iter().any(|a| a == action)
+        });
+        let from_virtual = self
+            .virtual_buttons
+            .get(action)
+            .is_some_and(|count| *count > 0);
+        let now_down = from_keys || from_virtual;
+
+        if now_down && self.down.insert(action.to_string()) {
+            self.pressed.insert(action.to_string());
+        } else if !now_down && self.down.remove(action) {
+            self.released.insert(action.to_string());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn key_round_trip() {
+        assert_eq!(serde_json::to_string(&Key::Space).unwrap(), r#""Space""#);
+        assert_eq!(serde_json::from_str::<Key>(r#""Escape""#).unwrap(), Key::Escape);
+        assert!(serde_json::from_str::<Key>(r#""F13""#).is_err());
+    }
+
+    #[test]
+    fn input_event_round_trip() {
+        let events = vec![
+            InputEvent::Touch(Touch {
+                id: 2,
+                x: 10.5,
+                y: 20.0,
+                phase: TouchPhase::Moved,
+            }),
+            InputEvent::KeyDown(Key::W),
+            InputEvent::KeyUp(Key::W),
+            InputEvent::Text("héllo".to_string()),
+            InputEvent::Composition {
+                text: "かな".to_string(),
+                cursor: Some((0, 3)),
+            },
+            InputEvent::Composition {
+                text: String::new(),
+                cursor: None,
+            },
+        ];
+        let json = serde_json::to_string(&events).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<InputEvent>>(&json).unwrap(), events);
+        let bytes = bincode::serialize(&events).unwrap();
+        assert_eq!(bincode::deserialize::<Vec<InputEvent>>(&bytes).unwrap(), events);
+    }
+
+    #[test]
+    fn actions_keep_only_bindings() {
+        let mut actions = Actions::new();
+        actions
+            .bind_key(Key::Space, "jump")
+            .bind_axis("move_x", Key::A, Key::D);
+        actions.key_down(Key::Space);
+        actions.key_down(Key::D);
+        actions.set_button("fire", true);
+        assert!(actions.is_down("jump"));
+
+        let json = serde_json::to_value(&actions).unwrap();
+        let fields: Vec<&String> = json.as_object().unwrap().keys().collect();
+        assert_eq!(fields, ["axes", "keys"]);
+
+        let mut loaded: Actions = serde_json::from_value(json).unwrap();
+        assert!(!loaded.is_down("jump"));
+        assert!(!loaded.is_down("fire"));
+        assert_eq!(loaded.axis("move_x"), 0.0);
+
+        loaded.key_down(Key::Space);
+        loaded.key_down(Key::A);
+        assert!(loaded.just_pressed("jump"));
+        assert_eq!(loaded.axis("move_x"), -1.0);
+    }
+}
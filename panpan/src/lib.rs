@@ -2,39 +2,182 @@
 // Core engine API - platform agnostic
 
 pub mod types;
+pub mod math;
+pub mod color;
+pub mod rand;
+pub mod collision;
+pub mod physics;
+pub mod tween;
 pub mod graphics;
+pub mod animation;
 pub mod input;
+pub mod gesture;
+pub mod virtual_controls;
+#[doc(hidden)]
+pub mod platform;
+pub mod replay;
+pub mod timestep;
+pub mod time;
+pub mod timer;
+pub mod conf;
+pub mod window;
+pub mod context;
+pub mod scene;
+#[doc(hidden)]
+pub mod hot;
 
 // Re-export commonly used items
-pub use types::{Color, Vec2, Rect};
-pub use graphics::{clear_screen, draw_rect, draw_circle, draw_text};
-pub use input::{Touch, TouchPhase, Key, InputEvent};
+pub use types::{Color, Vec2, Vec3, Mat3, Affine2, Rect};
+pub use graphics::{clear_screen, draw_rect, draw_line, draw_circle, draw_text, Graphics, Texture};
+pub use animation::{Animation, SpriteSheet};
+pub use input::{Touch, TouchPhase, Key, InputEvent, InputState, Actions};
+pub use input::{start_text_input, stop_text_input, is_text_input_active, set_text_input_area};
+pub use timestep::{GameLoop, TimestepConfig};
+pub use time::Time;
+pub use timer::{Timer, Timers, Coroutines};
+pub use rand::Rng;
+pub use tween::{Ease, Tween};
+pub use conf::{Conf, Icon, Orientation};
+pub use window::{CoordinateSpace, WindowMode};
+pub use context::Context;
+pub use scene::{Scene, SceneStack, SceneChange, Transition, SlideDirection};
+pub use gesture::{Gesture, GestureConfig, GestureRecognizer, SwipeDirection};
+pub use virtual_controls::{VirtualControls, VirtualJoystick, VirtualButton, VirtualDPad, JoystickMode};
 
 /// Main game trait that users must implement
 pub trait Game: Sized {
     /// Create a new game instance
-    fn new() -> Self;
+    fn new(ctx: &mut Context) -> Self;
     
-    /// Update game logic
-    fn update(&mut self, dt: f32);
+    /// Update game logic. With the default fixed timestep `dt` is always
+    /// the same length.
+    fn update(&mut self, ctx: &mut Context, dt: f32);
     
     /// Render the game
-    fn render(&self);
+    fn render(&self, ctx: &mut Context);
+
+    /// Optional: render with `alpha`, how far the current frame is between
+    /// the last fixed update and the next one (0.0..1.0). Override to
+    /// interpolate positions; the default just calls `render`.
+    fn render_interpolated(&self, ctx: &mut Context, _alpha: f32) {
+        self.render(ctx);
+    }
+
+    /// Optional: window and app settings, read once before the window is
+    /// created
+    fn conf() -> Conf {
+        Conf::default()
+    }
+
+    /// Optional: how the runner steps `update`. Defaults to 60 fixed
+    /// updates per second.
+    fn timestep() -> TimestepConfig {
+        TimestepConfig::default()
+    }
+
+    /// Optional: save the game state before a hot reload (`panpan run
+    /// --hot`). The bytes are handed to `restore` in the rebuilt code.
+    fn serialize(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Optional: rebuild the game from what `serialize` returned in the
+    /// previous build. The default starts over with `new`.
+    fn restore(ctx: &mut Context, _data: &[u8]) -> Self {
+        Self::new(ctx)
+    }
     
+    /// Optional: handle any input event. The default calls the specific
+    /// callbacks below; override it to forward events elsewhere, e.g. to a
+    /// `SceneStack`.
+    fn on_input(&mut self, event: &InputEvent) {
+        match event {
+            InputEvent::Touch(touch) => match touch.phase {
+                TouchPhase::Started => self.on_touch_down(touch.id, touch.x, touch.y),
+                TouchPhase::Moved => self.on_touch_move(touch.id, touch.x, touch.y),
+                TouchPhase::Ended => self.on_touch_up(touch.id),
+            },
+            InputEvent::KeyDown(key) => self.on_key_down(*key),
+            InputEvent::KeyUp(key) => self.on_key_up(*key),
+            InputEvent::Text(text) => self.on_text_input(text),
+            InputEvent::Composition { text, cursor } => self.on_text_composition(text, *cursor),
+        }
+    }
+
     /// Optional: handle input events
     fn on_touch_down(&mut self, _id: i32, _x: f32, _y: f32) {}
     fn on_touch_move(&mut self, _id: i32, _x: f32, _y: f32) {}
     fn on_touch_up(&mut self, _id: i32) {}
+    fn on_key_down(&mut self, _key: Key) {}
+    fn on_key_up(&mut self, _key: Key) {}
+
+    /// Optional: the app went to the background or was minimized. Updates
+    /// stop until `on_resume`.
+    fn on_pause(&mut self) {}
+    /// Optional: the app is back in the foreground
+    fn on_resume(&mut self) {}
+    /// Optional: the window gained or lost keyboard focus
+    fn on_focus_changed(&mut self, _focused: bool) {}
+    /// Optional: the OS is short on memory, drop caches if possible
+    fn on_low_memory(&mut self) {}
+    /// Optional: the user asked to close the window. Return `false` to keep
+    /// it open, e.g. to show a "save changes?" dialog. Desktop only.
+    fn on_close_requested(&mut self) -> bool {
+        true
+    }
+    /// Optional: the app is about to exit, last chance to save
+    fn on_quit(&mut self) {}
+
+    /// Optional: text committed while text input is active
+    fn on_text_input(&mut self, _text: &str) {}
+    /// Optional: IME composition in progress. An empty `text` ends it.
+    fn on_text_composition(&mut self, _text: &str, _cursor: Option<(usize, usize)>) {}
+}
+
+/// Forward an input event to `Game::on_input` and update the
+/// input state of `ctx`. Runners and replay playback both go through here.
+pub fn dispatch_event<G: Game>(game: &mut G, ctx: &mut Context, event: &InputEvent) {
+    ctx.scope(|ctx| {
+        ctx.handle_event(event);
+        game.on_input(event);
+    });
 }
 
 /// Internal: Runner will call this to initialize the rendering backend
 #[doc(hidden)]
-pub fn __internal_init_graphics(gl: glow::Context) {
-    graphics::init(gl);
+pub fn __internal_init_graphics(ctx: &mut Context, gl: glow::Context) {
+    ctx.init_graphics(gl);
 }
 
-/// Internal: Runner will call this on resize
+/// Internal: Runner will call this on resize, with the framebuffer size in
+/// physical pixels
 #[doc(hidden)]
-pub fn __internal_resize(width: i32, height: i32) {
-    graphics::set_viewport(width, height);
-}
\ No newline at end of file
+pub fn __internal_resize(ctx: &mut Context, width: i32, height: i32) {
+    ctx.graphics().set_viewport(width, height);
+}
+
+/// Internal: Runner will call this with how long presenting the frame took
+#[doc(hidden)]
+pub fn __internal_record_swap(ctx: &mut Context, took: std::time::Duration) {
+    ctx.time_mut().record_swap(took);
+}
+
+/// Internal: Runner will call this at startup and whenever the window moves
+/// to a display with a different scale factor
+#[doc(hidden)]
+pub fn __internal_set_scale_factor(ctx: &mut Context, scale: f32) {
+    ctx.set_scale_factor(scale);
+}
+
+/// Internal: Runner will call this at startup with `Conf::coordinate_space`
+#[doc(hidden)]
+pub fn __internal_set_coordinate_space(ctx: &mut Context, space: CoordinateSpace) {
+    ctx.set_coordinate_space(space);
+}
+
+/// Internal: Runner will call this once per frame to carry out requests
+/// made by the game, like showing the soft keyboard
+#[doc(hidden)]
+pub fn __internal_take_platform_requests(ctx: &mut Context) -> Vec<platform::PlatformRequest> {
+    ctx.take_platform_requests()
+}
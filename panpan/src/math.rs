@@ -0,0 +1,633 @@
+// panpan/src/math.rs
+// Vectors and 2D transforms shared by the renderer and games
+//
+// Re-exported from `panpan::types`. Matrices are column-major like OpenGL,
+// and `a * b` applies `b` first. With the `glam` feature every type converts
+// to and from its glam counterpart, and with `serde` they are serializable.
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vec2 {
+    pub const ZERO: Vec2 = Vec2::new(0.0, 0.0);
+    pub const ONE: Vec2 = Vec2::new(1.0, 1.0);
+    pub const X: Vec2 = Vec2::new(1.0, 0.0);
+    pub const Y: Vec2 = Vec2::new(0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+
+    pub const fn splat(v: f32) -> Self {
+        Self::new(v, v)
+    }
+
+    /// Unit vector pointing at `angle` radians, 0 along +x
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin)
+    }
+
+    pub fn dot(self, other: Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// z of the 3D cross product, positive when `other` is clockwise from
+    /// `self` on screen (y points down)
+    pub fn cross(self, other: Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn distance(self, other: Vec2) -> f32 {
+        (other - self).length()
+    }
+
+    pub fn distance_squared(self, other: Vec2) -> f32 {
+        (other - self).length_squared()
+    }
+
+    /// Same direction with length 1, or zero for a zero vector
+    pub fn normalize(self) -> Vec2 {
+        let length = self.length();
+        if length > 0.0 {
+            self / length
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    /// Shorten to at most `max` long
+    pub fn clamp_length(self, max: f32) -> Vec2 {
+        let length = self.length();
+        if length > max {
+            self * (max / length)
+        } else {
+            self
+        }
+    }
+
+    pub fn lerp(self, other: Vec2, t: f32) -> Vec2 {
+        self + (other - self) * t
+    }
+
+    /// Angle from +x in radians, in -PI..=PI
+    pub fn angle(self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    /// Signed angle that rotates `self` onto `other`
+    pub fn angle_to(self, other: Vec2) -> f32 {
+        self.cross(other).atan2(self.dot(other))
+    }
+
+    pub fn rotate(self, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// Rotated by 90 degrees
+    pub fn perp(self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+
+    pub fn min(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    pub fn max(self, other: Vec2) -> Vec2 {
+        Vec2::new(self.x.max(other.x), self.y.max(other.y))
+    }
+
+    pub fn abs(self) -> Vec2 {
+        Vec2::new(self.x.abs(), self.y.abs())
+    }
+
+    pub fn extend(self, z: f32) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub const ZERO: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vec3 = Vec3::new(1.0, 1.0, 1.0);
+    pub const X: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+    pub const Y: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    pub const Z: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    pub const fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn splat(v: f32) -> Self {
+        Self::new(v, v, v)
+    }
+
+    pub fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn distance(self, other: Vec3) -> f32 {
+        (other - self).length()
+    }
+
+    /// Same direction with length 1, or zero for a zero vector
+    pub fn normalize(self) -> Vec3 {
+        let length = self.length();
+        if length > 0.0 {
+            self / length
+        } else {
+            Vec3::ZERO
+        }
+    }
+
+    pub fn lerp(self, other: Vec3, t: f32) -> Vec3 {
+        self + (other - self) * t
+    }
+
+    pub fn min(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+
+    pub fn abs(self) -> Vec3 {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
+    /// Drop `z`
+    pub fn truncate(self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+/// 3x3 matrix, stored as columns. Used for 2D transforms in homogeneous
+/// coordinates, including projections.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mat3 {
+    pub x_axis: Vec3,
+    pub y_axis: Vec3,
+    pub z_axis: Vec3,
+}
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3::from_cols(Vec3::X, Vec3::Y, Vec3::Z);
+    pub const ZERO: Mat3 = Mat3::from_cols(Vec3::ZERO, Vec3::ZERO, Vec3::ZERO);
+
+    pub const fn from_cols(x_axis: Vec3, y_axis: Vec3, z_axis: Vec3) -> Self {
+        Self {
+            x_axis,
+            y_axis,
+            z_axis,
+        }
+    }
+
+    pub fn from_translation(translation: Vec2) -> Self {
+        Self::from_cols(Vec3::X, Vec3::Y, translation.extend(1.0))
+    }
+
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols(Vec3::new(cos, sin, 0.0), Vec3::new(-sin, cos, 0.0), Vec3::Z)
+    }
+
+    pub fn from_scale(scale: Vec2) -> Self {
+        Self::from_cols(
+            Vec3::new(scale.x, 0.0, 0.0),
+            Vec3::new(0.0, scale.y, 0.0),
+            Vec3::Z,
+        )
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        Mat3::from_cols(
+            Vec3::new(self.x_axis.x, self.y_axis.x, self.z_axis.x),
+            Vec3::new(self.x_axis.y, self.y_axis.y, self.z_axis.y),
+            Vec3::new(self.x_axis.z, self.y_axis.z, self.z_axis.z),
+        )
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.z_axis.dot(self.x_axis.cross(self.y_axis))
+    }
+
+    /// `None` if the matrix is not invertible
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det == 0.0 {
+            return None;
+        }
+        let rows = Mat3::from_cols(
+            self.y_axis.cross(self.z_axis),
+            self.z_axis.cross(self.x_axis),
+            self.x_axis.cross(self.y_axis),
+        );
+        Some(rows.transpose() * (1.0 / det))
+    }
+
+    /// Transform a position, including translation
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        (*self * point.extend(1.0)).truncate()
+    }
+
+    /// Transform a direction, ignoring translation
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        (*self * vector.extend(0.0)).truncate()
+    }
+
+    pub fn to_cols_array(&self) -> [f32; 9] {
+        let (x, y, z) = (self.x_axis, self.y_axis, self.z_axis);
+        [x.x, x.y, x.z, y.x, y.y, y.z, z.x, z.y, z.z]
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// 2D affine transform: a 2x2 matrix for rotation, scale and shear plus a
+/// translation. Cheaper than `Mat3` when no projection is involved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Affine2 {
+    pub x_axis: Vec2,
+    pub y_axis: Vec2,
+    pub translation: Vec2,
+}
+
+impl Affine2 {
+    pub const IDENTITY: Affine2 = Affine2 {
+        x_axis: Vec2::X,
+        y_axis: Vec2::Y,
+        translation: Vec2::ZERO,
+    };
+
+    pub fn from_translation(translation: Vec2) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn from_angle(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x_axis: Vec2::new(cos, sin),
+            y_axis: Vec2::new(-sin, cos),
+            translation: Vec2::ZERO,
+        }
+    }
+
+    pub fn from_scale(scale: Vec2) -> Self {
+        Self {
+            x_axis: Vec2::new(scale.x, 0.0),
+            y_axis: Vec2::new(0.0, scale.y),
+            translation: Vec2::ZERO,
+        }
+    }
+
+    /// Scale, then rotate, then translate
+    pub fn from_scale_angle_translation(scale: Vec2, angle: f32, translation: Vec2) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x_axis: Vec2::new(cos, sin) * scale.x,
+            y_axis: Vec2::new(-sin, cos) * scale.y,
+            translation,
+        }
+    }
+
+    pub fn transform_point(&self, point: Vec2) -> Vec2 {
+        self.transform_vector(point) + self.translation
+    }
+
+    pub fn transform_vector(&self, vector: Vec2) -> Vec2 {
+        self.x_axis * vector.x + self.y_axis * vector.y
+    }
+
+    /// `None` if the transform is not invertible (zero scale)
+    pub fn inverse(&self) -> Option<Affine2> {
+        let det = self.x_axis.cross(self.y_axis);
+        if det == 0.0 {
+            return None;
+        }
+        let x_axis = Vec2::new(self.y_axis.y, -self.x_axis.y) / det;
+        let y_axis = Vec2::new(-self.y_axis.x, self.x_axis.x) / det;
+        let inverse = Affine2 {
+            x_axis,
+            y_axis,
+            translation: Vec2::ZERO,
+        };
+        Some(Affine2 {
+            translation: -inverse.transform_vector(self.translation),
+            ..inverse
+        })
+    }
+}
+
+impl Default for Affine2 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Affine2> for Mat3 {
+    fn from(a: Affine2) -> Self {
+        Mat3::from_cols(
+            a.x_axis.extend(0.0),
+            a.y_axis.extend(0.0),
+            a.translation.extend(1.0),
+        )
+    }
+}
+
+// Operators
+
+macro_rules! impl_vector_ops {
+    ($t:ident { $($f:ident),+ }) => {
+        impl Add for $t {
+            type Output = $t;
+            fn add(self, rhs: $t) -> $t {
+                $t { $($f: self.$f + rhs.$f),+ }
+            }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+            fn sub(self, rhs: $t) -> $t {
+                $t { $($f: self.$f - rhs.$f),+ }
+            }
+        }
+
+        /// Component-wise
+        impl Mul for $t {
+            type Output = $t;
+            fn mul(self, rhs: $t) -> $t {
+                $t { $($f: self.$f * rhs.$f),+ }
+            }
+        }
+
+        /// Component-wise
+        impl Div for $t {
+            type Output = $t;
+            fn div(self, rhs: $t) -> $t {
+                $t { $($f: self.$f / rhs.$f),+ }
+            }
+        }
+
+        impl Mul<f32> for $t {
+            type Output = $t;
+            fn mul(self, rhs: f32) -> $t {
+                $t { $($f: self.$f * rhs),+ }
+            }
+        }
+
+        impl Mul<$t> for f32 {
+            type Output = $t;
+            fn mul(self, rhs: $t) -> $t {
+                rhs * self
+            }
+        }
+
+        impl Div<f32> for $t {
+            type Output = $t;
+            fn div(self, rhs: f32) -> $t {
+                $t { $($f: self.$f / rhs),+ }
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+            fn neg(self) -> $t {
+                $t { $($f: -self.$f),+ }
+            }
+        }
+
+        impl AddAssign for $t {
+            fn add_assign(&mut self, rhs: $t) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl SubAssign for $t {
+            fn sub_assign(&mut self, rhs: $t) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl MulAssign<f32> for $t {
+            fn mul_assign(&mut self, rhs: f32) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl DivAssign<f32> for $t {
+            fn div_assign(&mut self, rhs: f32) {
+                *self = *self / rhs;
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Vec2 { x, y });
+impl_vector_ops!(Vec3 { x, y, z });
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    fn mul(self, v: Vec3) -> Vec3 {
+        self.x_axis * v.x + self.y_axis * v.y + self.z_axis * v.z
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Mat3;
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        Mat3::from_cols(self * rhs.x_axis, self * rhs.y_axis, self * rhs.z_axis)
+    }
+}
+
+impl Mul<f32> for Mat3 {
+    type Output = Mat3;
+    fn mul(self, rhs: f32) -> Mat3 {
+        Mat3::from_cols(self.x_axis * rhs, self.y_axis * rhs, self.z_axis * rhs)
+    }
+}
+
+impl MulAssign for Mat3 {
+    fn mul_assign(&mut self, rhs: Mat3) {
+        *self = *self * rhs;
+    }
+}
+
+impl Mul for Affine2 {
+    type Output = Affine2;
+    fn mul(self, rhs: Affine2) -> Affine2 {
+        Affine2 {
+            x_axis: self.transform_vector(rhs.x_axis),
+            y_axis: self.transform_vector(rhs.y_axis),
+            translation: self.transform_point(rhs.translation),
+        }
+    }
+}
+
+impl MulAssign for Affine2 {
+    fn mul_assign(&mut self, rhs: Affine2) {
+        *self = *self * rhs;
+    }
+}
+
+// Conversions
+
+impl From<(f32, f32)> for Vec2 {
+    fn from((x, y): (f32, f32)) -> Self {
+        Vec2::new(x, y)
+    }
+}
+
+impl From<[f32; 2]> for Vec2 {
+    fn from([x, y]: [f32; 2]) -> Self {
+        Vec2::new(x, y)
+    }
+}
+
+impl From<Vec2> for (f32, f32) {
+    fn from(v: Vec2) -> Self {
+        (v.x, v.y)
+    }
+}
+
+impl From<Vec2> for [f32; 2] {
+    fn from(v: Vec2) -> Self {
+        [v.x, v.y]
+    }
+}
+
+impl From<(f32, f32, f32)> for Vec3 {
+    fn from((x, y, z): (f32, f32, f32)) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<Vec3> for (f32, f32, f32) {
+    fn from(v: Vec3) -> Self {
+        (v.x, v.y, v.z)
+    }
+}
+
+impl From<Vec3> for [f32; 3] {
+    fn from(v: Vec3) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_conversions {
+    use super::{Affine2, Mat3, Vec2, Vec3};
+
+    impl From<glam::Vec2> for Vec2 {
+        fn from(v: glam::Vec2) -> Self {
+            Vec2::new(v.x, v.y)
+        }
+    }
+
+    impl From<Vec2> for glam::Vec2 {
+        fn from(v: Vec2) -> Self {
+            glam::Vec2::new(v.x, v.y)
+        }
+    }
+
+    impl From<glam::Vec3> for Vec3 {
+        fn from(v: glam::Vec3) -> Self {
+            Vec3::new(v.x, v.y, v.z)
+        }
+    }
+
+    impl From<Vec3> for glam::Vec3 {
+        fn from(v: Vec3) -> Self {
+            glam::Vec3::new(v.x, v.y, v.z)
+        }
+    }
+
+    impl From<glam::Mat3> for Mat3 {
+        fn from(m: glam::Mat3) -> Self {
+            Mat3::from_cols(m.x_axis.into(), m.y_axis.into(), m.z_axis.into())
+        }
+    }
+
+    impl From<Mat3> for glam::Mat3 {
+        fn from(m: Mat3) -> Self {
+            glam::Mat3::from_cols(m.x_axis.into(), m.y_axis.into(), m.z_axis.into())
+        }
+    }
+
+    impl From<glam::Affine2> for Affine2 {
+        fn from(a: glam::Affine2) -> Self {
+            Affine2 {
+                x_axis: a.matrix2.x_axis.into(),
+                y_axis: a.matrix2.y_axis.into(),
+                translation: a.translation.into(),
+            }
+        }
+    }
+
+    impl From<Affine2> for glam::Affine2 {
+        fn from(a: Affine2) -> Self {
+            glam::Affine2::from_cols(a.x_axis.into(), a.y_axis.into(), a.translation.into())
+        }
+    }
+}
@@ -0,0 +1,1364 @@
+// panpan/src/physics.rs
+// Rigid-body physics built on `collision`
+//
+// A small sequential-impulse solver in the spirit of Box2D Lite: bodies with
+// one collider each, distance and pin joints, friction and restitution.
+// Call `World::step` from `Game::update`, which runs at a fixed timestep, so
+// the same inputs always give the same simulation (replays depend on it).
+//
+// A body's position is its center of mass and the point it rotates around;
+// place collider offsets accordingly. Units are game units, so gravity for a
+// pixel-sized world is in the hundreds.
+
+use crate::collision::{self, Manifold, Polygon, RayHit, Shape, SpatialHash};
+use crate::color::css;
+use crate::graphics::Graphics;
+use crate::types::{Color, Rect, Vec2};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Penetration left alone, so resting contacts stay touching
+const POSITION_SLOP: f32 = 0.05;
+/// Fraction of the remaining penetration or joint error removed per step
+const POSITION_CORRECTION: f32 = 0.4;
+/// Slower impacts than this (units per second) do not bounce
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+/// Fraction of a joint's error corrected per step
+const JOINT_CORRECTION: f32 = 0.2;
+/// Cosine of the largest normal change that still reuses last step's impulses
+const WARM_START_ALIGNMENT: f32 = 0.95;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    /// Moved by forces, gravity and collisions
+    Dynamic,
+    /// Never moves
+    Static,
+    /// Moves only by its velocity, pushes dynamic bodies but is not pushed
+    Kinematic,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColliderShape {
+    Circle {
+        radius: f32,
+    },
+    /// Box centered on the collider's offset
+    Box {
+        half_size: Vec2,
+    },
+    /// Convex polygon with points relative to the collider's offset
+    Polygon(Polygon),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    /// Position relative to the body, rotated with it
+    pub offset: Vec2,
+    /// Mass per square unit
+    pub density: f32,
+    pub friction: f32,
+    /// Bounciness, 0 for none and 1 for a perfect bounce
+    pub restitution: f32,
+    /// Sensors report collision events but do not push anything
+    pub sensor: bool,
+}
+
+impl Collider {
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            offset: Vec2::ZERO,
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            sensor: false,
+        }
+    }
+
+    pub fn circle(radius: f32) -> Self {
+        Self::new(ColliderShape::Circle { radius })
+    }
+
+    /// Box `width` by `height`, centered on the body
+    pub fn rect(width: f32, height: f32) -> Self {
+        Self::new(ColliderShape::Box {
+            half_size: Vec2::new(width * 0.5, height * 0.5),
+        })
+    }
+
+    pub fn polygon(polygon: Polygon) -> Self {
+        Self::new(ColliderShape::Polygon(polygon))
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_density(mut self, density: f32) -> Self {
+        self.density = density;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn sensor(mut self) -> Self {
+        self.sensor = true;
+        self
+    }
+
+    /// Mass and moment of inertia around the body's origin
+    fn mass_properties(&self) -> (f32, f32) {
+        let (area, inertia) = match &self.shape {
+            ColliderShape::Circle { radius } => {
+                let area = std::f32::consts::PI * radius * radius;
+                (area, area * radius * radius * 0.5)
+            }
+            ColliderShape::Box { half_size } => {
+                let size = *half_size * 2.0;
+                let area = size.x * size.y;
+                (area, area * size.length_squared() / 12.0)
+            }
+            ColliderShape::Polygon(polygon) => polygon_mass(polygon.points()),
+        };
+        let mass = area * self.density;
+        let inertia = inertia * self.density + mass * self.offset.length_squared();
+        (mass, inertia)
+    }
+
+    /// The collider placed at `position`, rotated by `angle`
+    fn world_shape(&self, position: Vec2, angle: f32) -> Shape {
+        let center = position + self.offset.rotate(angle);
+        match &self.shape {
+            ColliderShape::Circle { radius } => Shape::circle(center, *radius),
+            ColliderShape::Box { half_size } if angle == 0.0 => Shape::Rect(Rect::from_center(
+                center,
+                half_size.x * 2.0,
+                half_size.y * 2.0,
+            )),
+            ColliderShape::Box { half_size } => Shape::obb(center, *half_size, angle),
+            ColliderShape::Polygon(polygon) => {
+                let points: Vec<Vec2> = polygon
+                    .points()
+                    .iter()
+                    .map(|p| center + p.rotate(angle))
+                    .collect();
+                Shape::Polygon(Polygon::new(points).expect("rotation keeps the area"))
+            }
+        }
+    }
+}
+
+/// Area and inertia around the origin of a polygon, from a triangle fan
+fn polygon_mass(points: &[Vec2]) -> (f32, f32) {
+    let mut area = 0.0;
+    let mut inertia = 0.0;
+    for (i, &p1) in points.iter().enumerate() {
+        let p2 = points[(i + 1) % points.len()];
+        let triangle = p1.cross(p2) * 0.5;
+        area += triangle;
+        inertia += triangle * (p1.dot(p1) + p1.dot(p2) + p2.dot(p2)) / 6.0;
+    }
+    (area.abs(), inertia.abs())
+}
+
+#[derive(Debug, Clone)]
+pub struct Body {
+    kind: BodyKind,
+    pub position: Vec2,
+    /// Rotation in radians
+    pub angle: f32,
+    pub velocity: Vec2,
+    /// Radians per second
+    pub angular_velocity: f32,
+    /// Multiplies the world's gravity for this body
+    pub gravity_scale: f32,
+    /// Fraction of the velocity lost per second, roughly
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    /// Free for the game, e.g. an entity id
+    pub user_data: u64,
+    collider: Option<Collider>,
+    fixed_rotation: bool,
+    force: Vec2,
+    torque: f32,
+    inv_mass: f32,
+    inv_inertia: f32,
+}
+
+impl Body {
+    pub fn new(kind: BodyKind, position: Vec2) -> Self {
+        let mut body = Self {
+            kind,
+            position,
+            angle: 0.0,
+            velocity: Vec2::ZERO,
+            angular_velocity: 0.0,
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            user_data: 0,
+            collider: None,
+            fixed_rotation: false,
+            force: Vec2::ZERO,
+            torque: 0.0,
+            inv_mass: 0.0,
+            inv_inertia: 0.0,
+        };
+        body.update_mass();
+        body
+    }
+
+    pub fn dynamic(position: Vec2) -> Self {
+        Self::new(BodyKind::Dynamic, position)
+    }
+
+    pub fn fixed(position: Vec2) -> Self {
+        Self::new(BodyKind::Static, position)
+    }
+
+    pub fn kinematic(position: Vec2) -> Self {
+        Self::new(BodyKind::Kinematic, position)
+    }
+
+    pub fn with_collider(mut self, collider: Collider) -> Self {
+        self.set_collider(Some(collider));
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_angle(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    /// Keep the body upright, e.g. for characters
+    pub fn with_fixed_rotation(mut self) -> Self {
+        self.set_fixed_rotation(true);
+        self
+    }
+
+    pub fn with_user_data(mut self, user_data: u64) -> Self {
+        self.user_data = user_data;
+        self
+    }
+
+    pub fn kind(&self) -> BodyKind {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: BodyKind) {
+        self.kind = kind;
+        self.update_mass();
+    }
+
+    pub fn collider(&self) -> Option<&Collider> {
+        self.collider.as_ref()
+    }
+
+    /// Replace the collider; the mass follows from its size and density
+    pub fn set_collider(&mut self, collider: Option<Collider>) {
+        self.collider = collider;
+        self.update_mass();
+    }
+
+    pub fn set_fixed_rotation(&mut self, fixed: bool) {
+        self.fixed_rotation = fixed;
+        self.update_mass();
+    }
+
+    /// Zero for static and kinematic bodies
+    pub fn mass(&self) -> f32 {
+        if self.inv_mass > 0.0 {
+            1.0 / self.inv_mass
+        } else {
+            0.0
+        }
+    }
+
+    /// The collider in world coordinates
+    pub fn shape(&self) -> Option<Shape> {
+        let collider = self.collider.as_ref()?;
+        Some(collider.world_shape(self.position, self.angle))
+    }
+
+    /// Push the center of mass during the next step
+    pub fn apply_force(&mut self, force: Vec2) {
+        self.force += force;
+    }
+
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.torque += torque;
+    }
+
+    /// Change the velocity at once, as if hit at world `point`
+    pub fn apply_impulse(&mut self, impulse: Vec2, point: Vec2) {
+        self.velocity += impulse * self.inv_mass;
+        self.angular_velocity += (point - self.position).cross(impulse) * self.inv_inertia;
+    }
+
+    /// Velocity of the body at world `point`
+    pub fn velocity_at(&self, point: Vec2) -> Vec2 {
+        self.velocity + (point - self.position).perp() * self.angular_velocity
+    }
+
+    fn update_mass(&mut self) {
+        (self.inv_mass, self.inv_inertia) = match self.kind {
+            BodyKind::Dynamic => {
+                let (mass, inertia) = match &self.collider {
+                    Some(c) if !c.sensor => c.mass_properties(),
+                    _ => (1.0, 1.0),
+                };
+                let inv = |v: f32| if v > 0.0 { 1.0 / v } else { 0.0 };
+                let inv_inertia = if self.fixed_rotation {
+                    0.0
+                } else {
+                    inv(inertia)
+                };
+                (inv(mass), inv_inertia)
+            }
+            BodyKind::Static | BodyKind::Kinematic => (0.0, 0.0),
+        };
+    }
+}
+
+/// Identifies a body in its `World`. Stays unique after the body is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyHandle {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JointHandle {
+    index: usize,
+    generation: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Joint {
+    /// Keeps the anchors `length` apart, like a rod
+    Distance {
+        a: BodyHandle,
+        b: BodyHandle,
+        /// Anchors in each body's local coordinates
+        anchor_a: Vec2,
+        anchor_b: Vec2,
+        length: f32,
+    },
+    /// Pins the anchors together; the bodies can still rotate around it
+    Pin {
+        a: BodyHandle,
+        b: BodyHandle,
+        anchor_a: Vec2,
+        anchor_b: Vec2,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionEvent {
+    /// Two colliders started touching; the smaller handle comes first
+    Started(BodyHandle, BodyHandle),
+    Stopped(BodyHandle, BodyHandle),
+}
+
+/// Two touching bodies, from the last step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub a: BodyHandle,
+    pub b: BodyHandle,
+    /// Normal points from `a` to `b`
+    pub manifold: Manifold,
+    /// One of the colliders is a sensor, so nothing was pushed
+    pub sensor: bool,
+}
+
+/// Impulses accumulated for one contact point while solving
+#[derive(Debug, Clone, Copy)]
+struct ContactPoint {
+    r_a: Vec2,
+    r_b: Vec2,
+    normal_mass: f32,
+    tangent_mass: f32,
+    bias: f32,
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+/// A contact point's impulses kept for the next step
+#[derive(Debug, Clone, Copy)]
+struct CachedImpulse {
+    /// Point in body `a`'s rotated frame, relative to its position
+    local: Vec2,
+    normal: Vec2,
+    normal_impulse: f32,
+    tangent_impulse: f32,
+}
+
+/// Storage with handles that do not alias after removal
+#[derive(Debug, Clone)]
+struct Arena<T> {
+    slots: Vec<(u32, Option<T>)>,
+    free: Vec<usize>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<T> Arena<T> {
+    fn insert(&mut self, value: T) -> (usize, u32) {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.0 += 1;
+                slot.1 = Some(value);
+                (index, slot.0)
+            }
+            None => {
+                self.slots.push((0, Some(value)));
+                (self.slots.len() - 1, 0)
+            }
+        }
+    }
+
+    fn remove(&mut self, index: usize, generation: u32) -> Option<T> {
+        let slot = self.slots.get_mut(index)?;
+        if slot.0 != generation {
+            return None;
+        }
+        let value = slot.1.take()?;
+        self.free.push(index);
+        Some(value)
+    }
+
+    fn get(&self, index: usize, generation: u32) -> Option<&T> {
+        match self.slots.get(index)? {
+            (g, Some(value)) if *g == generation => Some(value),
+            _ => None,
+        }
+    }
+
+    fn get_mut(&mut self, index: usize, generation: u32) -> Option<&mut T> {
+        match self.slots.get_mut(index)? {
+            (g, Some(value)) if *g == generation => Some(value),
+            _ => None,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (usize, u32, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (g, value))| Some((i, *g, value.as_ref()?)))
+    }
+}
+
+pub struct World {
+    pub gravity: Vec2,
+    /// More iterations make stacks and joints stiffer but cost time
+    pub velocity_iterations: usize,
+    bodies: Arena<Body>,
+    joints: Arena<Joint>,
+    broadphase: SpatialHash,
+    contacts: Vec<Contact>,
+    /// Pairs touching after the last step, with the impulses to start the
+    /// next step from
+    touching: BTreeMap<(BodyHandle, BodyHandle), Vec<CachedImpulse>>,
+    events: Vec<CollisionEvent>,
+}
+
+impl World {
+    /// `cell_size` of the broadphase grid should be about the size of a
+    /// typical body, in game units
+    pub fn new(gravity: Vec2, cell_size: f32) -> Self {
+        Self {
+            gravity,
+            velocity_iterations: 8,
+            bodies: Arena::default(),
+            joints: Arena::default(),
+            broadphase: SpatialHash::new(cell_size),
+            contacts: Vec::new(),
+            touching: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn add_body(&mut self, body: Body) -> BodyHandle {
+        let (index, generation) = self.bodies.insert(body);
+        BodyHandle { index, generation }
+    }
+
+    /// Also removes the body's joints. Its contacts end with `Stopped`
+    /// events in the next step.
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<Body> {
+        let body = self.bodies.remove(handle.index, handle.generation)?;
+        self.broadphase.remove(handle.index);
+        let attached: Vec<JointHandle> = self
+            .joints()
+            .filter(|(_, joint)| {
+                let (a, b) = joint.bodies();
+                a == handle || b == handle
+            })
+            .map(|(h, _)| h)
+            .collect();
+        for joint in attached {
+            self.remove_joint(joint);
+        }
+        Some(body)
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&Body> {
+        self.bodies.get(handle.index, handle.generation)
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut Body> {
+        self.bodies.get_mut(handle.index, handle.generation)
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &Body)> {
+        self.bodies
+            .iter()
+            .map(|(index, generation, body)| (BodyHandle { index, generation }, body))
+    }
+
+    /// Keep world points `anchor_a` on `a` and `anchor_b` on `b` at their
+    /// current distance. `None` if a body does not exist.
+    pub fn add_distance_joint(
+        &mut self,
+        a: BodyHandle,
+        b: BodyHandle,
+        anchor_a: Vec2,
+        anchor_b: Vec2,
+    ) -> Option<JointHandle> {
+        let joint = Joint::Distance {
+            a,
+            b,
+            anchor_a: self.local_point(a, anchor_a)?,
+            anchor_b: self.local_point(b, anchor_b)?,
+            length: anchor_a.distance(anchor_b),
+        };
+        Some(self.add_joint(joint))
+    }
+
+    /// Pin `a` and `b` together at world point `anchor`
+    pub fn add_pin_joint(
+        &mut self,
+        a: BodyHandle,
+        b: BodyHandle,
+        anchor: Vec2,
+    ) -> Option<JointHandle> {
+        let joint = Joint::Pin {
+            a,
+            b,
+            anchor_a: self.local_point(a, anchor)?,
+            anchor_b: self.local_point(b, anchor)?,
+        };
+        Some(self.add_joint(joint))
+    }
+
+    fn add_joint(&mut self, joint: Joint) -> JointHandle {
+        let (index, generation) = self.joints.insert(joint);
+        JointHandle { index, generation }
+    }
+
+    pub fn remove_joint(&mut self, handle: JointHandle) -> Option<Joint> {
+        self.joints.remove(handle.index, handle.generation)
+    }
+
+    pub fn joint(&self, handle: JointHandle) -> Option<&Joint> {
+        self.joints.get(handle.index, handle.generation)
+    }
+
+    pub fn joints(&self) -> impl Iterator<Item = (JointHandle, &Joint)> {
+        self.joints
+            .iter()
+            .map(|(index, generation, joint)| (JointHandle { index, generation }, joint))
+    }
+
+    /// Contacts started and stopped during the last step
+    pub fn events(&self) -> &[CollisionEvent] {
+        &self.events
+    }
+
+    /// Everything touching after the last step
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Closest body hit by the ray, ignoring sensors. `None` for a zero
+    /// `direction`.
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+    ) -> Option<(BodyHandle, RayHit)> {
+        let direction = direction.normalize();
+        if direction == Vec2::ZERO {
+            return None;
+        }
+        let end = origin + direction * max_distance;
+        let area = Rect::from_min_max(origin, end);
+        let mut closest: Option<(BodyHandle, RayHit)> = None;
+        self.broadphase.query(area, |index| {
+            let Some((handle, body)) = self.handle_at(index) else {
+                return;
+            };
+            if body.collider.as_ref().is_some_and(|c| c.sensor) {
+                return;
+            }
+            let Some(hit) = body
+                .shape()
+                .and_then(|shape| shape.raycast(origin, direction, max_distance))
+            else {
+                return;
+            };
+            let closer = match &closest {
+                Some((other, best)) => (hit.distance, handle) < (best.distance, *other),
+                None => true,
+            };
+            if closer {
+                closest = Some((handle, hit));
+            }
+        });
+        closest
+    }
+
+    /// Advance the simulation by `dt` seconds
+    pub fn step(&mut self, dt: f32) {
+        self.events.clear();
+        if dt <= 0.0 {
+            return;
+        }
+
+        self.integrate_forces(dt);
+        self.find_contacts();
+        self.update_events();
+
+        let mut points = self.prepare_contacts();
+        self.warm_start(&points);
+        for _ in 0..self.velocity_iterations {
+            self.solve_joints(dt);
+            self.solve_contacts(&mut points);
+        }
+        self.touching = self
+            .contacts
+            .iter()
+            .zip(&points)
+            .map(|(contact, points)| {
+                let angle = self.body(contact.a).map_or(0.0, |a| a.angle);
+                let impulses = points
+                    .iter()
+                    .map(|point| CachedImpulse {
+                        local: point.r_a.rotate(-angle),
+                        normal: contact.manifold.normal,
+                        normal_impulse: point.normal_impulse,
+                        tangent_impulse: point.tangent_impulse,
+                    })
+                    .collect();
+                ((contact.a, contact.b), impulses)
+            })
+            .collect();
+
+        self.integrate_velocities(dt);
+        self.correct_positions();
+        self.correct_joints();
+    }
+
+    fn integrate_forces(&mut self, dt: f32) {
+        let gravity = self.gravity;
+        for (_, body) in self.bodies.slots.iter_mut() {
+            let Some(body) = body else { continue };
+            if body.kind == BodyKind::Dynamic {
+                body.velocity += (gravity * body.gravity_scale + body.force * body.inv_mass) * dt;
+                body.angular_velocity += body.torque * body.inv_inertia * dt;
+                body.velocity *= 1.0 / (1.0 + dt * body.linear_damping);
+                body.angular_velocity *= 1.0 / (1.0 + dt * body.angular_damping);
+            }
+            body.force = Vec2::ZERO;
+            body.torque = 0.0;
+        }
+    }
+
+    fn find_contacts(&mut self) {
+        let mut shapes = Vec::with_capacity(self.bodies.slots.len());
+        for (index, (_, body)) in self.bodies.slots.iter().enumerate() {
+            let shape = body.as_ref().and_then(Body::shape);
+            match &shape {
+                Some(shape) => self.broadphase.insert(index, shape.bounds()),
+                None => self.broadphase.remove(index),
+            }
+            shapes.push(shape);
+        }
+
+        // Sorted so the solver visits contacts in the same order every run
+        let mut pairs = Vec::new();
+        self.broadphase.pairs(|a, b| pairs.push((a, b)));
+        pairs.sort_unstable();
+
+        self.contacts.clear();
+        for (a, b) in pairs {
+            let (Some((handle_a, body_a)), Some((handle_b, body_b))) =
+                (self.handle_at(a), self.handle_at(b))
+            else {
+                continue;
+            };
+            if body_a.kind != BodyKind::Dynamic && body_b.kind != BodyKind::Dynamic {
+                continue;
+            }
+            let (Some(shape_a), Some(shape_b)) = (&shapes[a], &shapes[b]) else {
+                continue;
+            };
+            if let Some(manifold) = collision::overlap(shape_a, shape_b) {
+                let sensor = [body_a, body_b]
+                    .iter()
+                    .any(|body| body.collider.as_ref().is_some_and(|c| c.sensor));
+                self.contacts.push(Contact {
+                    a: handle_a,
+                    b: handle_b,
+                    manifold,
+                    sensor,
+                });
+            }
+        }
+    }
+
+    fn update_events(&mut self) {
+        let touching: BTreeSet<_> = self.contacts.iter().map(|c| (c.a, c.b)).collect();
+        for &(a, b) in self.touching.keys() {
+            if !touching.contains(&(a, b)) {
+                self.events.push(CollisionEvent::Stopped(a, b));
+            }
+        }
+        for &(a, b) in &touching {
+            if !self.touching.contains_key(&(a, b)) {
+                self.events.push(CollisionEvent::Started(a, b));
+            }
+        }
+    }
+
+    fn prepare_contacts(&self) -> Vec<Vec<ContactPoint>> {
+        self.contacts
+            .iter()
+            .map(|contact| {
+                if contact.sensor {
+                    return Vec::new();
+                }
+                let a = self.body(contact.a).unwrap();
+                let b = self.body(contact.b).unwrap();
+                let (ca, cb) = (a.collider().unwrap(), b.collider().unwrap());
+                let restitution = ca.restitution.max(cb.restitution);
+                let normal = contact.manifold.normal;
+                let tangent = normal.perp();
+                // Start from last step's impulses if the pair was touching.
+                // Manifolds do not keep their point order between steps, so
+                // points are matched by where they are on body `a`.
+                let previous = self
+                    .touching
+                    .get(&(contact.a, contact.b))
+                    .map_or(&[][..], Vec::as_slice);
+                contact
+                    .manifold
+                    .points()
+                    .iter()
+                    .map(|&p| {
+                        let (r_a, r_b) = (p - a.position, p - b.position);
+                        let local = r_a.rotate(-a.angle);
+                        let (normal_impulse, tangent_impulse) = previous
+                            .iter()
+                            .filter(|cached| cached.normal.dot(normal) > WARM_START_ALIGNMENT)
+                            .min_by(|x, y| {
+                                let dx = x.local.distance_squared(local);
+                                dx.total_cmp(&y.local.distance_squared(local))
+                            })
+                            .map_or((0.0, 0.0), |cached| {
+                                (cached.normal_impulse, cached.tangent_impulse)
+                            });
+                        let speed = (b.velocity_at(p) - a.velocity_at(p)).dot(normal);
+                        ContactPoint {
+                            r_a,
+                            r_b,
+                            normal_mass: inverse(effective_mass(a, b, r_a, r_b, normal)),
+                            tangent_mass: inverse(effective_mass(a, b, r_a, r_b, tangent)),
+                            bias: if speed < -RESTITUTION_THRESHOLD {
+                                -restitution * speed
+                            } else {
+                                0.0
+                            },
+                            normal_impulse,
+                            tangent_impulse,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Apply the impulses contacts start with, so resting bodies begin the
+    /// step already supported
+    fn warm_start(&mut self, points: &[Vec<ContactPoint>]) {
+        for (contact, points) in self.contacts.iter().zip(points) {
+            let Some((a, b)) = self.bodies.pair_mut(contact.a, contact.b) else {
+                continue;
+            };
+            let normal = contact.manifold.normal;
+            for point in points {
+                let impulse = normal * point.normal_impulse + normal.perp() * point.tangent_impulse;
+                apply(a, b, point.r_a, point.r_b, impulse);
+            }
+        }
+    }
+
+    fn solve_contacts(&mut self, points: &mut [Vec<ContactPoint>]) {
+        for (contact, points) in self.contacts.iter().zip(points) {
+            if points.is_empty() {
+                continue;
+            }
+            let Some((a, b)) = self.bodies.pair_mut(contact.a, contact.b) else {
+                continue;
+            };
+            let friction = (a.collider().unwrap().friction * b.collider().unwrap().friction).sqrt();
+            let normal = contact.manifold.normal;
+            let tangent = normal.perp();
+            for point in points.iter_mut() {
+                let (r_a, r_b) = (point.r_a, point.r_b);
+                let relative = |a: &Body, b: &Body| {
+                    b.velocity + r_b.perp() * b.angular_velocity
+                        - a.velocity
+                        - r_a.perp() * a.angular_velocity
+                };
+
+                // Push apart, never pull together
+                let speed = relative(a, b).dot(normal);
+                let impulse = point.normal_mass * (point.bias - speed);
+                let total = (point.normal_impulse + impulse).max(0.0);
+                let impulse = total - point.normal_impulse;
+                point.normal_impulse = total;
+                apply(a, b, r_a, r_b, normal * impulse);
+
+                // Friction, limited by how hard the bodies are pressed together
+                let speed = relative(a, b).dot(tangent);
+                let impulse = -point.tangent_mass * speed;
+                let limit = friction * point.normal_impulse;
+                let total = (point.tangent_impulse + impulse).clamp(-limit, limit);
+                let impulse = total - point.tangent_impulse;
+                point.tangent_impulse = total;
+                apply(a, b, r_a, r_b, tangent * impulse);
+            }
+        }
+    }
+
+    fn solve_joints(&mut self, dt: f32) {
+        for (_, joint) in self.joints.slots.iter() {
+            let Some(joint) = joint else { continue };
+            let (a, b) = joint.bodies();
+            let Some((a, b)) = self.bodies.pair_mut(a, b) else {
+                continue;
+            };
+            match *joint {
+                Joint::Distance {
+                    anchor_a,
+                    anchor_b,
+                    length,
+                    ..
+                } => {
+                    let r_a = anchor_a.rotate(a.angle);
+                    let r_b = anchor_b.rotate(b.angle);
+                    let delta = b.position + r_b - a.position - r_a;
+                    let distance = delta.length();
+                    if distance == 0.0 {
+                        continue;
+                    }
+                    let axis = delta / distance;
+                    let speed = (b.velocity_at(b.position + r_b) - a.velocity_at(a.position + r_a))
+                        .dot(axis);
+                    let bias = JOINT_CORRECTION * (distance - length) / dt;
+                    let impulse = -inverse(effective_mass(a, b, r_a, r_b, axis)) * (speed + bias);
+                    apply(a, b, r_a, r_b, axis * impulse);
+                }
+                Joint::Pin {
+                    anchor_a, anchor_b, ..
+                } => {
+                    let r_a = anchor_a.rotate(a.angle);
+                    let r_b = anchor_b.rotate(b.angle);
+                    let error = b.position + r_b - a.position - r_a;
+                    let velocity =
+                        b.velocity_at(b.position + r_b) - a.velocity_at(a.position + r_a);
+                    let target = -(velocity + error * (JOINT_CORRECTION / dt));
+                    apply(a, b, r_a, r_b, solve_point_mass(a, b, r_a, r_b, target));
+                }
+            }
+        }
+    }
+
+    fn integrate_velocities(&mut self, dt: f32) {
+        for (_, body) in self.bodies.slots.iter_mut() {
+            let Some(body) = body else { continue };
+            if body.kind == BodyKind::Static {
+                continue;
+            }
+            body.position += body.velocity * dt;
+            if !body.fixed_rotation {
+                body.angle += body.angular_velocity * dt;
+            }
+        }
+    }
+
+    /// Move overlapping bodies apart directly, so penetration does not build
+    /// up under stacks. Each point is corrected separately, which also turns
+    /// a tilted body flat against what it rests on.
+    fn correct_positions(&mut self) {
+        for contact in &self.contacts {
+            if contact.sensor {
+                continue;
+            }
+            let Some((a, b)) = self.bodies.pair_mut(contact.a, contact.b) else {
+                continue;
+            };
+            let normal = contact.manifold.normal;
+            let start = [(a.position, a.angle), (b.position, b.angle)];
+            // How far the point on `body` moved since the first correction
+            let moved = |body: &Body, (position, angle): (Vec2, f32), r: Vec2| {
+                body.position - position + r.perp() * (body.angle - angle)
+            };
+            let manifold = &contact.manifold;
+            for (&p, &depth) in manifold.points().iter().zip(manifold.depths()) {
+                let (r_a, r_b) = (p - start[0].0, p - start[1].0);
+                let depth = depth - (moved(b, start[1], r_b) - moved(a, start[0], r_a)).dot(normal);
+                if depth <= POSITION_SLOP {
+                    continue;
+                }
+                let mass = inverse(effective_mass(a, b, r_a, r_b, normal));
+                let correction = normal * ((depth - POSITION_SLOP) * POSITION_CORRECTION * mass);
+                shift(a, b, r_a, r_b, correction);
+            }
+        }
+    }
+
+    /// Move jointed bodies back into place, as velocities alone let joints
+    /// stretch while bodies swing
+    fn correct_joints(&mut self) {
+        for (_, joint) in self.joints.slots.iter() {
+            let Some(joint) = joint else { continue };
+            let (a, b) = joint.bodies();
+            let Some((a, b)) = self.bodies.pair_mut(a, b) else {
+                continue;
+            };
+            let (anchor_a, anchor_b) = joint.anchors();
+            let r_a = anchor_a.rotate(a.angle);
+            let r_b = anchor_b.rotate(b.angle);
+            let error = b.position + r_b - a.position - r_a;
+            let correction = match *joint {
+                Joint::Distance { length, .. } => {
+                    let distance = error.length();
+                    if distance == 0.0 {
+                        continue;
+                    }
+                    let axis = error / distance;
+                    let mass = inverse(effective_mass(a, b, r_a, r_b, axis));
+                    axis * -((distance - length) * mass)
+                }
+                Joint::Pin { .. } => solve_point_mass(a, b, r_a, r_b, -error),
+            };
+            shift(a, b, r_a, r_b, correction * POSITION_CORRECTION);
+        }
+    }
+
+    fn handle_at(&self, index: usize) -> Option<(BodyHandle, &Body)> {
+        let (generation, body) = self.bodies.slots.get(index)?;
+        let handle = BodyHandle {
+            index,
+            generation: *generation,
+        };
+        Some((handle, body.as_ref()?))
+    }
+
+    fn local_point(&self, handle: BodyHandle, point: Vec2) -> Option<Vec2> {
+        let body = self.body(handle)?;
+        Some((point - body.position).rotate(-body.angle))
+    }
+
+    /// Outline every collider, joint and contact point, for debugging
+    pub fn debug_draw(&self, gfx: &mut Graphics) {
+        const LINE: f32 = 1.0;
+
+        for (_, body) in self.bodies() {
+            let Some(shape) = body.shape() else { continue };
+            let color = match body.kind {
+                _ if body.collider.as_ref().is_some_and(|c| c.sensor) => css::YELLOW,
+                BodyKind::Dynamic => css::LIGHT_GREEN,
+                BodyKind::Static => css::GRAY,
+                BodyKind::Kinematic => css::LIGHT_BLUE,
+            };
+            draw_shape(gfx, &shape, body.angle, color, LINE);
+        }
+
+        for (_, joint) in self.joints() {
+            let (a, b) = joint.bodies();
+            let (Some(a), Some(b)) = (self.body(a), self.body(b)) else {
+                continue;
+            };
+            let (anchor_a, anchor_b) = joint.anchors();
+            let p = a.position + anchor_a.rotate(a.angle);
+            let q = b.position + anchor_b.rotate(b.angle);
+            gfx.draw_line(p.x, p.y, q.x, q.y, LINE, css::ORANGE);
+        }
+
+        for contact in &self.contacts {
+            for p in contact.manifold.points() {
+                gfx.draw_rect(p.x - 2.0, p.y - 2.0, 4.0, 4.0, css::RED);
+                let n = *p + contact.manifold.normal * 8.0;
+                gfx.draw_line(p.x, p.y, n.x, n.y, LINE, css::RED);
+            }
+        }
+    }
+}
+
+impl Joint {
+    pub fn bodies(&self) -> (BodyHandle, BodyHandle) {
+        match *self {
+            Joint::Distance { a, b, .. } | Joint::Pin { a, b, .. } => (a, b),
+        }
+    }
+
+    /// Anchors in each body's local coordinates
+    pub fn anchors(&self) -> (Vec2, Vec2) {
+        match *self {
+            Joint::Distance {
+                anchor_a, anchor_b, ..
+            }
+            | Joint::Pin {
+                anchor_a, anchor_b, ..
+            } => (anchor_a, anchor_b),
+        }
+    }
+}
+
+impl Arena<Body> {
+    /// Both bodies mutably; `None` if either is missing or they are the same
+    fn pair_mut(&mut self, a: BodyHandle, b: BodyHandle) -> Option<(&mut Body, &mut Body)> {
+        if a.index == b.index {
+            return None;
+        }
+        let (low, high) = (a.index.min(b.index), a.index.max(b.index));
+        let (left, right) = self.slots.split_at_mut(high);
+        let (first, second) = (&mut left[low], &mut right[0]);
+        let (slot_a, slot_b) = if a.index < b.index {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        if slot_a.0 != a.generation || slot_b.0 != b.generation {
+            return None;
+        }
+        Some((slot_a.1.as_mut()?, slot_b.1.as_mut()?))
+    }
+}
+
+/// How hard it is to change the relative velocity along `axis` at the contact
+fn effective_mass(a: &Body, b: &Body, r_a: Vec2, r_b: Vec2, axis: Vec2) -> f32 {
+    let ra = r_a.cross(axis);
+    let rb = r_b.cross(axis);
+    a.inv_mass + b.inv_mass + a.inv_inertia * ra * ra + b.inv_inertia * rb * rb
+}
+
+fn inverse(v: f32) -> f32 {
+    if v > 0.0 {
+        1.0 / v
+    } else {
+        0.0
+    }
+}
+
+/// Impulse that changes the relative velocity at the anchor by `target`
+fn solve_point_mass(a: &Body, b: &Body, r_a: Vec2, r_b: Vec2, target: Vec2) -> Vec2 {
+    let m = a.inv_mass + b.inv_mass;
+    let (ia, ib) = (a.inv_inertia, b.inv_inertia);
+    let k11 = m + ia * r_a.y * r_a.y + ib * r_b.y * r_b.y;
+    let k12 = -ia * r_a.x * r_a.y - ib * r_b.x * r_b.y;
+    let k22 = m + ia * r_a.x * r_a.x + ib * r_b.x * r_b.x;
+    let det = k11 * k22 - k12 * k12;
+    if det == 0.0 {
+        return Vec2::ZERO;
+    }
+    Vec2::new(
+        (k22 * target.x - k12 * target.y) / det,
+        (k11 * target.y - k12 * target.x) / det,
+    )
+}
+
+/// Apply `impulse` to `b` and its opposite to `a`
+fn apply(a: &mut Body, b: &mut Body, r_a: Vec2, r_b: Vec2, impulse: Vec2) {
+    a.velocity -= impulse * a.inv_mass;
+    a.angular_velocity -= r_a.cross(impulse) * a.inv_inertia;
+    b.velocity += impulse * b.inv_mass;
+    b.angular_velocity += r_b.cross(impulse) * b.inv_inertia;
+}
+
+/// Move `b` and `a` apart as `apply` would change their velocities
+fn shift(a: &mut Body, b: &mut Body, r_a: Vec2, r_b: Vec2, correction: Vec2) {
+    a.position -= correction * a.inv_mass;
+    a.angle -= r_a.cross(correction) * a.inv_inertia;
+    b.position += correction * b.inv_mass;
+    b.angle += r_b.cross(correction) * b.inv_inertia;
+}
+
+fn draw_shape(gfx: &mut Graphics, shape: &Shape, angle: f32, color: Color, width: f32) {
+    let outline = |gfx: &mut Graphics, points: &[Vec2]| {
+        for (i, p) in points.iter().enumerate() {
+            let q = points[(i + 1) % points.len()];
+            gfx.draw_line(p.x, p.y, q.x, q.y, width, color);
+        }
+    };
+    match shape {
+        Shape::Rect(rect) => {
+            let (min, max) = (rect.min(), rect.max());
+            outline(
+                gfx,
+                &[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)],
+            );
+        }
+        Shape::Obb(obb) => outline(gfx, &obb.corners()),
+        Shape::Polygon(polygon) => outline(gfx, polygon.points()),
+        Shape::Circle(circle) => {
+            const SEGMENTS: usize = 20;
+            let points: Vec<Vec2> = (0..SEGMENTS)
+                .map(|i| {
+                    let a = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+                    circle.center + Vec2::from_angle(a) * circle.radius
+                })
+                .collect();
+            outline(gfx, &points);
+            // A spoke, so rotation shows
+            let spoke = circle.center + Vec2::from_angle(angle) * circle.radius;
+            gfx.draw_line(
+                circle.center.x,
+                circle.center.y,
+                spoke.x,
+                spoke.y,
+                width,
+                color,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn count(events: &[CollisionEvent]) -> (usize, usize) {
+        let started = events
+            .iter()
+            .filter(|e| matches!(e, CollisionEvent::Started(..)))
+            .count();
+        (started, events.len() - started)
+    }
+
+    #[test]
+    fn box_comes_to_rest_on_the_ground() {
+        let square = Polygon::new([
+            Vec2::new(-10.0, -10.0),
+            Vec2::new(10.0, -10.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(-10.0, 10.0),
+        ])
+        .unwrap();
+        let body = Body::dynamic(Vec2::new(0.0, 50.0));
+        // A box that cannot turn always collides as a `Shape::Rect`, the
+        // others as an oriented box and as a polygon
+        for body in [
+            body.clone()
+                .with_collider(Collider::rect(20.0, 20.0))
+                .with_fixed_rotation(),
+            body.clone().with_collider(Collider::rect(20.0, 20.0)),
+            body.with_collider(Collider::polygon(square)),
+        ] {
+            let mut world = World::new(Vec2::new(0.0, 500.0), 50.0);
+            let ground = world.add_body(
+                Body::fixed(Vec2::new(0.0, 100.0)).with_collider(Collider::rect(400.0, 20.0)),
+            );
+            let top = world.add_body(body);
+
+            let mut events = Vec::new();
+            for _ in 0..180 {
+                world.step(DT);
+                events.extend_from_slice(world.events());
+            }
+            assert_eq!(events, [CollisionEvent::Started(ground, top)]);
+
+            // Resting on the ground's top at y = 90, within the slop
+            let body = world.body(top).unwrap();
+            assert!(
+                (body.position.y - 80.0).abs() < POSITION_SLOP * 2.0,
+                "{body:?}"
+            );
+            assert!(body.position.x.abs() < 1e-3, "{body:?}");
+            assert!(body.angle.abs() < 1e-4, "{body:?}");
+            assert!(body.velocity.length() < 1.0, "{body:?}");
+
+            world.remove_body(ground);
+            world.step(DT);
+            events.extend_from_slice(world.events());
+            world.step(DT);
+            events.extend_from_slice(world.events());
+            assert_eq!(count(&events), (1, 1));
+            assert_eq!(events[1], CollisionEvent::Stopped(ground, top));
+        }
+    }
+
+    #[test]
+    fn pendulum_keeps_its_length() {
+        let mut world = World::new(Vec2::new(0.0, 500.0), 50.0);
+        let pivot = world.add_body(Body::fixed(Vec2::ZERO));
+        let bob = world
+            .add_body(Body::dynamic(Vec2::new(100.0, 0.0)).with_collider(Collider::circle(5.0)));
+        world
+            .add_distance_joint(pivot, bob, Vec2::ZERO, Vec2::new(100.0, 0.0))
+            .unwrap();
+
+        let mut lowest = 0.0f32;
+        for _ in 0..300 {
+            world.step(DT);
+            let position = world.body(bob).unwrap().position;
+            assert!((position.length() - 100.0).abs() < 1.0, "{position:?}");
+            lowest = lowest.max(position.y);
+        }
+        // It swung through the bottom
+        assert!(lowest > 99.0, "{lowest}");
+    }
+
+    #[test]
+    fn pin_joint_holds_the_anchor() {
+        let mut world = World::new(Vec2::new(0.0, 500.0), 50.0);
+        let pivot = world.add_body(Body::fixed(Vec2::ZERO));
+        let plank = world.add_body(
+            Body::dynamic(Vec2::new(50.0, 0.0)).with_collider(Collider::rect(100.0, 10.0)),
+        );
+        world.add_pin_joint(pivot, plank, Vec2::ZERO).unwrap();
+
+        for _ in 0..300 {
+            world.step(DT);
+            let body = world.body(plank).unwrap();
+            let anchor = body.position + Vec2::new(-50.0, 0.0).rotate(body.angle);
+            assert!(anchor.length() < 1.0, "{anchor:?}");
+        }
+        // Hanging down by now, turned about a quarter
+        assert!(world.body(plank).unwrap().angle > 0.5);
+    }
+
+    #[test]
+    fn removing_a_body_removes_its_joints() {
+        let mut world = World::new(Vec2::ZERO, 50.0);
+        let a = world.add_body(Body::fixed(Vec2::ZERO));
+        let b = world.add_body(Body::dynamic(Vec2::new(10.0, 0.0)));
+        let c = world.add_body(Body::dynamic(Vec2::new(20.0, 0.0)));
+        world.add_pin_joint(a, b, Vec2::new(5.0, 0.0)).unwrap();
+        let kept = world
+            .add_distance_joint(a, c, Vec2::ZERO, Vec2::new(20.0, 0.0))
+            .unwrap();
+
+        world.remove_body(b).unwrap();
+        let joints: Vec<JointHandle> = world.joints().map(|(handle, _)| handle).collect();
+        assert_eq!(joints, [kept]);
+        assert!(world.body(b).is_none());
+        assert!(world.add_pin_joint(a, b, Vec2::ZERO).is_none());
+    }
+
+    #[test]
+    fn sensors_report_contacts_without_pushing() {
+        let mut world = World::new(Vec2::ZERO, 50.0);
+        let sensor = world.add_body(
+            Body::fixed(Vec2::new(50.0, 0.0)).with_collider(Collider::rect(20.0, 20.0).sensor()),
+        );
+        let ball = world.add_body(
+            Body::dynamic(Vec2::ZERO)
+                .with_collider(Collider::circle(5.0))
+                .with_velocity(Vec2::new(600.0, 0.0)),
+        );
+
+        let mut events = Vec::new();
+        for _ in 0..20 {
+            world.step(DT);
+            events.extend_from_slice(world.events());
+            if world.contacts().iter().any(|c| c.sensor) {
+                assert_eq!(world.contacts().len(), 1);
+            }
+            assert_eq!(world.body(ball).unwrap().velocity, Vec2::new(600.0, 0.0));
+        }
+        assert_eq!(
+            events,
+            [
+                CollisionEvent::Started(sensor, ball),
+                CollisionEvent::Stopped(sensor, ball)
+            ]
+        );
+        // Went straight through
+        let position = world.body(ball).unwrap().position;
+        assert!((position.x - 200.0).abs() < 1e-3, "{position:?}");
+        assert_eq!(position.y, 0.0);
+    }
+
+    #[test]
+    fn kinematic_bodies_follow_their_velocity() {
+        let mut world = World::new(Vec2::new(0.0, 500.0), 50.0);
+        let platform = world.add_body(
+            Body::kinematic(Vec2::new(0.0, 100.0))
+                .with_collider(Collider::rect(100.0, 10.0))
+                .with_velocity(Vec2::new(0.0, -60.0)),
+        );
+        let top = world.add_body(
+            Body::dynamic(Vec2::new(0.0, 90.0)).with_collider(Collider::rect(10.0, 10.0)),
+        );
+
+        for _ in 0..60 {
+            world.step(DT);
+        }
+        // Not slowed by gravity or by what it carries
+        let body = world.body(platform).unwrap();
+        assert!((body.position.y - 40.0).abs() < 1e-3, "{body:?}");
+        assert_eq!(body.velocity, Vec2::new(0.0, -60.0));
+        // Carried up on top of it
+        let top = world.body(top).unwrap();
+        assert!((top.position.y - 30.0).abs() < 1.0, "{top:?}");
+    }
+
+    #[test]
+    fn raycast_skips_sensors() {
+        let mut world = World::new(Vec2::ZERO, 50.0);
+        world.add_body(
+            Body::fixed(Vec2::new(20.0, 0.0)).with_collider(Collider::rect(10.0, 10.0).sensor()),
+        );
+        let wall = world
+            .add_body(Body::fixed(Vec2::new(50.0, 0.0)).with_collider(Collider::rect(10.0, 10.0)));
+        world.step(DT);
+
+        let (handle, hit) = world.raycast(Vec2::ZERO, Vec2::X, 100.0).unwrap();
+        assert_eq!(handle, wall);
+        assert!((hit.distance - 45.0).abs() < 1e-4, "{hit:?}");
+        assert!(world.raycast(Vec2::ZERO, Vec2::X, 40.0).is_none());
+        assert!(world.raycast(Vec2::ZERO, -Vec2::X, 100.0).is_none());
+        assert!(world.raycast(Vec2::ZERO, Vec2::ZERO, 100.0).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+// panpan/src/platform.rs
+// Requests from game code that only the platform runner can carry out, and
+// the window state the runner reports back
+//
+// Each `Context` owns one `Platform`. While the context is current (see
+// `Context::scope`) its state is lent to this thread, so the free functions
+// in `window` and `input` reach it without a `Context` argument.
+
+use crate::types::Rect;
+use crate::window::{CoordinateSpace, WindowMode};
+use std::cell::RefCell;
+
+thread_local! {
+    /// Platform state of the context that is current on this thread
+    static CURRENT: RefCell<Option<PlatformState>> = const { RefCell::new(None) };
+}
+
+/// Something the game asked the runner to do. Runners drain these once per
+/// frame with `__internal_take_platform_requests`.
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlatformRequest {
+    StartTextInput,
+    StopTextInput,
+    SetTextInputArea(Rect),
+    SetWindowMode(WindowMode),
+    SetVsync(bool),
+    SetTargetFps(Option<u32>),
+    SetWindowSize(u32, u32),
+}
+
+/// What a game and its runner share besides rendering and input
+#[doc(hidden)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlatformState {
+    requests: Vec<PlatformRequest>,
+    text_input_active: bool,
+    scale_factor: f32,
+    coordinate_space: CoordinateSpace,
+}
+
+impl Default for PlatformState {
+    fn default() -> Self {
+        Self {
+            requests: Vec::new(),
+            text_input_active: false,
+            scale_factor: 1.0,
+            coordinate_space: CoordinateSpace::Logical,
+        }
+    }
+}
+
+impl PlatformState {
+    pub(crate) fn push(&mut self, request: PlatformRequest) {
+        match request {
+            PlatformRequest::StartTextInput => self.text_input_active = true,
+            PlatformRequest::StopTextInput => self.text_input_active = false,
+            _ => {}
+        }
+        self.requests.push(request);
+    }
+
+    pub(crate) fn take(&mut self) -> Vec<PlatformRequest> {
+        std::mem::take(&mut self.requests)
+    }
+
+    pub(crate) fn is_text_input_active(&self) -> bool {
+        self.text_input_active
+    }
+
+    pub(crate) fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    pub(crate) fn set_scale_factor(&mut self, scale: f32) {
+        self.scale_factor = if scale > 0.0 { scale } else { 1.0 };
+    }
+
+    pub(crate) fn coordinate_space(&self) -> CoordinateSpace {
+        self.coordinate_space
+    }
+
+    pub(crate) fn set_coordinate_space(&mut self, space: CoordinateSpace) {
+        self.coordinate_space = space;
+    }
+
+    pub(crate) fn pixel_ratio(&self) -> f32 {
+        match self.coordinate_space {
+            CoordinateSpace::Logical => self.scale_factor,
+            CoordinateSpace::Physical => 1.0,
+        }
+    }
+
+    /// Internal: make this state current on this thread while `f` runs.
+    /// Hot reload uses it to hand the state to the library's own copy of
+    /// panpan for callbacks that get no `Context`.
+    #[doc(hidden)]
+    pub fn __internal_scope<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let outer = CURRENT.with(|current| current.replace(Some(std::mem::take(self))));
+        let result = f();
+        *self = CURRENT
+            .with(|current| current.replace(outer))
+            .unwrap_or_default();
+        result
+    }
+}
+
+/// Platform state of a `Context`, lent to `CURRENT` while the context is
+/// current, the same way `Graphics` lends its renderer
+#[derive(Debug, Default)]
+pub(crate) struct Platform {
+    state: PlatformState,
+    lent: bool,
+    /// What `CURRENT` held before lending, put back afterwards
+    outer: Option<PlatformState>,
+}
+
+impl Platform {
+    /// Make the state current on this thread. Returns false if it already
+    /// is.
+    pub(crate) fn lend(&mut self) -> bool {
+        if self.lent {
+            return false;
+        }
+        let state = std::mem::take(&mut self.state);
+        self.outer = CURRENT.with(|current| current.replace(Some(state)));
+        self.lent = true;
+        true
+    }
+
+    pub(crate) fn is_lent(&self) -> bool {
+        self.lent
+    }
+
+    /// Take the state back after `lend`
+    pub(crate) fn reclaim(&mut self) {
+        let outer = self.outer.take();
+        self.state = CURRENT
+            .with(|current| current.replace(outer))
+            .unwrap_or_default();
+        self.lent = false;
+    }
+
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&PlatformState) -> R) -> R {
+        if self.lent {
+            CURRENT.with(|current| f(current.borrow().as_ref().unwrap_or(&self.state)))
+        } else {
+            f(&self.state)
+        }
+    }
+
+    pub(crate) fn with_mut<R>(&mut self, f: impl FnOnce(&mut PlatformState) -> R) -> R {
+        if self.lent {
+            CURRENT.with(|current| match current.borrow_mut().as_mut() {
+                Some(state) => f(state),
+                None => f(&mut self.state),
+            })
+        } else {
+            f(&mut self.state)
+        }
+    }
+}
+
+/// Run `f` with the current context's platform state, if there is one
+pub(crate) fn with_current<R>(f: impl FnOnce(&mut PlatformState) -> R) -> Option<R> {
+    CURRENT.with(|current| current.borrow_mut().as_mut().map(f))
+}
+
+/// Queue a request on the current context. Outside of a context there is
+/// no runner to carry it out, so it is dropped.
+pub(crate) fn push(request: PlatformRequest) {
+    with_current(|state| state.push(request));
+}
+
+/// Internal: run `f` with the state lent by the current context, taken back
+/// from this copy of panpan, for hot reload to hand it to the library's
+/// copy. Without a current context `f` gets a throwaway state.
+#[doc(hidden)]
+pub fn __internal_unscoped<R>(f: impl FnOnce(&mut PlatformState) -> R) -> R {
+    match CURRENT.with(|current| current.borrow_mut().take()) {
+        Some(mut state) => {
+            let result = f(&mut state);
+            CURRENT.with(|current| *current.borrow_mut() = Some(state));
+            result
+        }
+        None => f(&mut PlatformState::default()),
+    }
+}
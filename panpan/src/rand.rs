@@ -0,0 +1,339 @@
+// panpan/src/rand.rs
+// Seedable random numbers that give the same results on every platform
+//
+// `Rng` is PCG32 (XSH-RR). Everything here uses integer math and IEEE
+// operations with exact results (no sin/cos), so the same seed produces the
+// same game on every platform, which replays and lockstep networking rely on.
+
+use crate::types::Vec2;
+use std::ops::{Range, RangeInclusive};
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rng {
+    state: u64,
+    /// Selects the stream, always odd
+    increment: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, 0)
+    }
+
+    /// Generators with the same seed but different streams produce unrelated
+    /// sequences, e.g. one stream for level generation and one for effects
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    /// A new independent generator seeded from this one
+    pub fn split(&mut self) -> Rng {
+        let seed = self.next_u64();
+        let stream = self.next_u64();
+        Rng::with_stream(seed, stream)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    /// Uniform in 0.0..1.0
+    pub fn f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Uniform in 0.0..1.0
+    pub fn f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// True with probability `p`
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.f32() < p
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u32() & 1 == 1
+    }
+
+    /// Uniform in `range`, for integer and float ranges: `rng.range(1..=6)`,
+    /// `rng.range(-1.0..1.0)`. Panics if an integer range is empty.
+    pub fn range<R: SampleRange>(&mut self, range: R) -> R::Output {
+        range.sample(self)
+    }
+
+    /// Uniform in 0..n without modulo bias
+    fn below(&mut self, n: u64) -> u64 {
+        // Lemire's multiply-and-reject
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = self.next_u64() as u128 * n as u128;
+            if product as u64 >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+
+    /// Uniformly distributed inside a circle of `radius` around the origin
+    pub fn in_circle(&mut self, radius: f32) -> Vec2 {
+        loop {
+            let p = Vec2::new(self.range(-1.0..1.0), self.range(-1.0..1.0));
+            if p.length_squared() <= 1.0 {
+                return p * radius;
+            }
+        }
+    }
+
+    /// Random direction of length 1
+    pub fn on_unit_circle(&mut self) -> Vec2 {
+        loop {
+            let p = Vec2::new(self.range(-1.0..1.0), self.range(-1.0..1.0));
+            let length_squared = p.length_squared();
+            if length_squared <= 1.0 && length_squared > 1e-4 {
+                return p / length_squared.sqrt();
+            }
+        }
+    }
+
+    /// Random element, `None` if `items` is empty
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(&items[self.range(0..items.len())])
+    }
+
+    /// Index picked with probability proportional to its weight. `None` if
+    /// there are no positive weights; negative weights count as zero.
+    pub fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = self.f32() * total;
+        let mut last = None;
+        for (i, w) in weights.iter().enumerate() {
+            if *w <= 0.0 {
+                continue;
+            }
+            if pick < *w {
+                return Some(i);
+            }
+            pick -= w;
+            last = Some(i);
+        }
+        // Rounding can leave `pick` just above the last weight
+        last
+    }
+
+    /// Random element with probability proportional to `weight(item)`
+    pub fn choose_weighted<'a, T>(
+        &mut self,
+        items: &'a [T],
+        weight: impl Fn(&T) -> f32,
+    ) -> Option<&'a T> {
+        let weights: Vec<f32> = items.iter().map(weight).collect();
+        self.weighted_index(&weights).map(|i| &items[i])
+    }
+
+    /// Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.range(0..=i);
+            items.swap(i, j);
+        }
+    }
+}
+
+/// Ranges `Rng::range` can sample from
+pub trait SampleRange {
+    type Output;
+    fn sample(self, rng: &mut Rng) -> Self::Output;
+}
+
+macro_rules! int_ranges {
+    ($($t:ty => $u:ty),*) => {$(
+        impl SampleRange for Range<$t> {
+            type Output = $t;
+            fn sample(self, rng: &mut Rng) -> $t {
+                assert!(self.start < self.end, "empty range {:?}", self);
+                let span = self.end.wrapping_sub(self.start) as $u as u64;
+                self.start.wrapping_add(rng.below(span) as $t)
+            }
+        }
+
+        impl SampleRange for RangeInclusive<$t> {
+            type Output = $t;
+            fn sample(self, rng: &mut Rng) -> $t {
+                let (start, end) = self.into_inner();
+                assert!(start <= end, "empty range {:?}", start..=end);
+                let span = end.wrapping_sub(start) as $u as u64;
+                let offset = match span.checked_add(1) {
+                    Some(n) => rng.below(n),
+                    // The whole 64-bit range
+                    None => rng.next_u64(),
+                };
+                start.wrapping_add(offset as $t)
+            }
+        }
+    )*};
+}
+
+int_ranges!(
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64,
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64,
+    usize => usize, isize => usize
+);
+
+// Only f32, so untyped literals like `-1.0..1.0` are not ambiguous; use
+// `Rng::f64` for doubles
+impl SampleRange for Range<f32> {
+    type Output = f32;
+    fn sample(self, rng: &mut Rng) -> f32 {
+        let value = self.start + (self.end - self.start) * rng.f32();
+        // Rounding can land exactly on `end`
+        if value < self.end {
+            value
+        } else {
+            self.start
+        }
+    }
+}
+
+impl SampleRange for RangeInclusive<f32> {
+    type Output = f32;
+    fn sample(self, rng: &mut Rng) -> f32 {
+        let (start, end) = self.into_inner();
+        (start + (end - start) * rng.f32()).min(end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(rng: &mut Rng, n: usize) -> Vec<u32> {
+        (0..n).map(|_| rng.next_u32()).collect()
+    }
+
+    #[test]
+    fn matches_the_pcg32_reference() {
+        // pcg32-demo from the PCG reference implementation: seed 42, stream 54
+        let mut rng = Rng::with_stream(42, 54);
+        assert_eq!(
+            take(&mut rng, 6),
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let same = take(&mut Rng::new(7), 16);
+        assert_eq!(take(&mut Rng::new(7), 16), same);
+        assert_eq!(take(&mut Rng::with_stream(7, 0), 16), same);
+        assert_ne!(take(&mut Rng::with_stream(7, 1), 16), same);
+        assert_ne!(take(&mut Rng::new(8), 16), same);
+
+        let mut parent = Rng::new(7);
+        let mut child = parent.split();
+        assert_ne!(take(&mut child, 16), take(&mut parent, 16));
+    }
+
+    #[test]
+    fn int_ranges_stay_in_bounds() {
+        let mut rng = Rng::new(1);
+        let mut seen = [false; 7];
+        for _ in 0..1000 {
+            let value = rng.range(-3..4);
+            assert!((-3..4).contains(&value));
+            seen[(value + 3) as usize] = true;
+
+            let die = rng.range(1..=6u8);
+            assert!((1..=6).contains(&die));
+            assert_eq!(rng.range(5..6), 5);
+            assert_eq!(rng.range(5..=5), 5);
+            assert!(rng.range(250..=u8::MAX) >= 250);
+            assert!(rng.range(0..usize::MAX) < usize::MAX);
+            rng.range(i64::MIN..=i64::MAX);
+        }
+        assert!(seen.iter().all(|&s| s), "{seen:?}");
+    }
+
+    #[test]
+    #[should_panic(expected = "empty range")]
+    fn empty_int_range_panics() {
+        Rng::new(1).range(3..3);
+    }
+
+    #[test]
+    fn float_ranges_stay_in_bounds() {
+        let mut rng = Rng::new(2);
+        for _ in 0..1000 {
+            let value = rng.range(-1.0..1.0);
+            assert!((-1.0..1.0).contains(&value), "{value}");
+            let value = rng.range(10.0..=10.5);
+            assert!((10.0..=10.5).contains(&value), "{value}");
+            assert_eq!(rng.range(2.0..=2.0), 2.0);
+            let unit = rng.f32();
+            assert!((0.0..1.0).contains(&unit));
+            let unit = rng.f64();
+            assert!((0.0..1.0).contains(&unit));
+        }
+    }
+
+    #[test]
+    fn weighted_index() {
+        let mut rng = Rng::new(3);
+        assert_eq!(rng.weighted_index(&[]), None);
+        assert_eq!(rng.weighted_index(&[0.0, 0.0]), None);
+        assert_eq!(rng.weighted_index(&[-1.0, 0.0]), None);
+
+        let mut counts = [0; 4];
+        for _ in 0..4000 {
+            counts[rng.weighted_index(&[1.0, 0.0, -2.0, 3.0]).unwrap()] += 1;
+        }
+        assert_eq!((counts[1], counts[2]), (0, 0));
+        // About 1000 and 3000
+        assert!((850..1150).contains(&counts[0]), "{counts:?}");
+
+        let items = ["never", "always"];
+        let picked = rng.choose_weighted(&items, |item| if *item == "never" { 0.0 } else { 1.0 });
+        assert_eq!(picked, Some(&"always"));
+    }
+
+    #[test]
+    fn shuffle_is_a_reproducible_permutation() {
+        let shuffled = |seed| {
+            let mut items: Vec<u32> = (0..20).collect();
+            Rng::new(seed).shuffle(&mut items);
+            items
+        };
+        let items = shuffled(4);
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+        assert_ne!(items, sorted);
+        assert_eq!(shuffled(4), items);
+        assert_ne!(shuffled(5), items);
+
+        let mut empty: [u32; 0] = [];
+        Rng::new(4).shuffle(&mut empty);
+    }
+}
@@ -0,0 +1,504 @@
+// panpan/src/replay.rs
+// Input recording and deterministic playback
+
+use crate::input::{InputEvent, Key, Touch, TouchPhase};
+use crate::timestep::GameLoop;
+use crate::{Context, Game};
+use std::io::{self, Read};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"PPRP";
+const VERSION: u8 = 2;
+
+/// What the game loop did after the events of a frame were dispatched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Step {
+    /// Ran a frame of `dt` seconds
+    Update(f32),
+    /// Paused, e.g. the window was minimized
+    Pause,
+    /// Resumed after a pause, which resets the fixed-timestep accumulator
+    Resume,
+}
+
+/// Everything that reached the game during one frame: the events dispatched
+/// before the step, and the step itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayFrame {
+    pub events: Vec<InputEvent>,
+    pub step: Step,
+}
+
+/// A recorded session that can be saved, loaded and played back
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Recording {
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    /// Total game time covered by the recording
+    pub fn duration(&self) -> f32 {
+        self.frames
+            .iter()
+            .map(|f| match f.step {
+                Step::Update(dt) => dt,
+                Step::Pause | Step::Resume => 0.0,
+            })
+            .sum()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        write_u32(&mut out, self.frames.len() as u32);
+        for frame in &self.frames {
+            match frame.step {
+                Step::Update(dt) => {
+                    out.push(0);
+                    out.extend_from_slice(&dt.to_le_bytes());
+                }
+                Step::Pause => out.push(1),
+                Step::Resume => out.push(2),
+            }
+            write_u32(&mut out, frame.events.len() as u32);
+            for event in &frame.events {
+                write_event(&mut out, event);
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        let input = &mut bytes;
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a panpan recording"));
+        }
+        let version = read_u8(input)?;
+        if version != VERSION {
+            return Err(invalid("unsupported recording version"));
+        }
+
+        let frame_count = read_u32(input)?;
+        let mut frames = Vec::new();
+        for _ in 0..frame_count {
+            let step = match read_u8(input)? {
+                0 => Step::Update(read_f32(input)?),
+                1 => Step::Pause,
+                2 => Step::Resume,
+                _ => return Err(invalid("unknown frame step")),
+            };
+            let event_count = read_u32(input)?;
+            let mut events = Vec::new();
+            for _ in 0..event_count {
+                events.push(read_event(input)?);
+            }
+            frames.push(ReplayFrame { events, step });
+        }
+
+        Ok(Self { frames })
+    }
+}
+
+/// Collects events, frame times and pauses while the game runs. Start it
+/// together with the game: playback always begins from `Game::new`.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    recording: Recording,
+    pending: Vec<InputEvent>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event that is about to be dispatched to the game
+    pub fn record_event(&mut self, event: &InputEvent) {
+        self.pending.push(event.clone());
+    }
+
+    /// Close the current frame, right before `update(dt)` is called
+    pub fn end_frame(&mut self, dt: f32) {
+        self.push(Step::Update(dt));
+    }
+
+    /// Record that the game loop was just paused
+    pub fn record_pause(&mut self) {
+        self.push(Step::Pause);
+    }
+
+    /// Record that the game loop was just resumed
+    pub fn record_resume(&mut self) {
+        self.push(Step::Resume);
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.recording.frames.len()
+    }
+
+    pub fn finish(self) -> Recording {
+        self.recording
+    }
+
+    /// Write the frames recorded so far to `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.recording.save(path)
+    }
+
+    fn push(&mut self, step: Step) {
+        self.recording.frames.push(ReplayFrame {
+            events: std::mem::take(&mut self.pending),
+            step,
+        });
+    }
+}
+
+/// Steps through a recording one frame at a time
+#[derive(Debug, Clone)]
+pub struct Player {
+    recording: Recording,
+    next: usize,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+
+    /// Dispatch the events of the next frame to `game`, pausing and
+    /// resuming `game_loop` where the recording did, and return the `dt` to
+    /// run the frame with, or `None` once the recording is exhausted
+    pub fn play_frame<G: Game>(
+        &mut self,
+        game: &mut G,
+        ctx: &mut Context,
+        game_loop: &mut GameLoop,
+    ) -> Option<f32> {
+        loop {
+            let frame = self.recording.frames.get(self.next)?;
+            self.next += 1;
+            for event in &frame.events {
+                crate::dispatch_event(game, ctx, event);
+            }
+            match frame.step {
+                Step::Update(dt) => return Some(dt),
+                Step::Pause => ctx.scope(|_| game_loop.pause(game)),
+                Step::Resume => ctx.scope(|_| game_loop.resume(game)),
+            }
+        }
+    }
+}
+
+/// Drive a fresh game through a whole recording without a window and return
+/// it, so the final state can be inspected in tests. Drawing calls are
+/// no-ops because the context has no graphics backend, and every call gets
+/// its own context, so replays can run in parallel.
+pub fn run_headless<G: Game>(recording: &Recording) -> G {
+    let mut ctx = Context::new();
+    let mut game = ctx.scope(G::new);
+    let mut game_loop = GameLoop::new(G::timestep());
+    let mut player = Player::new(recording.clone());
+    while let Some(dt) = player.play_frame(&mut game, &mut ctx, &mut game_loop) {
+        game_loop.frame(&mut game, &mut ctx, dt);
+    }
+    game
+}
+
+fn write_event(out: &mut Vec<u8>, event: &InputEvent) {
+    match event {
+        InputEvent::Touch(touch) => {
+            out.push(0);
+            out.extend_from_slice(&touch.id.to_le_bytes());
+            out.extend_from_slice(&touch.x.to_le_bytes());
+            out.extend_from_slice(&touch.y.to_le_bytes());
+            out.push(match touch.phase {
+                TouchPhase::Started => 0,
+                TouchPhase::Moved => 1,
+                TouchPhase::Ended => 2,
+            });
+        }
+        InputEvent::KeyDown(key) => {
+            out.push(1);
+            out.push(key_to_u8(*key));
+        }
+        InputEvent::KeyUp(key) => {
+            out.push(2);
+            out.push(key_to_u8(*key));
+        }
+        InputEvent::Text(text) => {
+            out.push(3);
+            write_str(out, text);
+        }
+        InputEvent::Composition { text, cursor } => {
+            out.push(4);
+            write_str(out, text);
+            match cursor {
+                Some((start, end)) => {
+                    out.push(1);
+                    write_u32(out, *start as u32);
+                    write_u32(out, *end as u32);
+                }
+                None => out.push(0),
+            }
+        }
+    }
+}
+
+fn read_event(input: &mut &[u8]) -> io::Result<InputEvent> {
+    Ok(match read_u8(input)? {
+        0 => {
+            let id = read_u32(input)? as i32;
+            let x = read_f32(input)?;
+            let y = read_f32(input)?;
+            let phase = match read_u8(input)? {
+                0 => TouchPhase::Started,
+                1 => TouchPhase::Moved,
+                2 => TouchPhase::Ended,
+                _ => return Err(invalid("unknown touch phase")),
+            };
+            InputEvent::Touch(Touch { id, x, y, phase })
+        }
+        1 => InputEvent::KeyDown(key_from_u8(read_u8(input)?)),
+        2 => InputEvent::KeyUp(key_from_u8(read_u8(input)?)),
+        3 => InputEvent::Text(read_str(input)?),
+        4 => {
+            let text = read_str(input)?;
+            let cursor = match read_u8(input)? {
+                0 => None,
+                _ => Some((read_u32(input)? as usize, read_u32(input)? as usize)),
+            };
+            InputEvent::Composition { text, cursor }
+        }
+        _ => return Err(invalid("unknown event type")),
+    })
+}
+
+// Index in this table is the key code on disk, so only ever append
+const KEYS: [Key; 13] = [
+    Key::W,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::Up,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Space,
+    Key::Enter,
+    Key::Backspace,
+    Key::Escape,
+    Key::Unknown,
+];
+
+fn key_to_u8(key: Key) -> u8 {
+    KEYS.iter()
+        .position(|k| *k == key)
+        .unwrap_or(KEYS.len() - 1) as u8
+}
+
+fn key_from_u8(value: u8) -> Key {
+    KEYS.get(value as usize).copied().unwrap_or(Key::Unknown)
+}
+
+fn write_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, text: &str) {
+    write_u32(out, text.len() as u32);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn read_u8(input: &mut &[u8]) -> io::Result<u8> {
+    let mut buf = [0u8; 1];
+    input.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u32(input: &mut &[u8]) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_f32(input: &mut &[u8]) -> io::Result<f32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn read_str(input: &mut &[u8]) -> io::Result<String> {
+    let len = read_u32(input)? as usize;
+    if len > input.len() {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (text, rest) = input.split_at(len);
+    *input = rest;
+    String::from_utf8(text.to_vec()).map_err(|_| invalid("text is not valid UTF-8"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Walks right while the key is held and counts what happened to it
+    #[derive(Debug, PartialEq)]
+    struct Walker {
+        x: f32,
+        updates: u32,
+        keys: Vec<Key>,
+        pauses: u32,
+        resumes: u32,
+    }
+
+    impl Game for Walker {
+        fn new(_ctx: &mut Context) -> Self {
+            Self {
+                x: 0.0,
+                updates: 0,
+                keys: Vec::new(),
+                pauses: 0,
+                resumes: 0,
+            }
+        }
+
+        fn update(&mut self, ctx: &mut Context, dt: f32) {
+            self.updates += 1;
+            if ctx.input().is_key_down(Key::Right) {
+                self.x += 100.0 * dt;
+            }
+        }
+
+        fn render(&self, _ctx: &mut Context) {}
+
+        fn on_key_down(&mut self, key: Key) {
+            self.keys.push(key);
+        }
+
+        fn on_pause(&mut self) {
+            self.pauses += 1;
+        }
+
+        fn on_resume(&mut self) {
+            self.resumes += 1;
+        }
+    }
+
+    enum Live {
+        Event(InputEvent),
+        Frame(f32),
+        Pause,
+        Resume,
+    }
+
+    /// Run `session` the way a runner does while recording
+    fn record(session: Vec<Live>) -> (Walker, Recording) {
+        let mut ctx = Context::new();
+        let mut game = Walker::new(&mut ctx);
+        let mut game_loop = GameLoop::new(Walker::timestep());
+        let mut recorder = Recorder::new();
+        for step in session {
+            match step {
+                Live::Event(event) => {
+                    recorder.record_event(&event);
+                    crate::dispatch_event(&mut game, &mut ctx, &event);
+                }
+                Live::Frame(dt) => {
+                    let simulated = if game_loop.skips_next_frame() {
+                        0.0
+                    } else {
+                        dt
+                    };
+                    recorder.end_frame(simulated);
+                    game_loop.frame(&mut game, &mut ctx, dt);
+                }
+                Live::Pause => {
+                    recorder.record_pause();
+                    game_loop.pause(&mut game);
+                }
+                Live::Resume => {
+                    recorder.record_resume();
+                    game_loop.resume(&mut game);
+                }
+            }
+        }
+        (game, recorder.finish())
+    }
+
+    #[test]
+    fn headless_replay_matches_live_session() {
+        let mut session = vec![Live::Event(InputEvent::KeyDown(Key::Right))];
+        session.extend([0.025, 0.013, 0.031, 0.017].map(Live::Frame));
+        // Leaves time in the accumulator, which resuming drops
+        session.extend([Live::Pause, Live::Frame(0.5), Live::Resume]);
+        session.extend([2.0, 0.021, 0.009].map(Live::Frame));
+        session.push(Live::Event(InputEvent::KeyUp(Key::Right)));
+        session.push(Live::Event(InputEvent::KeyDown(Key::Space)));
+        session.extend([0.016, 0.016].map(Live::Frame));
+
+        let (live, recording) = record(session);
+        assert_eq!(live.pauses, 1);
+        assert_eq!(live.resumes, 1);
+        assert!(live.x > 0.0);
+
+        let replayed = run_headless::<Walker>(&recording);
+        assert_eq!(replayed, live);
+    }
+
+    #[test]
+    fn recording_survives_bytes() {
+        let (_, recording) = record(vec![
+            Live::Event(InputEvent::KeyDown(Key::W)),
+            Live::Event(InputEvent::Touch(Touch {
+                id: 3,
+                x: 10.5,
+                y: -2.0,
+                phase: TouchPhase::Moved,
+            })),
+            Live::Frame(0.016),
+            Live::Pause,
+            Live::Event(InputEvent::Composition {
+                text: "かな".to_string(),
+                cursor: Some((0, 3)),
+            }),
+            Live::Resume,
+            Live::Event(InputEvent::Text("hi".to_string())),
+            Live::Frame(0.02),
+        ]);
+        assert_eq!(recording.frames.len(), 4);
+        assert_eq!(recording.frames[1].step, Step::Pause);
+
+        let loaded = Recording::from_bytes(&recording.to_bytes()).unwrap();
+        assert_eq!(loaded, recording);
+        // The frame right after resuming is not simulated
+        assert_eq!(loaded.duration(), 0.016);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Recording::from_bytes(b"PNG\0data").is_err());
+        let mut bytes = Recording::default().to_bytes();
+        bytes[4] = 1;
+        assert!(Recording::from_bytes(&bytes).is_err());
+    }
+}
@@ -0,0 +1,596 @@
+// panpan/src/scene.rs
+// Scene stack for title screens, gameplay, pause overlays and the like
+//
+// The game keeps a `SceneStack` and forwards `update`, `render`, input and
+// lifecycle calls to it. Only the top scene updates and receives input.
+// Scenes below it are drawn as long as everything above them is an overlay.
+
+use crate::input::InputEvent;
+use crate::types::Color;
+use crate::Context;
+
+/// One screen of the game. Scenes change the stack by returning a
+/// `SceneChange` from `update` or `on_input`.
+pub trait Scene {
+    fn update(&mut self, ctx: &mut Context, dt: f32) -> SceneChange;
+
+    fn render(&self, ctx: &mut Context);
+
+    /// Optional: overlays, like a pause menu, let the scenes below them
+    /// render (but not update)
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Optional: handle an input event. Only the top scene gets them.
+    fn on_input(&mut self, _event: &InputEvent) -> SceneChange {
+        SceneChange::None
+    }
+
+    /// Optional: the scene was put on the stack
+    fn on_enter(&mut self) {}
+    /// Optional: the scene was taken off the stack
+    fn on_exit(&mut self) {}
+    /// Optional: another scene was pushed on top of this one
+    fn on_covered(&mut self) {}
+    /// Optional: the scene on top was popped, this one is on top again
+    fn on_uncovered(&mut self) {}
+
+    /// Optional: app lifecycle, see the `Game` methods of the same name.
+    /// Pause, resume, focus and close requests go to the top scene, low
+    /// memory and quit to every scene.
+    fn on_pause(&mut self) {}
+    fn on_resume(&mut self) {}
+    fn on_focus_changed(&mut self, _focused: bool) {}
+    fn on_close_requested(&mut self) -> bool {
+        true
+    }
+    fn on_low_memory(&mut self) {}
+    fn on_quit(&mut self) {}
+}
+
+/// How the stack animates from one set of scenes to the next
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Transition {
+    #[default]
+    None,
+    /// Fade the old scenes out to `color`, then the new ones in
+    Fade { duration: f32, color: Color },
+    /// Move the new scenes in from the side they slide towards, pushing the
+    /// old ones out
+    Slide {
+        duration: f32,
+        direction: SlideDirection,
+    },
+}
+
+impl Transition {
+    /// Fade through black
+    pub fn fade(duration: f32) -> Self {
+        Transition::Fade {
+            duration,
+            color: Color::BLACK,
+        }
+    }
+
+    pub fn slide(direction: SlideDirection, duration: f32) -> Self {
+        Transition::Slide {
+            duration,
+            direction,
+        }
+    }
+
+    fn duration(&self) -> f32 {
+        match *self {
+            Transition::None => 0.0,
+            Transition::Fade { duration, .. } | Transition::Slide { duration, .. } => duration,
+        }
+    }
+}
+
+/// Direction the scenes move in during a `Transition::Slide`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlideDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// What a scene wants to happen to the stack
+#[derive(Default)]
+pub enum SceneChange {
+    #[default]
+    None,
+    Push(Box<dyn Scene>, Transition),
+    Pop(Transition),
+    /// Swap the top scene for another one
+    Replace(Box<dyn Scene>, Transition),
+}
+
+impl SceneChange {
+    pub fn push(scene: impl Scene + 'static) -> Self {
+        SceneChange::Push(Box::new(scene), Transition::None)
+    }
+
+    pub fn pop() -> Self {
+        SceneChange::Pop(Transition::None)
+    }
+
+    pub fn replace(scene: impl Scene + 'static) -> Self {
+        SceneChange::Replace(Box::new(scene), Transition::None)
+    }
+
+    /// Animate the change, e.g. `SceneChange::pop().with_transition(...)`
+    pub fn with_transition(self, transition: Transition) -> Self {
+        match self {
+            SceneChange::None => SceneChange::None,
+            SceneChange::Push(scene, _) => SceneChange::Push(scene, transition),
+            SceneChange::Pop(_) => SceneChange::Pop(transition),
+            SceneChange::Replace(scene, _) => SceneChange::Replace(scene, transition),
+        }
+    }
+}
+
+/// A transition in progress
+struct Active {
+    transition: Transition,
+    elapsed: f32,
+    /// Scene that left the stack and is still drawn until the end
+    outgoing: Option<Box<dyn Scene>>,
+    /// Stack length before the change, not counting `outgoing`
+    kept: usize,
+}
+
+/// Stack of scenes, top last
+#[derive(Default)]
+pub struct SceneStack {
+    scenes: Vec<Box<dyn Scene>>,
+    active: Option<Active>,
+}
+
+impl SceneStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start with `scene` as the only scene
+    pub fn with(scene: impl Scene + 'static) -> Self {
+        let mut stack = Self::new();
+        stack.push(Box::new(scene), Transition::None);
+        stack
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// True once the last scene was popped, usually time to quit
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn is_transitioning(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn push(&mut self, mut scene: Box<dyn Scene>, transition: Transition) {
+        self.finish_transition();
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_covered();
+        }
+        scene.on_enter();
+        self.scenes.push(scene);
+        self.start(transition, None, self.scenes.len() - 1);
+    }
+
+    pub fn pop(&mut self, transition: Transition) {
+        self.finish_transition();
+        let Some(mut scene) = self.scenes.pop() else {
+            return;
+        };
+        scene.on_exit();
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_uncovered();
+        }
+        self.start(transition, Some(scene), self.scenes.len());
+    }
+
+    pub fn replace(&mut self, mut scene: Box<dyn Scene>, transition: Transition) {
+        self.finish_transition();
+        let old = self.scenes.pop().map(|mut old| {
+            old.on_exit();
+            old
+        });
+        scene.on_enter();
+        self.scenes.push(scene);
+        self.start(transition, old, self.scenes.len() - 1);
+    }
+
+    /// Carry out a change returned by a scene
+    pub fn apply(&mut self, change: SceneChange) {
+        match change {
+            SceneChange::None => {}
+            SceneChange::Push(scene, transition) => self.push(scene, transition),
+            SceneChange::Pop(transition) => self.pop(transition),
+            SceneChange::Replace(scene, transition) => self.replace(scene, transition),
+        }
+    }
+
+    /// Update the top scene. Nothing updates while a transition plays.
+    pub fn update(&mut self, ctx: &mut Context, dt: f32) {
+        if let Some(active) = &mut self.active {
+            active.elapsed += dt;
+            if active.elapsed >= active.transition.duration() {
+                self.active = None;
+            }
+            return;
+        }
+        let change = match self.scenes.last_mut() {
+            Some(top) => top.update(ctx, dt),
+            None => return,
+        };
+        self.apply(change);
+    }
+
+    pub fn render(&self, ctx: &mut Context) {
+        let Some(active) = &self.active else {
+            render_visible(ctx, self.scenes.iter().map(|s| s.as_ref()));
+            return;
+        };
+
+        let before = self.scenes[..active.kept]
+            .iter()
+            .map(|s| s.as_ref())
+            .chain(active.outgoing.as_deref());
+        let after = self.scenes.iter().map(|s| s.as_ref());
+        let t = (active.elapsed / active.transition.duration()).clamp(0.0, 1.0);
+
+        match active.transition {
+            Transition::None => render_visible(ctx, after),
+            Transition::Fade { color, .. } => {
+                // Out over the first half, in over the second
+                let cover = if t < 0.5 {
+                    render_visible(ctx, before);
+                    t * 2.0
+                } else {
+                    render_visible(ctx, after);
+                    (1.0 - t) * 2.0
+                };
+                let gfx = ctx.graphics();
+                let (width, height) = gfx.screen_size();
+                let (x, y) = gfx.offset();
+                gfx.draw_rect(
+                    -x,
+                    -y,
+                    width,
+                    height,
+                    Color::new(color.r, color.g, color.b, color.a * cover),
+                );
+            }
+            Transition::Slide { direction, .. } => {
+                let (width, height) = ctx.graphics().screen_size();
+                let (dx, dy) = match direction {
+                    SlideDirection::Left => (-width, 0.0),
+                    SlideDirection::Right => (width, 0.0),
+                    SlideDirection::Up => (0.0, -height),
+                    SlideDirection::Down => (0.0, height),
+                };
+                let base = ctx.graphics().offset();
+                ctx.graphics().set_offset(base.0 + dx * t, base.1 + dy * t);
+                render_visible(ctx, before);
+                ctx.graphics()
+                    .set_offset(base.0 + dx * (t - 1.0), base.1 + dy * (t - 1.0));
+                render_visible(ctx, after);
+                ctx.graphics().set_offset(base.0, base.1);
+            }
+        }
+    }
+
+    /// Send an input event to the top scene. Ignored during transitions.
+    pub fn handle_input(&mut self, event: &InputEvent) {
+        if self.active.is_some() {
+            return;
+        }
+        let change = match self.scenes.last_mut() {
+            Some(top) => top.on_input(event),
+            None => return,
+        };
+        self.apply(change);
+    }
+
+    pub fn on_pause(&mut self) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_pause();
+        }
+    }
+
+    pub fn on_resume(&mut self) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_resume();
+        }
+    }
+
+    pub fn on_focus_changed(&mut self, focused: bool) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.on_focus_changed(focused);
+        }
+    }
+
+    /// Whether the top scene lets the window close, true without scenes
+    pub fn on_close_requested(&mut self) -> bool {
+        match self.scenes.last_mut() {
+            Some(top) => top.on_close_requested(),
+            None => true,
+        }
+    }
+
+    pub fn on_low_memory(&mut self) {
+        for scene in &mut self.scenes {
+            scene.on_low_memory();
+        }
+    }
+
+    pub fn on_quit(&mut self) {
+        for scene in self.scenes.iter_mut().rev() {
+            scene.on_quit();
+        }
+    }
+
+    fn start(&mut self, transition: Transition, outgoing: Option<Box<dyn Scene>>, kept: usize) {
+        if transition.duration() > 0.0 {
+            self.active = Some(Active {
+                transition,
+                elapsed: 0.0,
+                outgoing,
+                kept,
+            });
+        }
+    }
+
+    /// Jump to the end of the running transition, if any
+    fn finish_transition(&mut self) {
+        self.active = None;
+    }
+}
+
+/// Render the top scene and everything visible through overlays, bottom up
+fn render_visible<'a>(ctx: &mut Context, scenes: impl Iterator<Item = &'a dyn Scene>) {
+    let scenes: Vec<&dyn Scene> = scenes.collect();
+    let first = scenes
+        .iter()
+        .rposition(|scene| !scene.is_overlay())
+        .unwrap_or(0);
+    for scene in &scenes[first..] {
+        scene.render(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Key;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Logs every call, and hands out `change` from its next update or input
+    struct Stub {
+        name: &'static str,
+        log: Log,
+        overlay: bool,
+        change: Option<SceneChange>,
+        allow_close: bool,
+    }
+
+    impl Stub {
+        fn new(name: &'static str, log: &Log) -> Self {
+            Self {
+                name,
+                log: log.clone(),
+                overlay: false,
+                change: None,
+                allow_close: true,
+            }
+        }
+
+        fn overlay(mut self) -> Self {
+            self.overlay = true;
+            self
+        }
+
+        fn then(mut self, change: SceneChange) -> Self {
+            self.change = Some(change);
+            self
+        }
+
+        fn log(&self, what: &str) {
+            self.log.borrow_mut().push(format!("{} {what}", self.name));
+        }
+    }
+
+    impl Scene for Stub {
+        fn update(&mut self, _ctx: &mut Context, _dt: f32) -> SceneChange {
+            self.log("update");
+            self.change.take().unwrap_or_default()
+        }
+
+        fn render(&self, _ctx: &mut Context) {
+            self.log("render");
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+
+        fn on_input(&mut self, _event: &InputEvent) -> SceneChange {
+            self.log("input");
+            self.change.take().unwrap_or_default()
+        }
+
+        fn on_enter(&mut self) {
+            self.log("enter");
+        }
+
+        fn on_exit(&mut self) {
+            self.log("exit");
+        }
+
+        fn on_covered(&mut self) {
+            self.log("covered");
+        }
+
+        fn on_uncovered(&mut self) {
+            self.log("uncovered");
+        }
+
+        fn on_pause(&mut self) {
+            self.log("pause");
+        }
+
+        fn on_low_memory(&mut self) {
+            self.log("low memory");
+        }
+
+        fn on_close_requested(&mut self) -> bool {
+            self.log("close requested");
+            self.allow_close
+        }
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.borrow_mut())
+    }
+
+    #[test]
+    fn hooks_run_in_order() {
+        let log = Log::default();
+        let mut stack = SceneStack::with(Stub::new("a", &log));
+        assert_eq!(take(&log), ["a enter"]);
+
+        stack.push(Box::new(Stub::new("b", &log)), Transition::None);
+        assert_eq!(take(&log), ["a covered", "b enter"]);
+
+        stack.replace(Box::new(Stub::new("c", &log)), Transition::None);
+        assert_eq!(take(&log), ["b exit", "c enter"]);
+
+        stack.pop(Transition::None);
+        assert_eq!(take(&log), ["c exit", "a uncovered"]);
+
+        stack.pop(Transition::None);
+        assert_eq!(take(&log), ["a exit"]);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn scenes_change_the_stack_from_update_and_input() {
+        let log = Log::default();
+        let mut ctx = Context::new();
+        let b = Stub::new("b", &log).then(SceneChange::pop());
+        let mut stack = SceneStack::with(Stub::new("a", &log).then(SceneChange::push(b)));
+        take(&log);
+
+        stack.update(&mut ctx, 0.1);
+        assert_eq!(take(&log), ["a update", "a covered", "b enter"]);
+        stack.handle_input(&InputEvent::KeyDown(Key::Space));
+        assert_eq!(take(&log), ["b input", "b exit", "a uncovered"]);
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn renders_from_the_topmost_opaque_scene_up() {
+        let log = Log::default();
+        let mut ctx = Context::new();
+        let mut stack = SceneStack::with(Stub::new("a", &log));
+        for scene in [
+            Stub::new("b", &log).overlay(),
+            Stub::new("c", &log),
+            Stub::new("d", &log).overlay(),
+            Stub::new("e", &log).overlay(),
+        ] {
+            stack.push(Box::new(scene), Transition::None);
+        }
+        take(&log);
+
+        stack.render(&mut ctx);
+        assert_eq!(take(&log), ["c render", "d render", "e render"]);
+
+        stack.pop(Transition::None);
+        stack.pop(Transition::None);
+        stack.pop(Transition::None);
+        take(&log);
+        stack.render(&mut ctx);
+        assert_eq!(take(&log), ["a render", "b render"]);
+
+        // Only the top one updates, even below an overlay
+        stack.update(&mut ctx, 0.1);
+        assert_eq!(take(&log), ["b update"]);
+    }
+
+    #[test]
+    fn transitions_block_updates_and_input_until_done() {
+        let log = Log::default();
+        let mut ctx = Context::new();
+        let mut stack = SceneStack::with(Stub::new("a", &log));
+        stack.push(Box::new(Stub::new("b", &log)), Transition::fade(1.0));
+        take(&log);
+        assert!(stack.is_transitioning());
+
+        // Fading out of the old scenes first, then into the new ones
+        stack.update(&mut ctx, 0.25);
+        stack.render(&mut ctx);
+        stack.handle_input(&InputEvent::KeyDown(Key::Space));
+        assert_eq!(take(&log), ["a render"]);
+        stack.update(&mut ctx, 0.5);
+        stack.render(&mut ctx);
+        assert_eq!(take(&log), ["b render"]);
+
+        stack.update(&mut ctx, 0.25);
+        assert!(!stack.is_transitioning());
+        assert!(take(&log).is_empty());
+        stack.update(&mut ctx, 0.25);
+        stack.handle_input(&InputEvent::KeyDown(Key::Space));
+        assert_eq!(take(&log), ["b update", "b input"]);
+
+        // The popped scene is still drawn while it slides out
+        stack.pop(Transition::slide(SlideDirection::Left, 0.5));
+        take(&log);
+        stack.render(&mut ctx);
+        assert_eq!(take(&log), ["b render", "a render"]);
+        stack.update(&mut ctx, 0.5);
+        stack.render(&mut ctx);
+        assert_eq!(take(&log), ["a render"]);
+    }
+
+    #[test]
+    fn lifecycle_calls() {
+        let log = Log::default();
+        let mut stack = SceneStack::new();
+        assert!(stack.on_close_requested());
+
+        stack.push(Box::new(Stub::new("a", &log)), Transition::None);
+        let mut b = Stub::new("b", &log);
+        b.allow_close = false;
+        stack.push(Box::new(b), Transition::None);
+        take(&log);
+
+        assert!(!stack.on_close_requested());
+        stack.on_pause();
+        stack.on_low_memory();
+        assert_eq!(
+            take(&log),
+            [
+                "b close requested",
+                "b pause",
+                "a low memory",
+                "b low memory"
+            ]
+        );
+
+        stack.pop(Transition::None);
+        take(&log);
+        assert!(stack.on_close_requested());
+        assert_eq!(take(&log), ["a close requested"]);
+    }
+}
@@ -0,0 +1,114 @@
+// panpan/src/time.rs
+// Frame timing: FPS, elapsed time and how long each phase of a frame took
+//
+// `GameLoop` measures update and render, the runners add the buffer swap.
+// Android presents after the frame callback returns, so there the swap time
+// is always zero.
+
+use std::time::Duration;
+
+/// Weight of the newest sample in the smoothed values
+const SMOOTHING: f32 = 0.1;
+
+/// Timing information of a `Context`, see `Context::timing`
+#[derive(Debug, Clone, Default)]
+pub struct Time {
+    delta: f32,
+    elapsed: f64,
+    real_elapsed: f64,
+    frame_count: u64,
+    frame_delta: f32,
+    frame_time: f32,
+    update_time: f32,
+    render_time: f32,
+    swap_time: f32,
+}
+
+impl Time {
+    /// Length of the current update step in seconds
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Simulated seconds since the game started, the sum of all update steps
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Wall-clock seconds spent running, not counting time paused
+    pub fn real_elapsed(&self) -> f64 {
+        self.real_elapsed
+    }
+
+    /// Frames rendered so far
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Seconds between the last two frames, unsmoothed
+    pub fn frame_delta(&self) -> f32 {
+        self.frame_delta
+    }
+
+    /// Seconds between frames, smoothed over the last few dozen frames
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+
+    /// Frames per second, from the smoothed frame time
+    pub fn fps(&self) -> f32 {
+        if self.frame_time > 0.0 {
+            1.0 / self.frame_time
+        } else {
+            0.0
+        }
+    }
+
+    /// Smoothed seconds spent in `Game::update` per frame, all steps together
+    pub fn update_time(&self) -> f32 {
+        self.update_time
+    }
+
+    /// Smoothed seconds spent in `Game::render` per frame
+    pub fn render_time(&self) -> f32 {
+        self.render_time
+    }
+
+    /// Smoothed seconds spent presenting the frame (waiting for vsync included)
+    pub fn swap_time(&self) -> f32 {
+        self.swap_time
+    }
+
+    pub(crate) fn begin_frame(&mut self, frame_dt: f32) {
+        self.frame_delta = frame_dt;
+        self.real_elapsed += frame_dt as f64;
+        smooth(&mut self.frame_time, frame_dt);
+    }
+
+    pub(crate) fn begin_update(&mut self, dt: f32) {
+        self.delta = dt;
+        self.elapsed += dt as f64;
+    }
+
+    pub(crate) fn end_update(&mut self, took: Duration) {
+        smooth(&mut self.update_time, took.as_secs_f32());
+    }
+
+    pub(crate) fn end_render(&mut self, took: Duration) {
+        smooth(&mut self.render_time, took.as_secs_f32());
+        self.frame_count += 1;
+    }
+
+    pub(crate) fn record_swap(&mut self, took: Duration) {
+        smooth(&mut self.swap_time, took.as_secs_f32());
+    }
+}
+
+/// Exponential moving average, starting from the first sample
+fn smooth(value: &mut f32, sample: f32) {
+    if *value == 0.0 {
+        *value = sample;
+    } else {
+        *value += (sample - *value) * SMOOTHING;
+    }
+}
@@ -0,0 +1,312 @@
+// panpan/src/timer/coroutine.rs
+// Executor for async blocks polled once per frame
+//
+// Coroutines are plain futures. `wait_seconds` and the other helpers read
+// the executor's clock from a thread-local set while `Coroutines::update`
+// polls, so they only work inside coroutines. Nothing is ever woken: every
+// running coroutine is polled on each update, which is cheap for the
+// handful a game runs at a time.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{self, Poll, Waker};
+
+/// Waits are this close to done count as done, so waiting a whole number of
+/// frames is not a frame late from rounding
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    now: f64,
+    /// Where the next wait of the coroutine being polled starts: `now`, or
+    /// the exact end of the wait it just finished, so chained waits do not
+    /// drift by the part of a frame each one overran
+    anchor: f64,
+}
+
+thread_local! {
+    static CLOCK: Cell<Option<Clock>> = const { Cell::new(None) };
+}
+
+fn clock() -> Clock {
+    CLOCK
+        .get()
+        .expect("coroutine helpers only work inside Coroutines::update")
+}
+
+type Coroutine = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Identifies a coroutine in `Coroutines`; stays unique after it finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CoroutineHandle(u64);
+
+/// Runs async blocks, e.g. one that awaits `wait_seconds(3.0)` and then
+/// starts the next wave. Coroutines must be `'static`, so they share game
+/// state through `Rc<RefCell<_>>` or push messages the game reads.
+#[derive(Default)]
+pub struct Coroutines {
+    running: Vec<(CoroutineHandle, Coroutine)>,
+    time: f64,
+    next: u64,
+}
+
+impl Coroutines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start `future`. It first runs during the next `update`.
+    pub fn spawn(&mut self, future: impl Future<Output = ()> + 'static) -> CoroutineHandle {
+        let handle = CoroutineHandle(self.next);
+        self.next += 1;
+        self.running.push((handle, Box::pin(future)));
+        handle
+    }
+
+    /// Drop a coroutine wherever it is waiting. Returns false if it already
+    /// finished.
+    pub fn cancel(&mut self, handle: CoroutineHandle) -> bool {
+        let before = self.running.len();
+        self.running.retain(|(h, _)| *h != handle);
+        self.running.len() != before
+    }
+
+    pub fn is_running(&self, handle: CoroutineHandle) -> bool {
+        self.running.iter().any(|(h, _)| *h == handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.running.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    /// Cancel every coroutine
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+
+    /// Seconds of `dt` this executor has been updated with
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Advance the clock by `dt` and run every coroutine until it waits
+    /// again or finishes
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt as f64;
+        let now = self.time;
+        let mut cx = task::Context::from_waker(Waker::noop());
+        // Put back whatever an outer executor had set, for nested executors
+        let outer = CLOCK.get();
+        self.running.retain_mut(|(_, future)| {
+            CLOCK.set(Some(Clock { now, anchor: now }));
+            future.as_mut().poll(&mut cx).is_pending()
+        });
+        CLOCK.set(outer);
+    }
+}
+
+/// Finishes `seconds` after it is first awaited
+pub fn wait_seconds(seconds: f32) -> WaitSeconds {
+    WaitSeconds {
+        seconds: seconds.max(0.0) as f64,
+        until: None,
+    }
+}
+
+/// Finishes on the next `update`
+pub fn next_frame() -> NextFrame {
+    NextFrame { waited: false }
+}
+
+/// Finishes on the first `update` where `condition` returns true, checked
+/// right away and then once per update
+pub fn wait_until<F: FnMut() -> bool>(condition: F) -> WaitUntil<F> {
+    WaitUntil { condition }
+}
+
+/// Future of `wait_seconds`
+#[derive(Debug)]
+pub struct WaitSeconds {
+    seconds: f64,
+    until: Option<f64>,
+}
+
+impl Future for WaitSeconds {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut task::Context) -> Poll<()> {
+        let clock = clock();
+        let seconds = self.seconds;
+        let until = *self.until.get_or_insert(clock.anchor + seconds);
+        if clock.now + EPSILON < until {
+            return Poll::Pending;
+        }
+        CLOCK.set(Some(Clock {
+            anchor: until,
+            ..clock
+        }));
+        Poll::Ready(())
+    }
+}
+
+/// Future of `next_frame`
+#[derive(Debug)]
+pub struct NextFrame {
+    waited: bool,
+}
+
+impl Future for NextFrame {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut task::Context) -> Poll<()> {
+        clock();
+        if self.waited {
+            Poll::Ready(())
+        } else {
+            self.waited = true;
+            Poll::Pending
+        }
+    }
+}
+
+/// Future of `wait_until`
+#[derive(Debug)]
+pub struct WaitUntil<F> {
+    condition: F,
+}
+
+// The condition is never pinned, only called
+impl<F> Unpin for WaitUntil<F> {}
+
+impl<F: FnMut() -> bool> Future for WaitUntil<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut task::Context) -> Poll<()> {
+        clock();
+        if (self.condition)() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<&'static str>>>;
+
+    /// Updates until `log` has `entry`, returns how many it took
+    fn updates_until(coroutines: &mut Coroutines, dt: f32, log: &Log, entry: &str) -> u32 {
+        for frame in 1..=1000 {
+            coroutines.update(dt);
+            if log.borrow().contains(&entry) {
+                return frame;
+            }
+        }
+        panic!("{entry:?} never logged");
+    }
+
+    #[test]
+    fn chained_waits_do_not_drift() {
+        let mut coroutines = Coroutines::new();
+        let log = Log::default();
+        let done = log.clone();
+        coroutines.spawn(async move {
+            wait_seconds(0.5).await;
+            done.borrow_mut().push("half");
+            wait_seconds(0.5).await;
+            done.borrow_mut().push("done");
+        });
+
+        // Starts waiting on its first update, done exactly 60 frames later
+        coroutines.update(1.0 / 60.0);
+        assert_eq!(updates_until(&mut coroutines, 1.0 / 60.0, &log, "half"), 30);
+        assert_eq!(updates_until(&mut coroutines, 1.0 / 60.0, &log, "done"), 30);
+        assert!(coroutines.is_empty());
+
+        // Frames that overrun each wait: the second still ends 0.6 s after
+        // the first started, not 0.6 s after the first ended
+        let log = Log::default();
+        let done = log.clone();
+        coroutines.spawn(async move {
+            wait_seconds(0.3).await;
+            done.borrow_mut().push("first");
+            wait_seconds(0.3).await;
+            done.borrow_mut().push("second");
+        });
+        assert_eq!(updates_until(&mut coroutines, 0.25, &log, "first"), 3);
+        assert_eq!(updates_until(&mut coroutines, 0.25, &log, "second"), 1);
+    }
+
+    #[test]
+    fn next_frame_and_wait_until() {
+        let mut coroutines = Coroutines::new();
+        let log = Log::default();
+        let ready = Rc::new(Cell::new(false));
+        let (done, flag) = (log.clone(), ready.clone());
+        coroutines.spawn(async move {
+            done.borrow_mut().push("start");
+            next_frame().await;
+            done.borrow_mut().push("next");
+            wait_until(|| flag.get()).await;
+            done.borrow_mut().push("ready");
+            // Does not wait at all
+            wait_seconds(0.0).await;
+            wait_until(|| true).await;
+            done.borrow_mut().push("end");
+        });
+
+        coroutines.update(0.1);
+        assert_eq!(*log.borrow(), ["start"]);
+        coroutines.update(0.1);
+        coroutines.update(0.1);
+        assert_eq!(*log.borrow(), ["start", "next"]);
+        ready.set(true);
+        coroutines.update(0.1);
+        assert_eq!(*log.borrow(), ["start", "next", "ready", "end"]);
+        assert!(coroutines.is_empty());
+        assert!((coroutines.time() - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cancel_drops_the_coroutine() {
+        let mut coroutines = Coroutines::new();
+        let state = Rc::new(());
+        let held = state.clone();
+        let waiting = coroutines.spawn(async move {
+            let _held = held;
+            wait_seconds(10.0).await;
+        });
+        let quick = coroutines.spawn(async {});
+        assert_eq!(coroutines.len(), 2);
+
+        coroutines.update(0.1);
+        assert!(!coroutines.is_running(quick));
+        assert!(!coroutines.cancel(quick));
+        assert!(coroutines.is_running(waiting));
+        assert_eq!(Rc::strong_count(&state), 2);
+
+        assert!(coroutines.cancel(waiting));
+        assert!(!coroutines.cancel(waiting));
+        assert_eq!(Rc::strong_count(&state), 1);
+        assert!(coroutines.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "only work inside")]
+    fn helpers_panic_outside_update() {
+        let mut wait = std::pin::pin!(wait_seconds(1.0));
+        let _ = wait
+            .as_mut()
+            .poll(&mut task::Context::from_waker(Waker::noop()));
+    }
+}
@@ -0,0 +1,301 @@
+// panpan/src/timer/mod.rs
+// Timers and coroutines, advanced by the `dt` of `Game::update`
+//
+// `Timer` is a single countdown to keep in a game struct. `Timers` holds any
+// number of one-shot and repeating timers and hands back the value given to
+// each timer when it goes off, so the game can react without callbacks that
+// would need to borrow it. `Coroutines` runs async blocks that wait with
+// `wait_seconds(2.0).await`, for scripts that read top to bottom.
+
+mod coroutine;
+
+pub use coroutine::{
+    next_frame, wait_seconds, wait_until, CoroutineHandle, Coroutines, NextFrame, WaitSeconds,
+    WaitUntil,
+};
+
+/// A countdown that goes off once, or every `duration` seconds when
+/// repeating
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timer {
+    duration: f32,
+    elapsed: f32,
+    repeating: bool,
+    finished: bool,
+}
+
+impl Timer {
+    /// Goes off once after `seconds`
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            duration: seconds.max(0.0),
+            elapsed: 0.0,
+            repeating: false,
+            finished: false,
+        }
+    }
+
+    /// Goes off every `interval` seconds
+    pub fn repeating(interval: f32) -> Self {
+        Self {
+            repeating: true,
+            ..Self::new(interval)
+        }
+    }
+
+    /// Advance by `dt`. Returns how many times the timer went off, at most 1
+    /// unless a repeating timer's interval is shorter than `dt`.
+    pub fn update(&mut self, dt: f32) -> u32 {
+        if self.finished {
+            return 0;
+        }
+        self.elapsed += dt;
+        if self.elapsed < self.duration {
+            return 0;
+        }
+        if !self.repeating {
+            self.elapsed = self.duration;
+            self.finished = true;
+            return 1;
+        }
+        if self.duration <= 0.0 {
+            self.elapsed = 0.0;
+            return 1;
+        }
+        let times = (self.elapsed / self.duration) as u32;
+        self.elapsed -= times as f32 * self.duration;
+        times
+    }
+
+    /// A one-shot timer went off; repeating timers never finish
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// Seconds until the timer goes off next
+    pub fn remaining(&self) -> f32 {
+        self.duration - self.elapsed
+    }
+
+    /// 0.0 when started, 1.0 when going off
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.elapsed / self.duration
+        } else {
+            1.0
+        }
+    }
+
+    /// Count down from the start again
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.finished = false;
+    }
+}
+
+/// Identifies a timer in `Timers`; stays unique after the timer is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimerHandle(u64);
+
+struct Scheduled<T> {
+    handle: TimerHandle,
+    remaining: f32,
+    /// Repeating timers start over with this
+    interval: Option<f32>,
+    value: T,
+}
+
+/// Scheduled values, handed back by `update` when their time comes, e.g.
+/// `timers.after(3.0, Event::SpawnWave)`
+pub struct Timers<T> {
+    scheduled: Vec<Scheduled<T>>,
+    next: u64,
+}
+
+impl<T> Default for Timers<T> {
+    fn default() -> Self {
+        Self {
+            scheduled: Vec::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<T> Timers<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hand back `value` once, after `seconds`
+    pub fn after(&mut self, seconds: f32, value: T) -> TimerHandle {
+        self.schedule(seconds, None, value)
+    }
+
+    /// Hand back `value` every `interval` seconds until cancelled
+    pub fn every(&mut self, interval: f32, value: T) -> TimerHandle
+    where
+        T: Clone,
+    {
+        self.schedule(interval, Some(interval), value)
+    }
+
+    /// Stop a timer before it goes off. Returns its value, `None` if it
+    /// already went off or was cancelled.
+    pub fn cancel(&mut self, handle: TimerHandle) -> Option<T> {
+        let index = self.scheduled.iter().position(|s| s.handle == handle)?;
+        Some(self.scheduled.remove(index).value)
+    }
+
+    pub fn is_active(&self, handle: TimerHandle) -> bool {
+        self.scheduled.iter().any(|s| s.handle == handle)
+    }
+
+    /// Seconds until the timer goes off next
+    pub fn remaining(&self, handle: TimerHandle) -> Option<f32> {
+        self.scheduled
+            .iter()
+            .find(|s| s.handle == handle)
+            .map(|s| s.remaining)
+    }
+
+    pub fn len(&self) -> usize {
+        self.scheduled.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scheduled.is_empty()
+    }
+
+    /// Cancel everything
+    pub fn clear(&mut self) {
+        self.scheduled.clear();
+    }
+
+    /// Advance by `dt` and return the values of every timer that went off,
+    /// in the order they were due
+    pub fn update(&mut self, dt: f32) -> Vec<T>
+    where
+        T: Clone,
+    {
+        // (seconds into this update, scheduling order, value)
+        let mut due = Vec::new();
+        self.scheduled.retain_mut(|s| {
+            s.remaining -= dt;
+            match s.interval {
+                None if s.remaining <= 0.0 => {
+                    due.push((dt + s.remaining, s.handle, s.value.clone()));
+                    false
+                }
+                Some(interval) => {
+                    while s.remaining <= 0.0 {
+                        due.push((dt + s.remaining, s.handle, s.value.clone()));
+                        if interval <= 0.0 {
+                            s.remaining = 0.0;
+                            break;
+                        }
+                        s.remaining += interval;
+                    }
+                    true
+                }
+                None => true,
+            }
+        });
+        due.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+        due.into_iter().map(|(_, _, value)| value).collect()
+    }
+
+    fn schedule(&mut self, seconds: f32, interval: Option<f32>, value: T) -> TimerHandle {
+        let handle = TimerHandle(self.next);
+        self.next += 1;
+        self.scheduled.push(Scheduled {
+            handle,
+            remaining: seconds.max(0.0),
+            interval: interval.map(|interval| interval.max(0.0)),
+            value,
+        });
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_go_off_once_or_every_interval() {
+        let mut once = Timer::new(0.5);
+        assert_eq!(once.update(0.25), 0);
+        assert_eq!(once.progress(), 0.5);
+        assert_eq!(once.update(1.0), 1);
+        assert!(once.is_finished());
+        assert_eq!((once.remaining(), once.progress()), (0.0, 1.0));
+        assert_eq!(once.update(1.0), 0);
+        once.reset();
+        assert_eq!(once.remaining(), 0.5);
+
+        // Several times in one long update, keeping the remainder
+        let mut repeating = Timer::repeating(0.25);
+        assert_eq!(repeating.update(1.125), 4);
+        assert_eq!(repeating.remaining(), 0.125);
+        assert_eq!(repeating.update(0.125), 1);
+        assert_eq!(repeating.update(0.125), 0);
+        assert!(!repeating.is_finished());
+
+        // Without an interval, once per update
+        let mut every_update = Timer::repeating(0.0);
+        assert_eq!(every_update.update(1.0), 1);
+        assert_eq!(every_update.update(0.0), 1);
+    }
+
+    #[test]
+    fn values_come_back_in_the_order_they_were_due() {
+        let mut timers = Timers::new();
+        timers.after(0.75, "last");
+        timers.after(0.125, "first");
+        timers.every(0.25, "tick");
+        timers.after(0.5, "tied");
+        assert_eq!(timers.len(), 4);
+
+        // Ties go in scheduling order
+        assert_eq!(
+            timers.update(1.0),
+            ["first", "tick", "tick", "tied", "last", "tick", "tick"]
+        );
+        assert_eq!(timers.len(), 1);
+        assert_eq!(timers.update(0.125), [] as [&str; 0]);
+        assert_eq!(timers.update(0.125), ["tick"]);
+    }
+
+    #[test]
+    fn cancelled_and_fired_timers_are_gone() {
+        let mut timers = Timers::new();
+        let once = timers.after(0.5, 1);
+        let repeating = timers.every(0.5, 2);
+        let cancelled = timers.after(0.25, 3);
+
+        assert_eq!(timers.cancel(cancelled), Some(3));
+        assert_eq!(timers.cancel(cancelled), None);
+        assert!(!timers.is_active(cancelled));
+        assert_eq!(timers.remaining(once), Some(0.5));
+
+        assert_eq!(timers.update(0.75), [1, 2]);
+        assert!(!timers.is_active(once));
+        assert_eq!(timers.cancel(once), None);
+        assert_eq!(timers.remaining(once), None);
+        assert!(timers.is_active(repeating));
+        assert_eq!(timers.remaining(repeating), Some(0.25));
+
+        // Handles are never reused
+        let again = timers.after(0.5, 4);
+        assert!(![once, repeating, cancelled].contains(&again));
+
+        timers.clear();
+        assert!(timers.is_empty());
+        assert_eq!(timers.update(1.0), [] as [i32; 0]);
+    }
+}
@@ -0,0 +1,305 @@
+// panpan/src/timestep.rs
+// Fixed-timestep game loop shared by all runners
+
+use crate::{Context, Game};
+use std::time::Instant;
+
+/// How the loop turns wall-clock frame times into `Game::update` calls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimestepConfig {
+    /// Updates per second. Zero or less disables fixed stepping, so `update`
+    /// runs once per frame with the clamped frame time.
+    pub update_rate: f32,
+    /// Most updates run in a single frame. Time beyond that is dropped so a
+    /// slow device does not fall further and further behind.
+    pub max_steps: u32,
+    /// Frame times are clamped to this many seconds, e.g. after a stall in
+    /// a debugger or when the app comes back from the background
+    pub max_frame_time: f32,
+}
+
+impl TimestepConfig {
+    pub const fn fixed(update_rate: f32) -> Self {
+        Self {
+            update_rate,
+            max_steps: 5,
+            max_frame_time: 0.25,
+        }
+    }
+
+    /// Update once per frame with the (clamped) frame time
+    pub const fn variable() -> Self {
+        Self::fixed(0.0)
+    }
+}
+
+impl Default for TimestepConfig {
+    fn default() -> Self {
+        Self::fixed(60.0)
+    }
+}
+
+/// Accumulates frame time and runs `Game::update` in fixed steps
+#[derive(Debug, Clone)]
+pub struct GameLoop {
+    config: TimestepConfig,
+    accumulator: f32,
+    alpha: f32,
+    paused: bool,
+    /// The first frame after a resume measures the time spent in the
+    /// background, which must not be simulated
+    discard_next: bool,
+}
+
+impl GameLoop {
+    pub fn new(config: TimestepConfig) -> Self {
+        Self {
+            config,
+            accumulator: 0.0,
+            alpha: 1.0,
+            paused: false,
+            discard_next: false,
+        }
+    }
+
+    pub fn config(&self) -> &TimestepConfig {
+        &self.config
+    }
+
+    /// Length of one fixed update, or `None` for a variable timestep
+    pub fn fixed_dt(&self) -> Option<f32> {
+        (self.config.update_rate > 0.0).then(|| 1.0 / self.config.update_rate)
+    }
+
+    /// How far rendering is between the last two updates, in 0.0..1.0
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stop updating and tell the game. Does nothing if already paused, so
+    /// runners can call it from every event that implies a pause.
+    pub fn pause<G: Game>(&mut self, game: &mut G) {
+        if !self.paused {
+            self.paused = true;
+            game.on_pause();
+        }
+    }
+
+    /// Resume updating and tell the game. The time spent paused is skipped.
+    pub fn resume<G: Game>(&mut self, game: &mut G) {
+        if self.paused {
+            self.paused = false;
+            self.accumulator = 0.0;
+            self.discard_next = true;
+            game.on_resume();
+        }
+    }
+
+    /// True when the next call to `update` ignores its frame time because the
+    /// loop was just resumed
+    pub fn skips_next_frame(&self) -> bool {
+        self.discard_next
+    }
+
+    /// Run as many updates as `frame_dt` calls for and return how many ran.
+    /// Nothing runs while paused.
+    pub fn update<G: Game>(&mut self, game: &mut G, ctx: &mut Context, frame_dt: f32) -> u32 {
+        if self.paused {
+            return 0;
+        }
+        if std::mem::take(&mut self.discard_next) {
+            return 0;
+        }
+
+        // NaN and negative times (clock adjustments) count as no time at all
+        let frame_dt = if frame_dt > 0.0 { frame_dt } else { 0.0 };
+        let dt = frame_dt.min(self.config.max_frame_time);
+        ctx.time_mut().begin_frame(frame_dt);
+
+        let start = Instant::now();
+        let steps = match self.fixed_dt() {
+            None => {
+                ctx.scope(|ctx| {
+                    ctx.time_mut().begin_update(dt);
+                    game.update(ctx, dt);
+                });
+                self.alpha = 1.0;
+                1
+            }
+            Some(step) => {
+                self.accumulator += dt;
+                let mut steps = 0;
+                ctx.scope(|ctx| {
+                    while self.accumulator >= step && steps < self.config.max_steps {
+                        ctx.time_mut().begin_update(step);
+                        game.update(ctx, step);
+                        self.accumulator -= step;
+                        steps += 1;
+                    }
+                });
+                if self.accumulator >= step {
+                    // Hit the step limit, drop the backlog instead of spiralling
+                    self.accumulator %= step;
+                }
+                self.alpha = self.accumulator / step;
+                steps
+            }
+        };
+        ctx.time_mut().end_update(start.elapsed());
+        steps
+    }
+
+    /// Render with the current interpolation alpha
+    pub fn render<G: Game>(&self, game: &G, ctx: &mut Context) {
+        let start = Instant::now();
+        ctx.scope(|ctx| game.render_interpolated(ctx, self.alpha));
+        ctx.time_mut().end_render(start.elapsed());
+    }
+
+    /// Update and render one frame
+    pub fn frame<G: Game>(&mut self, game: &mut G, ctx: &mut Context, frame_dt: f32) {
+        self.update(game, ctx, frame_dt);
+        self.render(game, ctx);
+    }
+}
+
+impl Default for GameLoop {
+    fn default() -> Self {
+        Self::new(TimestepConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 64 updates per second, so steps and the frame times below are exact
+    /// in binary
+    const STEP: f32 = 1.0 / 64.0;
+
+    #[derive(Default)]
+    struct Steps {
+        dts: Vec<f32>,
+        pauses: u32,
+        resumes: u32,
+    }
+
+    impl Game for Steps {
+        fn new(_ctx: &mut Context) -> Self {
+            Self::default()
+        }
+
+        fn update(&mut self, _ctx: &mut Context, dt: f32) {
+            self.dts.push(dt);
+        }
+
+        fn render(&self, _ctx: &mut Context) {}
+
+        fn on_pause(&mut self) {
+            self.pauses += 1;
+        }
+
+        fn on_resume(&mut self) {
+            self.resumes += 1;
+        }
+    }
+
+    fn setup(config: TimestepConfig) -> (GameLoop, Steps, Context) {
+        let mut ctx = Context::new();
+        (GameLoop::new(config), Steps::new(&mut ctx), ctx)
+    }
+
+    #[test]
+    fn fixed_steps_carry_the_remainder() {
+        let (mut game_loop, mut game, mut ctx) = setup(TimestepConfig::fixed(64.0));
+        assert_eq!(game_loop.fixed_dt(), Some(STEP));
+
+        let frames = [1.5 * STEP, 1.5 * STEP, 0.25 * STEP, 0.75 * STEP, STEP];
+        let mut counts = Vec::new();
+        let mut alphas = Vec::new();
+        for frame in frames {
+            counts.push(game_loop.update(&mut game, &mut ctx, frame));
+            alphas.push(game_loop.alpha());
+        }
+        assert_eq!(counts, [1, 2, 0, 1, 1]);
+        assert_eq!(alphas, [0.5, 0.0, 0.25, 0.0, 0.0]);
+        assert!(game.dts.iter().all(|&dt| dt == STEP));
+        assert_eq!(game.dts.len(), 5);
+    }
+
+    #[test]
+    fn alpha_stays_below_one() {
+        let (mut game_loop, mut game, mut ctx) = setup(TimestepConfig::fixed(60.0));
+        for i in 0..200 {
+            // Uneven frames between 5 and 40 ms
+            let frame = 0.005 + (i * 7 % 36) as f32 * 0.001;
+            game_loop.update(&mut game, &mut ctx, frame);
+            let alpha = game_loop.alpha();
+            assert!((0.0..1.0).contains(&alpha), "{alpha} after {frame}");
+        }
+    }
+
+    #[test]
+    fn slow_frames_are_capped() {
+        let config = TimestepConfig {
+            max_steps: 3,
+            ..TimestepConfig::fixed(64.0)
+        };
+        let (mut game_loop, mut game, mut ctx) = setup(config);
+        // Eight and a half steps due, three run and the backlog is dropped
+        // except for the fraction
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 8.5 * STEP), 3);
+        assert_eq!(game_loop.alpha(), 0.5);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 0.5 * STEP), 1);
+        assert_eq!(game_loop.alpha(), 0.0);
+    }
+
+    #[test]
+    fn frame_times_are_clamped() {
+        let config = TimestepConfig {
+            max_steps: 100,
+            ..TimestepConfig::fixed(64.0)
+        };
+        let (mut game_loop, mut game, mut ctx) = setup(config);
+        // 0.25 s at most
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 10.0), 16);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, -1.0), 0);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, f32::NAN), 0);
+        assert_eq!(game_loop.alpha(), 0.0);
+
+        let (mut game_loop, mut game, mut ctx) = setup(TimestepConfig::variable());
+        assert_eq!(game_loop.fixed_dt(), None);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 0.01), 1);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 3.0), 1);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, -1.0), 1);
+        assert_eq!(game.dts, [0.01, 0.25, 0.0]);
+        assert_eq!(game_loop.alpha(), 1.0);
+    }
+
+    #[test]
+    fn resume_discards_the_time_away() {
+        let (mut game_loop, mut game, mut ctx) = setup(TimestepConfig::fixed(64.0));
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 1.5 * STEP), 1);
+
+        game_loop.pause(&mut game);
+        game_loop.pause(&mut game);
+        assert!(game_loop.is_paused());
+        assert_eq!(game_loop.update(&mut game, &mut ctx, STEP), 0);
+
+        game_loop.resume(&mut game);
+        game_loop.resume(&mut game);
+        assert_eq!((game.pauses, game.resumes), (1, 1));
+        assert!(game_loop.skips_next_frame());
+        // The frame spanning the pause is dropped, and so is the half step
+        // left over from before it
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 5.0), 0);
+        assert!(!game_loop.skips_next_frame());
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 0.5 * STEP), 0);
+        assert_eq!(game_loop.update(&mut game, &mut ctx, 0.5 * STEP), 1);
+        assert_eq!(game.dts.len(), 2);
+    }
+}
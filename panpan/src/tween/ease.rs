@@ -0,0 +1,247 @@
+// panpan/src/tween/ease.rs
+// Easing curves, the formulas from easings.net
+//
+// Every curve maps 0.0 to 0.0 and 1.0 to 1.0. Back and elastic go outside
+// 0.0..1.0 on the way, so the values they drive overshoot.
+
+use std::f32::consts::PI;
+
+/// How far back the `Back` curves pull
+const BACK_OVERSHOOT: f32 = 1.70158;
+
+/// Shape of a tween over time. `In` curves start slow, `Out` curves end
+/// slow, `InOut` curves do both.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Ease {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    QuartIn,
+    QuartOut,
+    QuartInOut,
+    QuintIn,
+    QuintOut,
+    QuintInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    CircIn,
+    CircOut,
+    CircInOut,
+    /// Pulls back before moving
+    BackIn,
+    /// Overshoots the target and settles back
+    BackOut,
+    BackInOut,
+    /// Wobbles like a spring
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    /// Bounces like a dropped ball
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+    /// Any other curve
+    Custom(fn(f32) -> f32),
+}
+
+impl Ease {
+    /// Eased progress for `t` in 0.0..=1.0
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => out(t, |t| t * t),
+            Ease::QuadInOut => in_out(t, |t| t * t),
+            Ease::CubicIn => t * t * t,
+            Ease::CubicOut => out(t, |t| t * t * t),
+            Ease::CubicInOut => in_out(t, |t| t * t * t),
+            Ease::QuartIn => t.powi(4),
+            Ease::QuartOut => out(t, |t| t.powi(4)),
+            Ease::QuartInOut => in_out(t, |t| t.powi(4)),
+            Ease::QuintIn => t.powi(5),
+            Ease::QuintOut => out(t, |t| t.powi(5)),
+            Ease::QuintInOut => in_out(t, |t| t.powi(5)),
+            Ease::SineIn => sine_in(t),
+            Ease::SineOut => out(t, sine_in),
+            Ease::SineInOut => in_out(t, sine_in),
+            Ease::ExpoIn => expo_in(t),
+            Ease::ExpoOut => out(t, expo_in),
+            Ease::ExpoInOut => in_out(t, expo_in),
+            Ease::CircIn => circ_in(t),
+            Ease::CircOut => out(t, circ_in),
+            Ease::CircInOut => in_out(t, circ_in),
+            Ease::BackIn => back_in(t, BACK_OVERSHOOT),
+            Ease::BackOut => out(t, |t| back_in(t, BACK_OVERSHOOT)),
+            // Each half is half as long, so it pulls back further
+            Ease::BackInOut => in_out(t, |t| back_in(t, BACK_OVERSHOOT * 1.525)),
+            Ease::ElasticIn => elastic_in(t),
+            Ease::ElasticOut => out(t, elastic_in),
+            Ease::ElasticInOut => elastic_in_out(t),
+            Ease::BounceIn => out(t, bounce_out),
+            Ease::BounceOut => bounce_out(t),
+            Ease::BounceInOut => in_out(t, |t| out(t, bounce_out)),
+            Ease::Custom(f) => f(t),
+        }
+    }
+}
+
+/// The `In` curve played backwards
+fn out(t: f32, curve: impl Fn(f32) -> f32) -> f32 {
+    1.0 - curve(1.0 - t)
+}
+
+/// The `In` curve for the first half, the `Out` curve for the second
+fn in_out(t: f32, curve: impl Fn(f32) -> f32) -> f32 {
+    if t < 0.5 {
+        curve(t * 2.0) * 0.5
+    } else {
+        1.0 - curve((1.0 - t) * 2.0) * 0.5
+    }
+}
+
+fn sine_in(t: f32) -> f32 {
+    1.0 - (t * PI * 0.5).cos()
+}
+
+fn expo_in(t: f32) -> f32 {
+    if t <= 0.0 {
+        0.0
+    } else {
+        2.0f32.powf(10.0 * t - 10.0)
+    }
+}
+
+fn circ_in(t: f32) -> f32 {
+    1.0 - (1.0 - t * t).sqrt()
+}
+
+fn back_in(t: f32, overshoot: f32) -> f32 {
+    t * t * ((overshoot + 1.0) * t - overshoot)
+}
+
+fn elastic_in(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    -(2.0f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * (2.0 * PI / 3.0)).sin()
+}
+
+/// Not two halves of `elastic_in`: the wobble has a longer period
+fn elastic_in_out(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    let wave = ((20.0 * t - 11.125) * (2.0 * PI / 4.5)).sin();
+    if t < 0.5 {
+        -(2.0f32.powf(20.0 * t - 10.0)) * wave * 0.5
+    } else {
+        2.0f32.powf(-20.0 * t + 10.0) * wave * 0.5 + 1.0
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Ease; 31] = [
+        Ease::Linear,
+        Ease::QuadIn,
+        Ease::QuadOut,
+        Ease::QuadInOut,
+        Ease::CubicIn,
+        Ease::CubicOut,
+        Ease::CubicInOut,
+        Ease::QuartIn,
+        Ease::QuartOut,
+        Ease::QuartInOut,
+        Ease::QuintIn,
+        Ease::QuintOut,
+        Ease::QuintInOut,
+        Ease::SineIn,
+        Ease::SineOut,
+        Ease::SineInOut,
+        Ease::ExpoIn,
+        Ease::ExpoOut,
+        Ease::ExpoInOut,
+        Ease::CircIn,
+        Ease::CircOut,
+        Ease::CircInOut,
+        Ease::BackIn,
+        Ease::BackOut,
+        Ease::BackInOut,
+        Ease::ElasticIn,
+        Ease::ElasticOut,
+        Ease::ElasticInOut,
+        Ease::BounceIn,
+        Ease::BounceOut,
+        Ease::BounceInOut,
+    ];
+
+    #[test]
+    fn curves_start_and_end_in_place() {
+        for ease in ALL {
+            assert!(ease.apply(0.0).abs() < 1e-6, "{ease:?}");
+            assert!((ease.apply(1.0) - 1.0).abs() < 1e-6, "{ease:?}");
+            assert!(
+                (ease.apply(0.5 - 1e-4) - ease.apply(0.5)).abs() < 1e-2,
+                "{ease:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn in_out_curves_match_easings_net() {
+        // Written out as on easings.net
+        let back = |t: f32| {
+            let c2 = 1.70158 * 1.525;
+            if t < 0.5 {
+                (2.0 * t).powi(2) * ((c2 + 1.0) * 2.0 * t - c2) / 2.0
+            } else {
+                ((2.0 * t - 2.0).powi(2) * ((c2 + 1.0) * (t * 2.0 - 2.0) + c2) + 2.0) / 2.0
+            }
+        };
+        let elastic = |t: f32| {
+            let c5 = (2.0 * PI) / 4.5;
+            if t < 0.5 {
+                -(2.0f32.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * c5).sin()) / 2.0
+            } else {
+                (2.0f32.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * c5).sin()) / 2.0 + 1.0
+            }
+        };
+        for i in 1..20 {
+            let t = i as f32 / 20.0;
+            assert!((Ease::BackInOut.apply(t) - back(t)).abs() < 1e-5, "{t}");
+            assert!(
+                (Ease::ElasticInOut.apply(t) - elastic(t)).abs() < 1e-5,
+                "{t}"
+            );
+        }
+    }
+}
@@ -0,0 +1,743 @@
+// panpan/src/tween/mod.rs
+// Tweens: values that move from one end to another over time
+//
+// A `Tween` eases a value between two ends and is advanced by the `dt` of
+// `Game::update`. Tweens combine into a `Sequence` (one after another) or a
+// `Parallel` group (all at once), each holding a `Vec`, array or tuple of
+// tweens so every value stays readable; groups nest. Time left over when
+// something finishes carries into what comes next, so sequences keep exact
+// timing at any frame rate.
+
+mod ease;
+
+pub use ease::Ease;
+
+use crate::types::{Color, Rect, Vec2, Vec3};
+
+/// Values a tween can move between
+pub trait Lerp: Copy {
+    /// `self` at `t` = 0.0, `to` at `t` = 1.0. Overshooting eases pass `t`
+    /// outside that range.
+    fn lerp(self, to: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, to: f32, t: f32) -> f32 {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(self, to: Vec2, t: f32) -> Vec2 {
+        Vec2::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, to: Vec3, t: f32) -> Vec3 {
+        Vec3::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, to: Color, t: f32) -> Color {
+        Color::lerp(self, to, t)
+    }
+}
+
+impl Lerp for Rect {
+    fn lerp(self, to: Rect, t: f32) -> Rect {
+        Rect::new(
+            self.x.lerp(to.x, t),
+            self.y.lerp(to.y, t),
+            self.width.lerp(to.width, t),
+            self.height.lerp(to.height, t),
+        )
+    }
+}
+
+/// Anything that plays over time: tweens, delays and groups of them
+pub trait Tweenable {
+    /// Move forward by `dt` seconds. Returns how much of `dt` was left after
+    /// finishing, 0.0 while still playing.
+    fn advance(&mut self, dt: f32) -> f32;
+
+    fn is_finished(&self) -> bool;
+
+    /// Back to the start, as if just created
+    fn reset(&mut self);
+}
+
+impl<T: Tweenable + ?Sized> Tweenable for Box<T> {
+    fn advance(&mut self, dt: f32) -> f32 {
+        (**self).advance(dt)
+    }
+
+    fn is_finished(&self) -> bool {
+        (**self).is_finished()
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+/// Eases a value from `from` to `to` over `duration` seconds
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    duration: f32,
+    ease: Ease,
+    delay: f32,
+    /// Extra plays after the first, `None` forever
+    repeat: Option<u32>,
+    yoyo: bool,
+    on_complete: Option<Box<dyn FnMut()>>,
+
+    delay_left: f32,
+    /// Into the current play
+    time: f32,
+    remaining: Option<u32>,
+    /// Playing from `to` back to `from`
+    reversed: bool,
+    finished: bool,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration: duration.max(0.0),
+            ease: Ease::Linear,
+            delay: 0.0,
+            repeat: Some(0),
+            yoyo: false,
+            on_complete: None,
+            delay_left: 0.0,
+            time: 0.0,
+            remaining: Some(0),
+            reversed: false,
+            finished: false,
+        }
+    }
+
+    pub fn with_ease(mut self, ease: Ease) -> Self {
+        self.ease = ease;
+        self
+    }
+
+    /// Hold at `from` for `seconds` before starting. Only the first play
+    /// waits, not repeats.
+    pub fn with_delay(mut self, seconds: f32) -> Self {
+        self.delay = seconds.max(0.0);
+        self.delay_left = self.delay;
+        self
+    }
+
+    /// Play `times` more times after the first
+    pub fn with_repeat(mut self, times: u32) -> Self {
+        self.repeat = Some(times);
+        self.remaining = self.repeat;
+        self
+    }
+
+    /// Repeat forever; the tween never finishes
+    pub fn looping(mut self) -> Self {
+        self.repeat = None;
+        self.remaining = None;
+        self
+    }
+
+    /// Every other play goes from `to` back to `from`
+    pub fn yoyo(mut self) -> Self {
+        self.yoyo = true;
+        self
+    }
+
+    /// Call `f` once the tween finishes, from the `advance` that finished it
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+
+    /// Advance by `dt` and return the new value
+    pub fn update(&mut self, dt: f32) -> T {
+        self.advance(dt);
+        self.value()
+    }
+
+    pub fn value(&self) -> T {
+        let t = self.progress();
+        let t = if self.reversed { 1.0 - t } else { t };
+        self.from.lerp(self.to, self.ease.apply(t))
+    }
+
+    /// How far into the current play, 0.0..=1.0 before easing
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.time / self.duration
+        } else if self.delay_left > 0.0 {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    pub fn from(&self) -> T {
+        self.from
+    }
+
+    pub fn to(&self) -> T {
+        self.to
+    }
+
+    /// Start over from the current value towards `to`, e.g. when a button
+    /// is hovered again before its last tween finished
+    pub fn retarget(&mut self, to: T) {
+        self.from = self.value();
+        self.to = to;
+        self.reset();
+    }
+
+    fn finish(&mut self) {
+        self.finished = true;
+        if let Some(f) = &mut self.on_complete {
+            f();
+        }
+    }
+}
+
+impl<T: Lerp> Tweenable for Tween<T> {
+    fn advance(&mut self, mut dt: f32) -> f32 {
+        if self.finished {
+            return dt;
+        }
+        if self.delay_left > 0.0 {
+            let waited = dt.min(self.delay_left);
+            self.delay_left -= waited;
+            dt -= waited;
+            if self.delay_left > 0.0 {
+                return 0.0;
+            }
+        }
+        self.time += dt;
+        while self.time >= self.duration {
+            match &mut self.remaining {
+                Some(0) => {
+                    let left = self.time - self.duration;
+                    self.time = self.duration;
+                    self.finish();
+                    return left;
+                }
+                Some(n) => *n -= 1,
+                // Looping forever without length, nothing left to play
+                None if self.duration == 0.0 => return 0.0,
+                None => {}
+            }
+            self.time -= self.duration;
+            self.reversed ^= self.yoyo;
+        }
+        0.0
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn reset(&mut self) {
+        self.delay_left = self.delay;
+        self.time = 0.0;
+        self.remaining = self.repeat;
+        self.reversed = false;
+        self.finished = false;
+    }
+}
+
+/// Waits without changing anything, e.g. a pause inside a `Sequence`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Delay {
+    duration: f32,
+    elapsed: f32,
+}
+
+impl Delay {
+    pub fn new(seconds: f32) -> Self {
+        Self {
+            duration: seconds.max(0.0),
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl Tweenable for Delay {
+    fn advance(&mut self, dt: f32) -> f32 {
+        if self.is_finished() {
+            return dt;
+        }
+        self.elapsed += dt;
+        let left = (self.elapsed - self.duration).max(0.0);
+        self.elapsed = self.elapsed.min(self.duration);
+        left
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+/// What groups hold: a `Vec`, array or tuple of tweenables. Use
+/// `Vec<Box<dyn Tweenable>>` to mix types without a tuple.
+pub trait TweenList {
+    fn count(&self) -> usize;
+
+    fn get_mut(&mut self, index: usize) -> &mut dyn Tweenable;
+
+    fn reset_all(&mut self) {
+        for i in 0..self.count() {
+            self.get_mut(i).reset();
+        }
+    }
+}
+
+impl<T: Tweenable> TweenList for Vec<T> {
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut dyn Tweenable {
+        &mut self[index]
+    }
+}
+
+impl<T: Tweenable, const N: usize> TweenList for [T; N] {
+    fn count(&self) -> usize {
+        N
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut dyn Tweenable {
+        &mut self[index]
+    }
+}
+
+macro_rules! tuple_lists {
+    ($(($($name:ident $index:tt),+))*) => {$(
+        impl<$($name: Tweenable),+> TweenList for ($($name,)+) {
+            fn count(&self) -> usize {
+                [$($index),+].len()
+            }
+
+            fn get_mut(&mut self, index: usize) -> &mut dyn Tweenable {
+                match index {
+                    $($index => &mut self.$index,)+
+                    _ => panic!("index {} out of range", index),
+                }
+            }
+        }
+    )*};
+}
+
+tuple_lists! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7)
+}
+
+/// Plays its tweens one after another
+pub struct Sequence<L> {
+    tweens: L,
+    current: usize,
+    repeat: Option<u32>,
+    remaining: Option<u32>,
+    on_complete: Option<Box<dyn FnMut()>>,
+    finished: bool,
+}
+
+impl<L: TweenList> Sequence<L> {
+    pub fn new(tweens: L) -> Self {
+        Self {
+            tweens,
+            current: 0,
+            repeat: Some(0),
+            remaining: Some(0),
+            on_complete: None,
+            finished: false,
+        }
+    }
+
+    /// Play the whole sequence `times` more times after the first
+    pub fn with_repeat(mut self, times: u32) -> Self {
+        self.repeat = Some(times);
+        self.remaining = self.repeat;
+        self
+    }
+
+    /// Start over forever; the sequence never finishes
+    pub fn looping(mut self) -> Self {
+        self.repeat = None;
+        self.remaining = None;
+        self
+    }
+
+    /// Call `f` once the last tween finishes
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+
+    pub fn tweens(&self) -> &L {
+        &self.tweens
+    }
+
+    pub fn tweens_mut(&mut self) -> &mut L {
+        &mut self.tweens
+    }
+
+    /// Index of the tween playing now
+    pub fn current(&self) -> usize {
+        self.current
+    }
+}
+
+impl<L: TweenList> Tweenable for Sequence<L> {
+    fn advance(&mut self, mut dt: f32) -> f32 {
+        if self.finished {
+            return dt;
+        }
+        loop {
+            let (start, whole) = (dt, self.current == 0);
+            while self.current < self.tweens.count() {
+                let tween = self.tweens.get_mut(self.current);
+                dt = tween.advance(dt);
+                if !tween.is_finished() {
+                    return 0.0;
+                }
+                self.current += 1;
+            }
+            match &mut self.remaining {
+                Some(0) => {
+                    self.finished = true;
+                    if let Some(f) = &mut self.on_complete {
+                        f();
+                    }
+                    return dt;
+                }
+                Some(n) => *n -= 1,
+                // Looping forever without length, nothing left to play
+                None if whole && dt >= start => return 0.0,
+                None => {}
+            }
+            self.tweens.reset_all();
+            self.current = 0;
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn reset(&mut self) {
+        self.tweens.reset_all();
+        self.current = 0;
+        self.remaining = self.repeat;
+        self.finished = false;
+    }
+}
+
+/// Plays its tweens all at once, finished when the longest is
+pub struct Parallel<L> {
+    tweens: L,
+    repeat: Option<u32>,
+    remaining: Option<u32>,
+    on_complete: Option<Box<dyn FnMut()>>,
+    finished: bool,
+}
+
+impl<L: TweenList> Parallel<L> {
+    pub fn new(tweens: L) -> Self {
+        Self {
+            tweens,
+            repeat: Some(0),
+            remaining: Some(0),
+            on_complete: None,
+            finished: false,
+        }
+    }
+
+    /// Play the whole group `times` more times after the first
+    pub fn with_repeat(mut self, times: u32) -> Self {
+        self.repeat = Some(times);
+        self.remaining = self.repeat;
+        self
+    }
+
+    /// Start over forever; the group never finishes
+    pub fn looping(mut self) -> Self {
+        self.repeat = None;
+        self.remaining = None;
+        self
+    }
+
+    /// Call `f` once every tween finished
+    pub fn on_complete(mut self, f: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(f));
+        self
+    }
+
+    pub fn tweens(&self) -> &L {
+        &self.tweens
+    }
+
+    pub fn tweens_mut(&mut self) -> &mut L {
+        &mut self.tweens
+    }
+}
+
+impl<L: TweenList> Tweenable for Parallel<L> {
+    fn advance(&mut self, mut dt: f32) -> f32 {
+        if self.finished {
+            return dt;
+        }
+        loop {
+            let start = dt;
+            let mut playing = false;
+            for i in 0..self.tweens.count() {
+                let tween = self.tweens.get_mut(i);
+                let left = tween.advance(start);
+                if tween.is_finished() {
+                    // Time left after the last one finished
+                    dt = dt.min(left);
+                } else {
+                    playing = true;
+                }
+            }
+            if playing {
+                return 0.0;
+            }
+            match &mut self.remaining {
+                Some(0) => {
+                    self.finished = true;
+                    if let Some(f) = &mut self.on_complete {
+                        f();
+                    }
+                    return dt;
+                }
+                Some(n) => *n -= 1,
+                // Looping forever without length, nothing left to play
+                None if dt >= start => return 0.0,
+                None => {}
+            }
+            self.tweens.reset_all();
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn reset(&mut self) {
+        self.tweens.reset_all();
+        self.remaining = self.repeat;
+        self.finished = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// A callback that counts its calls
+    fn counter() -> (Rc<Cell<u32>>, impl FnMut() + 'static) {
+        let calls = Rc::new(Cell::new(0));
+        let count = calls.clone();
+        (calls, move || count.set(count.get() + 1))
+    }
+
+    #[test]
+    fn tweens_return_the_time_left_over() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0);
+        assert_eq!(tween.advance(0.25), 0.0);
+        assert_eq!(tween.value(), 2.5);
+        assert_eq!(tween.advance(1.0), 0.25);
+        assert!(tween.is_finished());
+        assert_eq!(tween.value(), 10.0);
+        // Finished tweens hand back all of it
+        assert_eq!(tween.advance(0.5), 0.5);
+
+        tween.reset();
+        assert_eq!(tween.update(0.5), 5.0);
+    }
+
+    #[test]
+    fn sequences_carry_the_left_over_time() {
+        let (calls, on_complete) = counter();
+        let mut sequence = Sequence::new((Tween::new(0.0, 1.0, 0.5), Tween::new(0.0, 1.0, 0.5)))
+            .on_complete(on_complete);
+
+        let mut steps = 0;
+        let mut left = 0.0;
+        while !sequence.is_finished() {
+            left = sequence.advance(0.3);
+            steps += 1;
+            if steps == 2 {
+                // 0.1 s into the second tween
+                assert_eq!(sequence.current(), 1);
+                assert!((sequence.tweens().1.value() - 0.2).abs() < 1e-5);
+            }
+        }
+        // Done at 1.0 s, during the fourth step, with 0.2 s to spare
+        assert_eq!(steps, 4);
+        assert!((left - 0.2).abs() < 1e-5, "{left}");
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(sequence.advance(0.3), 0.3);
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn sequences_repeat_and_skip_through_short_tweens() {
+        let mut sequence = Sequence::new(vec![Delay::new(0.25), Delay::new(0.25)]).with_repeat(2);
+        // Several tweens and a repeat within one step
+        assert_eq!(sequence.advance(0.75), 0.0);
+        assert_eq!(sequence.current(), 1);
+        assert_eq!(sequence.advance(1.0), 0.25);
+        assert!(sequence.is_finished());
+
+        sequence.reset();
+        assert_eq!((sequence.current(), sequence.is_finished()), (0, false));
+        assert_eq!(sequence.advance(0.25), 0.0);
+        assert_eq!(sequence.current(), 1);
+    }
+
+    #[test]
+    fn parallel_groups_end_with_the_longest() {
+        let (calls, on_complete) = counter();
+        let mut group = Parallel::new((
+            Tween::new(0.0, 1.0, 0.5),
+            Tween::new(Vec2::ZERO, Vec2::new(4.0, 0.0), 1.0),
+        ))
+        .on_complete(on_complete);
+
+        assert_eq!(group.advance(0.75), 0.0);
+        assert_eq!(group.tweens().0.value(), 1.0);
+        assert_eq!(group.tweens().1.value(), Vec2::new(3.0, 0.0));
+        assert_eq!(group.advance(0.5), 0.25);
+        assert!(group.is_finished());
+        assert_eq!(group.advance(0.5), 0.5);
+        assert_eq!(calls.get(), 1);
+
+        // Repeats start every tween over, with the time left
+        let mut group = Parallel::new([Delay::new(0.5), Delay::new(1.0)]).with_repeat(1);
+        assert_eq!(group.advance(1.25), 0.0);
+        assert!(!group.tweens()[0].is_finished());
+        assert_eq!(group.advance(1.0), 0.25);
+    }
+
+    #[test]
+    fn yoyo_repeats_end_back_at_the_start() {
+        let (calls, on_complete) = counter();
+        let mut tween = Tween::new(0.0, 10.0, 1.0)
+            .with_repeat(1)
+            .yoyo()
+            .on_complete(on_complete);
+        assert_eq!(tween.update(0.75), 7.5);
+        // On the way back
+        assert_eq!(tween.update(0.5), 7.5);
+        assert!(!tween.is_finished());
+        assert_eq!(tween.advance(1.0), 0.25);
+        assert_eq!(tween.value(), 0.0);
+        assert_eq!(calls.get(), 1);
+
+        // Without yoyo each play starts over from `from`
+        let mut tween = Tween::new(0.0, 10.0, 1.0).with_repeat(1);
+        assert_eq!(tween.update(1.25), 2.5);
+        assert_eq!(tween.advance(1.0), 0.25);
+        assert_eq!(tween.value(), 10.0);
+    }
+
+    #[test]
+    fn delay_applies_to_the_first_play_only() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0).with_delay(0.5).with_repeat(1);
+        assert_eq!(tween.update(0.25), 0.0);
+        assert_eq!(tween.progress(), 0.0);
+        // The rest of the delay, then a quarter of the first play
+        assert_eq!(tween.update(0.5), 2.5);
+        assert_eq!(tween.update(1.0), 2.5);
+        assert_eq!(tween.advance(1.0), 0.25);
+        assert!(tween.is_finished());
+
+        // Reset waits again
+        tween.reset();
+        assert_eq!(tween.update(0.5), 0.0);
+        assert_eq!(tween.update(0.5), 5.0);
+
+        // A zero-length tween waits out its delay before jumping
+        let mut tween = Tween::new(0.0, 10.0, 0.0).with_delay(0.5);
+        assert_eq!(tween.update(0.25), 0.0);
+        assert_eq!(tween.advance(0.5), 0.25);
+        assert_eq!(tween.value(), 10.0);
+    }
+
+    #[test]
+    fn zero_length_loops_do_not_hang() {
+        let mut tween = Tween::new(0.0, 1.0, 0.0).looping();
+        assert_eq!(tween.advance(1.0), 0.0);
+        assert!(!tween.is_finished());
+
+        let mut sequence = Sequence::new(vec![Delay::new(0.0), Delay::new(0.0)]).looping();
+        assert_eq!(sequence.advance(1.0), 0.0);
+        assert!(!sequence.is_finished());
+
+        let mut group = Parallel::new(vec![Delay::new(0.0)]).looping();
+        assert_eq!(group.advance(1.0), 0.0);
+        assert!(!group.is_finished());
+
+        // Loops with length still go round as often as `dt` covers
+        let mut sequence = Sequence::new((Delay::new(0.25), Delay::new(0.0))).looping();
+        assert_eq!(sequence.advance(0.6), 0.0);
+        assert!(!sequence.tweens().0.is_finished());
+        assert_eq!(sequence.advance(0.15), 0.0);
+        assert_eq!(sequence.current(), 0);
+    }
+
+    #[test]
+    fn on_complete_runs_once_per_finish() {
+        let (calls, on_complete) = counter();
+        let mut tween = Tween::new(0.0, 1.0, 0.5).on_complete(on_complete);
+        for _ in 0..10 {
+            tween.advance(0.2);
+        }
+        assert_eq!(calls.get(), 1);
+        tween.reset();
+        tween.advance(1.0);
+        assert_eq!(calls.get(), 2);
+
+        // Tweens finished inside a group fire as they finish
+        let (calls, on_complete) = counter();
+        let mut sequence =
+            Sequence::new(vec![Tween::new(0.0, 1.0, 0.25).on_complete(on_complete)]).with_repeat(2);
+        sequence.advance(1.0);
+        assert!(sequence.is_finished());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn retarget_starts_from_the_current_value() {
+        let mut tween = Tween::new(0.0, 10.0, 1.0);
+        tween.advance(0.5);
+        tween.retarget(0.0);
+        assert_eq!((tween.from(), tween.to(), tween.value()), (5.0, 0.0, 5.0));
+        assert_eq!(tween.update(0.5), 2.5);
+    }
+}
@@ -1,42 +1,12 @@
 // panpan/src/types.rs
 // Core data types used throughout the engine
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Color {
-    pub r: f32,
-    pub g: f32,
-    pub b: f32,
-    pub a: f32,
-}
-
-impl Color {
-    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
-        Self { r, g, b, a }
-    }
-    
-    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
-    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
-    pub const RED: Color = Color::new(1.0, 0.0, 0.0, 1.0);
-    pub const GREEN: Color = Color::new(0.0, 1.0, 0.0, 1.0);
-    pub const BLUE: Color = Color::new(0.0, 0.0, 1.0, 1.0);
-    pub const YELLOW: Color = Color::new(1.0, 1.0, 0.0, 1.0);
-    pub const CYAN: Color = Color::new(0.0, 1.0, 1.0, 1.0);
-    pub const MAGENTA: Color = Color::new(1.0, 0.0, 1.0, 1.0);
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Vec2 {
-    pub x: f32,
-    pub y: f32,
-}
-
-impl Vec2 {
-    pub const fn new(x: f32, y: f32) -> Self {
-        Self { x, y }
-    }
-}
+pub use crate::color::Color;
+pub use crate::math::{Vec2, Vec3, Mat3, Affine2};
 
+/// Axis-aligned rectangle; `x`, `y` is the top-left corner (y points down)
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -48,4 +18,310 @@ impl Rect {
     pub const fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
         Self { x, y, width, height }
     }
-}
\ No newline at end of file
+
+    /// From two opposite corners, in any order
+    pub fn from_min_max(a: Vec2, b: Vec2) -> Self {
+        let (min, max) = (a.min(b), a.max(b));
+        Self::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    pub fn from_center(center: Vec2, width: f32, height: f32) -> Self {
+        Self::new(center.x - width * 0.5, center.y - height * 0.5, width, height)
+    }
+
+    /// Smallest rect containing all `points`, `None` if there are none
+    pub fn from_points(points: impl IntoIterator<Item = Vec2>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            (min.min(p), max.max(p))
+        });
+        Some(Self::from_min_max(min, max))
+    }
+
+    pub fn left(&self) -> f32 {
+        self.x
+    }
+
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn top(&self) -> f32 {
+        self.y
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    /// Top-left corner
+    pub fn min(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    /// Bottom-right corner
+    pub fn max(&self) -> Vec2 {
+        Vec2::new(self.right(), self.bottom())
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        self.anchor(Vec2::new(0.5, 0.5))
+    }
+
+    /// Point at a fraction of the size: (0, 0) is the top-left corner,
+    /// (1, 1) the bottom-right, (0.5, 1) the middle of the bottom edge
+    pub fn anchor(&self, anchor: Vec2) -> Vec2 {
+        self.min() + self.size() * anchor
+    }
+
+    /// Same size, moved so `anchor` (as in `Rect::anchor`) is at `point`
+    pub fn anchored_at(&self, anchor: Vec2, point: Vec2) -> Rect {
+        let min = point - self.size() * anchor;
+        Rect::new(min.x, min.y, self.width, self.height)
+    }
+
+    pub fn translate(&self, offset: Vec2) -> Rect {
+        Rect::new(self.x + offset.x, self.y + offset.y, self.width, self.height)
+    }
+
+    /// Grow by `amount` on every side; negative amounts shrink
+    pub fn expand(&self, amount: f32) -> Rect {
+        Rect::new(
+            self.x - amount,
+            self.y - amount,
+            self.width + amount * 2.0,
+            self.height + amount * 2.0,
+        )
+    }
+
+    /// Shrink by `amount` on every side, never below zero size
+    pub fn shrink(&self, amount: f32) -> Rect {
+        let center = self.center();
+        let width = (self.width - amount * 2.0).max(0.0);
+        let height = (self.height - amount * 2.0).max(0.0);
+        Rect::from_center(center, width, height)
+    }
+
+    /// Edges count as inside, so touches on the border still hit
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.x
+            && point.x <= self.right()
+            && point.y >= self.y
+            && point.y <= self.bottom()
+    }
+
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.right() <= self.right()
+            && other.y >= self.y
+            && other.bottom() <= self.bottom()
+    }
+
+    /// True if the rects overlap; touching edges do not count
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// The overlapping area, `None` if the rects do not intersect
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        Some(Rect::from_min_max(
+            self.min().max(other.min()),
+            self.max().min(other.max()),
+        ))
+    }
+
+    /// Smallest rect containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect::from_min_max(self.min().min(other.min()), self.max().max(other.max()))
+    }
+
+    /// Shortest offset that moves `self` out of `other`, along one axis only;
+    /// `None` if they do not intersect
+    pub fn penetration(&self, other: &Rect) -> Option<Vec2> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let push_left = other.x - self.right();
+        let push_right = other.right() - self.x;
+        let push_up = other.y - self.bottom();
+        let push_down = other.bottom() - self.y;
+        let dx = if -push_left < push_right { push_left } else { push_right };
+        let dy = if -push_up < push_down { push_up } else { push_down };
+        Some(if dx.abs() < dy.abs() {
+            Vec2::new(dx, 0.0)
+        } else {
+            Vec2::new(0.0, dy)
+        })
+    }
+
+    /// Offset that moves `self` back inside `bounds`, zero on an axis where it
+    /// already fits. A rect larger than `bounds` is aligned to its top-left.
+    pub fn offset_to_fit(&self, bounds: &Rect) -> Vec2 {
+        let fit = |min: f32, max: f32, lo: f32, hi: f32| {
+            if min < lo || max - min > hi - lo {
+                lo - min
+            } else if max > hi {
+                hi - max
+            } else {
+                0.0
+            }
+        };
+        Vec2::new(
+            fit(self.x, self.right(), bounds.x, bounds.right()),
+            fit(self.y, self.bottom(), bounds.y, bounds.bottom()),
+        )
+    }
+
+    /// Closest point on or inside the rect
+    pub fn clamp_point(&self, point: Vec2) -> Vec2 {
+        point.max(self.min()).min(self.max())
+    }
+
+    pub fn intersects_circle(&self, center: Vec2, radius: f32) -> bool {
+        self.clamp_point(center).distance_squared(center) <= radius * radius
+    }
+
+    /// Where the segment from `start` to `end` enters the rect, as a fraction
+    /// of its length (0 if it starts inside); `None` if it misses
+    pub fn segment_entry(&self, start: Vec2, end: Vec2) -> Option<f32> {
+        let delta = end - start;
+        let (mut enter, mut exit) = (0.0f32, 1.0f32);
+        for (origin, d, lo, hi) in [
+            (start.x, delta.x, self.x, self.right()),
+            (start.y, delta.y, self.y, self.bottom()),
+        ] {
+            if d == 0.0 {
+                if origin < lo || origin > hi {
+                    return None;
+                }
+                continue;
+            }
+            let (t0, t1) = ((lo - origin) / d, (hi - origin) / d);
+            enter = enter.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+            if enter > exit {
+                return None;
+            }
+        }
+        Some(enter)
+    }
+
+    pub fn intersects_segment(&self, start: Vec2, end: Vec2) -> bool {
+        self.segment_entry(start, end).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersection_and_union() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let b = Rect::new(5.0, -5.0, 10.0, 10.0);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5.0, 0.0, 5.0, 5.0)));
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+        assert_eq!(a.union(&b), Rect::new(0.0, -5.0, 15.0, 15.0));
+
+        // Touching edges are not an intersection
+        let beside = Rect::new(10.0, 0.0, 5.0, 5.0);
+        assert!(!a.intersects(&beside));
+        assert_eq!(a.intersection(&beside), None);
+        assert_eq!(a.union(&beside), Rect::new(0.0, 0.0, 15.0, 10.0));
+    }
+
+    #[test]
+    fn penetration_takes_the_shorter_way_out() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let b = Rect::new(8.0, 2.0, 10.0, 6.0);
+        assert_eq!(a.penetration(&b), Some(Vec2::new(-2.0, 0.0)));
+        assert_eq!(b.penetration(&a), Some(Vec2::new(2.0, 0.0)));
+
+        let below = Rect::new(2.0, 9.0, 6.0, 10.0);
+        assert_eq!(a.penetration(&below), Some(Vec2::new(0.0, -1.0)));
+
+        // Deep inside, out through the nearest side
+        let inner = Rect::new(40.0, 30.0, 10.0, 10.0);
+        let outer = Rect::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(inner.penetration(&outer), Some(Vec2::new(0.0, -40.0)));
+        let touching = Rect::new(10.0, 0.0, 5.0, 5.0);
+        assert_eq!(a.penetration(&touching), None);
+    }
+
+    #[test]
+    fn offset_to_fit() {
+        let bounds = Rect::new(0.0, 0.0, 100.0, 50.0);
+        let inside = Rect::new(10.0, 10.0, 20.0, 20.0);
+        assert_eq!(inside.offset_to_fit(&bounds), Vec2::ZERO);
+        let flush = Rect::new(80.0, 30.0, 20.0, 20.0);
+        assert_eq!(flush.offset_to_fit(&bounds), Vec2::ZERO);
+
+        let past_right = Rect::new(90.0, 10.0, 20.0, 20.0);
+        assert_eq!(past_right.offset_to_fit(&bounds), Vec2::new(-10.0, 0.0));
+        let past_top_left = Rect::new(-5.0, -3.0, 20.0, 20.0);
+        assert_eq!(past_top_left.offset_to_fit(&bounds), Vec2::new(5.0, 3.0));
+        let past_bottom = Rect::new(10.0, 45.0, 20.0, 20.0);
+        assert_eq!(past_bottom.offset_to_fit(&bounds), Vec2::new(0.0, -15.0));
+
+        // Too tall to fit: top-aligned
+        let tall = Rect::new(10.0, 20.0, 20.0, 80.0);
+        assert_eq!(tall.offset_to_fit(&bounds), Vec2::new(0.0, -20.0));
+    }
+
+    #[test]
+    fn segment_entry() {
+        let rect = Rect::new(10.0, 10.0, 10.0, 10.0);
+        let hit = |start: Vec2, end: Vec2| rect.segment_entry(start, end);
+
+        assert_eq!(hit(Vec2::new(0.0, 15.0), Vec2::new(20.0, 15.0)), Some(0.5));
+        assert_eq!(hit(Vec2::new(30.0, 15.0), Vec2::new(10.0, 15.0)), Some(0.5));
+        assert_eq!(hit(Vec2::new(15.0, 0.0), Vec2::new(15.0, 40.0)), Some(0.25));
+        assert_eq!(hit(Vec2::new(0.0, 0.0), Vec2::new(20.0, 20.0)), Some(0.5));
+        // Starting inside
+        assert_eq!(hit(Vec2::new(15.0, 15.0), Vec2::new(50.0, 15.0)), Some(0.0));
+
+        // Too short, parallel outside, passing by, pointing away
+        assert_eq!(hit(Vec2::new(0.0, 15.0), Vec2::new(5.0, 15.0)), None);
+        assert_eq!(hit(Vec2::new(0.0, 5.0), Vec2::new(30.0, 5.0)), None);
+        assert_eq!(hit(Vec2::new(0.0, 0.0), Vec2::new(30.0, 5.0)), None);
+        assert_eq!(hit(Vec2::new(0.0, 15.0), Vec2::new(-10.0, 15.0)), None);
+        assert!(!rect.intersects_segment(Vec2::new(0.0, 15.0), Vec2::new(5.0, 15.0)));
+        assert!(rect.intersects_segment(Vec2::new(0.0, 15.0), Vec2::new(10.0, 15.0)));
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn vec2_round_trip() {
+        let v = Vec2::new(1.5, -2.25);
+        let json = serde_json::to_string(&v).unwrap();
+        assert_eq!(json, r#"{"x":1.5,"y":-2.25}"#);
+        assert_eq!(serde_json::from_str::<Vec2>(&json).unwrap(), v);
+        let bytes = bincode::serialize(&v).unwrap();
+        assert_eq!(bincode::deserialize::<Vec2>(&bytes).unwrap(), v);
+    }
+
+    #[test]
+    fn rect_round_trip() {
+        let rect = Rect::new(10.0, 20.0, 30.5, 40.0);
+        let json = serde_json::to_string(&rect).unwrap();
+        assert_eq!(json, r#"{"x":10.0,"y":20.0,"width":30.5,"height":40.0}"#);
+        assert_eq!(serde_json::from_str::<Rect>(&json).unwrap(), rect);
+        let bytes = bincode::serialize(&rect).unwrap();
+        assert_eq!(bincode::deserialize::<Rect>(&bytes).unwrap(), rect);
+    }
+}
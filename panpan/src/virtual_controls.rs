@@ -0,0 +1,424 @@
+// panpan/src/virtual_controls.rs
+// On-screen joystick, D-pad and buttons for touch devices
+
+use crate::graphics::{draw_circle, draw_rect};
+use crate::input::Actions;
+use crate::types::{Color, Rect, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoystickMode {
+    /// Always centered at the configured position
+    Fixed,
+    /// Re-centers wherever the finger lands inside the activation area
+    Floating,
+}
+
+/// Analog stick that writes its deflection to two axes
+#[derive(Debug, Clone)]
+pub struct VirtualJoystick {
+    pub center: Vec2,
+    pub radius: f32,
+    pub mode: JoystickMode,
+    /// Where a floating joystick may be grabbed
+    pub area: Rect,
+    /// Deflection below this fraction of the radius reads as zero
+    pub dead_zone: f32,
+    pub x_axis: String,
+    pub y_axis: String,
+    touch: Option<i32>,
+    origin: Vec2,
+    value: Vec2,
+}
+
+impl VirtualJoystick {
+    pub fn new(center: Vec2, radius: f32, x_axis: &str, y_axis: &str) -> Self {
+        Self {
+            center,
+            radius,
+            mode: JoystickMode::Fixed,
+            area: Rect::new(
+                center.x - radius,
+                center.y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            ),
+            dead_zone: 0.15,
+            x_axis: x_axis.to_string(),
+            y_axis: y_axis.to_string(),
+            touch: None,
+            origin: center,
+            value: Vec2::new(0.0, 0.0),
+        }
+    }
+
+    /// Make the joystick floating inside `area`
+    pub fn floating(mut self, area: Rect) -> Self {
+        self.mode = JoystickMode::Floating;
+        self.area = area;
+        self
+    }
+
+    /// Current deflection, each component in -1.0..=1.0
+    pub fn value(&self) -> Vec2 {
+        self.value
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.touch.is_some()
+    }
+
+    fn hit(&self, position: Vec2) -> bool {
+        match self.mode {
+            JoystickMode::Fixed => position.distance(self.center) <= self.radius,
+            JoystickMode::Floating => self.area.contains(position),
+        }
+    }
+
+    fn grab(&mut self, id: i32, position: Vec2, actions: &mut Actions) {
+        self.touch = Some(id);
+        self.origin = match self.mode {
+            JoystickMode::Fixed => self.center,
+            JoystickMode::Floating => position,
+        };
+        self.drag(position, actions);
+    }
+
+    fn drag(&mut self, position: Vec2, actions: &mut Actions) {
+        let offset = position - self.origin;
+        let length = offset.length();
+        let radius = self.radius.max(1.0);
+        let deflection = (length / radius).min(1.0);
+
+        self.value = if deflection <= self.dead_zone || length == 0.0 {
+            Vec2::ZERO
+        } else {
+            // Rescale so the output starts at zero right outside the dead zone
+            let scaled = (deflection - self.dead_zone) / (1.0 - self.dead_zone);
+            offset / length * scaled
+        };
+
+        actions.set_axis(&self.x_axis, self.value.x);
+        actions.set_axis(&self.y_axis, self.value.y);
+    }
+
+    fn release(&mut self, actions: &mut Actions) {
+        self.touch = None;
+        self.origin = self.center;
+        self.value = Vec2::new(0.0, 0.0);
+        actions.set_axis(&self.x_axis, 0.0);
+        actions.set_axis(&self.y_axis, 0.0);
+    }
+
+    fn draw(&self, style: &ControlStyle) {
+        let base = if self.is_active() {
+            self.origin
+        } else {
+            self.center
+        };
+        draw_circle(base.x, base.y, self.radius, style.base);
+        let knob = Vec2::new(
+            base.x + self.value.x * self.radius,
+            base.y + self.value.y * self.radius,
+        );
+        let color = if self.is_active() {
+            style.active
+        } else {
+            style.idle
+        };
+        draw_circle(knob.x, knob.y, self.radius * 0.4, color);
+    }
+}
+
+/// Round button bound to an action
+#[derive(Debug, Clone)]
+pub struct VirtualButton {
+    pub center: Vec2,
+    pub radius: f32,
+    pub action: String,
+    touch: Option<i32>,
+}
+
+impl VirtualButton {
+    pub fn new(center: Vec2, radius: f32, action: &str) -> Self {
+        Self {
+            center,
+            radius,
+            action: action.to_string(),
+            touch: None,
+        }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.touch.is_some()
+    }
+
+    fn draw(&self, style: &ControlStyle) {
+        let color = if self.is_pressed() {
+            style.active
+        } else {
+            style.idle
+        };
+        draw_circle(self.center.x, self.center.y, self.radius, color);
+    }
+}
+
+/// Four way directional pad. The finger may slide between directions.
+#[derive(Debug, Clone)]
+pub struct VirtualDPad {
+    pub center: Vec2,
+    /// Half the width of the whole pad
+    pub radius: f32,
+    /// Actions for up, down, left and right
+    pub actions: [String; 4],
+    touch: Option<i32>,
+    direction: Option<usize>,
+}
+
+impl VirtualDPad {
+    pub fn new(center: Vec2, radius: f32, up: &str, down: &str, left: &str, right: &str) -> Self {
+        Self {
+            center,
+            radius,
+            actions: [up, down, left, right].map(str::to_string),
+            touch: None,
+            direction: None,
+        }
+    }
+
+    fn hit(&self, position: Vec2) -> bool {
+        Rect::from_center(self.center, self.radius * 2.0, self.radius * 2.0).contains(position)
+    }
+
+    fn direction_at(&self, position: Vec2) -> Option<usize> {
+        let d = position - self.center;
+        if d.length() < self.radius * 0.2 {
+            return None;
+        }
+        Some(if d.x.abs() > d.y.abs() {
+            if d.x < 0.0 {
+                2
+            } else {
+                3
+            }
+        } else if d.y < 0.0 {
+            0
+        } else {
+            1
+        })
+    }
+
+    fn set_direction(&mut self, direction: Option<usize>, actions: &mut Actions) {
+        if direction == self.direction {
+            return;
+        }
+        if let Some(old) = self.direction {
+            actions.set_button(&self.actions[old], false);
+        }
+        if let Some(new) = direction {
+            actions.set_button(&self.actions[new], true);
+        }
+        self.direction = direction;
+    }
+
+    fn draw(&self, style: &ControlStyle) {
+        let arm = self.radius / 3.0;
+        let (cx, cy) = (self.center.x, self.center.y);
+        // Up, down, left, right arms
+        let arms = [
+            (cx - arm * 0.5, cy - self.radius),
+            (cx - arm * 0.5, cy + self.radius - arm),
+            (cx - self.radius, cy - arm * 0.5),
+            (cx + self.radius - arm, cy - arm * 0.5),
+        ];
+        draw_rect(
+            cx - self.radius,
+            cy - arm * 0.5,
+            self.radius * 2.0,
+            arm,
+            style.base,
+        );
+        draw_rect(
+            cx - arm * 0.5,
+            cy - self.radius,
+            arm,
+            self.radius * 2.0,
+            style.base,
+        );
+        for (i, (x, y)) in arms.iter().enumerate() {
+            let color = if self.direction == Some(i) {
+                style.active
+            } else {
+                style.idle
+            };
+            draw_rect(*x, *y, arm, arm, color);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControlStyle {
+    pub base: Color,
+    pub idle: Color,
+    pub active: Color,
+}
+
+impl Default for ControlStyle {
+    fn default() -> Self {
+        Self {
+            base: Color::new(1.0, 1.0, 1.0, 0.15),
+            idle: Color::new(1.0, 1.0, 1.0, 0.35),
+            active: Color::new(1.0, 1.0, 1.0, 0.6),
+        }
+    }
+}
+
+/// A set of on-screen controls sharing the touch stream.
+///
+/// Each control owns the touch id that grabbed it until that finger is
+/// lifted, so several fingers can drive different controls at once. The
+/// touch methods return `true` when a control consumed the touch, so the
+/// rest can go to gameplay.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualControls {
+    pub joysticks: Vec<VirtualJoystick>,
+    pub buttons: Vec<VirtualButton>,
+    pub dpads: Vec<VirtualDPad>,
+    pub style: ControlStyle,
+    pub visible: bool,
+}
+
+impl VirtualControls {
+    pub fn new() -> Self {
+        Self {
+            visible: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_joystick(mut self, joystick: VirtualJoystick) -> Self {
+        self.joysticks.push(joystick);
+        self
+    }
+
+    pub fn with_button(mut self, button: VirtualButton) -> Self {
+        self.buttons.push(button);
+        self
+    }
+
+    pub fn with_dpad(mut self, dpad: VirtualDPad) -> Self {
+        self.dpads.push(dpad);
+        self
+    }
+
+    pub fn touch_down(&mut self, actions: &mut Actions, id: i32, x: f32, y: f32) -> bool {
+        let position = Vec2::new(x, y);
+
+        // Buttons first so a button placed over a floating area still works
+        if let Some(button) = self
+            .buttons
+            .iter_mut()
+            .find(|b| b.touch.is_none() && position.distance(b.center) <= b.radius)
+        {
+            button.touch = Some(id);
+            actions.set_button(&button.action, true);
+            return true;
+        }
+
+        if let Some(dpad) = self
+            .dpads
+            .iter_mut()
+            .find(|d| d.touch.is_none() && d.hit(position))
+        {
+            dpad.touch = Some(id);
+            let direction = dpad.direction_at(position);
+            dpad.set_direction(direction, actions);
+            return true;
+        }
+
+        if let Some(joystick) = self
+            .joysticks
+            .iter_mut()
+            .find(|j| j.touch.is_none() && j.hit(position))
+        {
+            joystick.grab(id, position, actions);
+            return true;
+        }
+
+        false
+    }
+
+    pub fn touch_move(&mut self, actions: &mut Actions, id: i32, x: f32, y: f32) -> bool {
+        let position = Vec2::new(x, y);
+
+        if self.buttons.iter().any(|b| b.touch == Some(id)) {
+            return true;
+        }
+
+        if let Some(dpad) = self.dpads.iter_mut().find(|d| d.touch == Some(id)) {
+            let direction = dpad.direction_at(position);
+            dpad.set_direction(direction, actions);
+            return true;
+        }
+
+        if let Some(joystick) = self.joysticks.iter_mut().find(|j| j.touch == Some(id)) {
+            joystick.drag(position, actions);
+            return true;
+        }
+
+        false
+    }
+
+    pub fn touch_up(&mut self, actions: &mut Actions, id: i32) -> bool {
+        if let Some(button) = self.buttons.iter_mut().find(|b| b.touch == Some(id)) {
+            button.touch = None;
+            actions.set_button(&button.action, false);
+            return true;
+        }
+
+        if let Some(dpad) = self.dpads.iter_mut().find(|d| d.touch == Some(id)) {
+            dpad.touch = None;
+            dpad.set_direction(None, actions);
+            return true;
+        }
+
+        if let Some(joystick) = self.joysticks.iter_mut().find(|j| j.touch == Some(id)) {
+            joystick.release(actions);
+            return true;
+        }
+
+        false
+    }
+
+    /// Release every control, e.g. when the app loses focus
+    pub fn release_all(&mut self, actions: &mut Actions) {
+        for button in &mut self.buttons {
+            if button.touch.take().is_some() {
+                actions.set_button(&button.action, false);
+            }
+        }
+        for dpad in &mut self.dpads {
+            dpad.touch = None;
+            dpad.set_direction(None, actions);
+        }
+        for joystick in &mut self.joysticks {
+            if joystick.is_active() {
+                joystick.release(actions);
+            }
+        }
+    }
+
+    pub fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+        for dpad in &self.dpads {
+            dpad.draw(&self.style);
+        }
+        for joystick in &self.joysticks {
+            joystick.draw(&self.style);
+        }
+        for button in &self.buttons {
+            button.draw(&self.style);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// panpan/src/window.rs
+// Runtime control over the window and frame pacing
+//
+// The `Context` methods are the API. The free functions here are kept for
+// code without a `Context` at hand, and act on the context that is current
+// on this thread, i.e. from inside `Game::new`, `update`, `render` and the
+// input and lifecycle callbacks. Outside of those they do nothing.
+
+use crate::platform::{self, PlatformRequest};
+
+/// Units for positions passed to drawing calls and reported by input events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoordinateSpace {
+    /// Device independent points: a 100 unit button has the same physical
+    /// size on a 1x and a 2x display, and is drawn at full resolution
+    #[default]
+    Logical,
+    /// Raw framebuffer pixels
+    Physical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    /// Covers the screen without changing the display mode
+    BorderlessFullscreen,
+    /// Switches the display to its best video mode. Falls back to
+    /// borderless where that is not supported.
+    ExclusiveFullscreen,
+}
+
+impl WindowMode {
+    /// Borderless fullscreen or windowed
+    pub(crate) fn fullscreen(fullscreen: bool) -> Self {
+        if fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        }
+    }
+}
+
+/// `Context::scale_factor` of the current context, 1.0 without one
+pub fn scale_factor() -> f32 {
+    platform::with_current(|state| state.scale_factor()).unwrap_or(1.0)
+}
+
+/// `Context::coordinate_space` of the current context
+pub fn coordinate_space() -> CoordinateSpace {
+    platform::with_current(|state| state.coordinate_space()).unwrap_or_default()
+}
+
+/// Physical pixels per game coordinate unit: the scale factor for
+/// `CoordinateSpace::Logical`, 1.0 for `CoordinateSpace::Physical`
+pub fn pixel_ratio() -> f32 {
+    platform::with_current(|state| state.pixel_ratio()).unwrap_or(1.0)
+}
+
+/// `Context::set_window_mode` on the current context
+pub fn set_window_mode(mode: WindowMode) {
+    platform::push(PlatformRequest::SetWindowMode(mode));
+}
+
+/// `Context::set_fullscreen` on the current context
+pub fn set_fullscreen(fullscreen: bool) {
+    set_window_mode(WindowMode::fullscreen(fullscreen));
+}
+
+/// `Context::set_vsync` on the current context
+pub fn set_vsync(vsync: bool) {
+    platform::push(PlatformRequest::SetVsync(vsync));
+}
+
+/// `Context::set_target_fps` on the current context
+pub fn set_target_fps(fps: Option<u32>) {
+    platform::push(PlatformRequest::SetTargetFps(fps));
+}
+
+/// `Context::set_window_size` on the current context
+pub fn set_window_size(width: u32, height: u32) {
+    platform::push(PlatformRequest::SetWindowSize(width, height));
+}
@@ -3,7 +3,14 @@ package com.lucidum.panpan
 import android.app.Activity
 import android.opengl.GLSurfaceView
 import android.os.Bundle
+import android.text.InputType
+import android.view.KeyEvent
 import android.view.MotionEvent
+import android.view.View
+import android.view.inputmethod.BaseInputConnection
+import android.view.inputmethod.EditorInfo
+import android.view.inputmethod.InputConnection
+import android.view.inputmethod.InputMethodManager
 
 class MainActivity : Activity() {
 
@@ -14,14 +21,25 @@ class MainActivity : Activity() {
     }
 
     external fun nativeInit()
-    external fun nativeResize(width: Int, height: Int)
+    external fun nativeResize(width: Int, height: Int, density: Float)
     external fun nativeRender()
     external fun nativeTouchDown(id: Int, x: Float, y: Float)
     external fun nativeTouchMove(id: Int, x: Float, y: Float)
     external fun nativeTouchUp(id: Int)
     external fun nativeUpdateTime(deltaTime: Float)
+    external fun nativeKeyDown(keyCode: Int)
+    external fun nativeKeyUp(keyCode: Int)
+    external fun nativeTextInput(text: String)
+    external fun nativeTextComposition(text: String, cursor: Int)
+    external fun nativePause()
+    external fun nativeResume()
+    external fun nativeFocusChanged(focused: Boolean)
+    external fun nativeLowMemory()
+    external fun nativeQuit()
 
     private lateinit var glView: GLSurfaceView
+    private var textInputActive = false
+    private var frameLimitNanos = 0L
     private var lastFrameTime = System.nanoTime()
 
     override fun onCreate(savedInstanceState: Bundle?) {
@@ -31,9 +49,20 @@ class MainActivity : Activity() {
             override fun onMeasure(widthMeasureSpec: Int, heightMeasureSpec: Int) {
                 super.onMeasure(widthMeasureSpec, heightMeasureSpec)
             }
+
+            override fun onCheckIsTextEditor(): Boolean = textInputActive
+
+            override fun onCreateInputConnection(outAttrs: EditorInfo): InputConnection {
+                outAttrs.inputType = InputType.TYPE_CLASS_TEXT
+                outAttrs.imeOptions = EditorInfo.IME_ACTION_DONE or EditorInfo.IME_FLAG_NO_FULLSCREEN
+                return TextConnection(this)
+            }
         }
+        glView.isFocusableInTouchMode = true
 
         glView.setEGLContextClientVersion(3)
+        // Not guaranteed by every driver; panpan restores its resources when it isn't honored
+        glView.preserveEGLContextOnPause = true
 
         glView.setRenderer(object : GLSurfaceView.Renderer {
             override fun onSurfaceCreated(unused: javax.microedition.khronos.opengles.GL10?, config: javax.microedition.khronos.egl.EGLConfig?) {
@@ -42,7 +71,8 @@ class MainActivity : Activity() {
             }
 
             override fun onSurfaceChanged(unused: javax.microedition.khronos.opengles.GL10?, width: Int, height: Int) {
-                nativeResize(width, height)
+                // Touch positions arrive in pixels, density turns them into dp
+                nativeResize(width, height, resources.displayMetrics.density)
             }
 
             override fun onDrawFrame(unused: javax.microedition.khronos.opengles.GL10?) {
@@ -52,6 +82,14 @@ class MainActivity : Activity() {
                 
                 nativeUpdateTime(deltaTime)
                 nativeRender()
+
+                // Frame rate cap from the game's Conf
+                if (frameLimitNanos > 0) {
+                    val remaining = frameLimitNanos - (System.nanoTime() - currentTime)
+                    if (remaining > 0) {
+                        Thread.sleep(remaining / 1_000_000, (remaining % 1_000_000).toInt())
+                    }
+                }
             }
         })
 
@@ -89,13 +127,146 @@ class MainActivity : Activity() {
         return true
     }
 
+    // Called from Rust on the GL thread with the settings from the game's Conf
+    @Suppress("unused")
+    fun applyConf(orientation: Int, fullscreen: Boolean, targetFps: Int) {
+        setTargetFps(targetFps)
+        setFullscreen(fullscreen)
+        runOnUiThread { requestedOrientation = orientation }
+    }
+
+    // Called from Rust on the GL thread, 0 removes the cap
+    fun setTargetFps(targetFps: Int) {
+        frameLimitNanos = if (targetFps > 0) 1_000_000_000L / targetFps else 0L
+    }
+
+    // Called from Rust on the GL thread
+    fun setFullscreen(fullscreen: Boolean) {
+        runOnUiThread { setImmersive(fullscreen) }
+    }
+
+    private fun setImmersive(fullscreen: Boolean) {
+        @Suppress("DEPRECATION")
+        glView.systemUiVisibility = if (fullscreen) {
+            View.SYSTEM_UI_FLAG_FULLSCREEN or
+                View.SYSTEM_UI_FLAG_HIDE_NAVIGATION or
+                View.SYSTEM_UI_FLAG_IMMERSIVE_STICKY or
+                View.SYSTEM_UI_FLAG_LAYOUT_STABLE or
+                View.SYSTEM_UI_FLAG_LAYOUT_FULLSCREEN or
+                View.SYSTEM_UI_FLAG_LAYOUT_HIDE_NAVIGATION
+        } else {
+            View.SYSTEM_UI_FLAG_VISIBLE
+        }
+    }
+
+    // Called from Rust on the GL thread when the game starts text input
+    @Suppress("unused")
+    fun showSoftKeyboard() {
+        runOnUiThread {
+            textInputActive = true
+            glView.requestFocus()
+            val imm = getSystemService(INPUT_METHOD_SERVICE) as InputMethodManager
+            imm.restartInput(glView)
+            imm.showSoftInput(glView, 0)
+        }
+    }
+
+    // Called from Rust on the GL thread when the game stops text input
+    @Suppress("unused")
+    fun hideSoftKeyboard() {
+        runOnUiThread {
+            textInputActive = false
+            val imm = getSystemService(INPUT_METHOD_SERVICE) as InputMethodManager
+            imm.hideSoftInputFromWindow(glView.windowToken, 0)
+        }
+    }
+
+    override fun onKeyDown(keyCode: Int, event: KeyEvent): Boolean {
+        glView.queueEvent { nativeKeyDown(keyCode) }
+        return super.onKeyDown(keyCode, event)
+    }
+
+    override fun onKeyUp(keyCode: Int, event: KeyEvent): Boolean {
+        glView.queueEvent { nativeKeyUp(keyCode) }
+        return super.onKeyUp(keyCode, event)
+    }
+
+    // Routes soft keyboard text to the game on the GL thread
+    private inner class TextConnection(view: GLSurfaceView) : BaseInputConnection(view, false) {
+        override fun commitText(text: CharSequence, newCursorPosition: Int): Boolean {
+            val committed = text.toString()
+            glView.queueEvent {
+                nativeTextComposition("", -1)
+                nativeTextInput(committed)
+            }
+            return true
+        }
+
+        override fun setComposingText(text: CharSequence, newCursorPosition: Int): Boolean {
+            val composing = text.toString()
+            glView.queueEvent { nativeTextComposition(composing, composing.length) }
+            return true
+        }
+
+        override fun finishComposingText(): Boolean {
+            glView.queueEvent { nativeTextComposition("", -1) }
+            return true
+        }
+
+        override fun deleteSurroundingText(beforeLength: Int, afterLength: Int): Boolean {
+            // The game owns the text, so forward deletions as backspace presses
+            glView.queueEvent {
+                repeat(beforeLength) {
+                    nativeKeyDown(KeyEvent.KEYCODE_DEL)
+                    nativeKeyUp(KeyEvent.KEYCODE_DEL)
+                }
+            }
+            return true
+        }
+
+        override fun sendKeyEvent(event: KeyEvent): Boolean {
+            val keyCode = event.keyCode
+            when (event.action) {
+                KeyEvent.ACTION_DOWN -> glView.queueEvent { nativeKeyDown(keyCode) }
+                KeyEvent.ACTION_UP -> glView.queueEvent { nativeKeyUp(keyCode) }
+            }
+            return true
+        }
+    }
+
     override fun onPause() {
         super.onPause()
+        nativePause()
         glView.onPause()
     }
 
     override fun onResume() {
         super.onResume()
         glView.onResume()
+        nativeResume()
+    }
+
+    override fun onWindowFocusChanged(hasFocus: Boolean) {
+        super.onWindowFocusChanged(hasFocus)
+        nativeFocusChanged(hasFocus)
+    }
+
+    override fun onTrimMemory(level: Int) {
+        super.onTrimMemory(level)
+        if (level >= TRIM_MEMORY_RUNNING_LOW) {
+            nativeLowMemory()
+        }
+    }
+
+    override fun onLowMemory() {
+        super.onLowMemory()
+        nativeLowMemory()
+    }
+
+    override fun onDestroy() {
+        super.onDestroy()
+        if (isFinishing) {
+            nativeQuit()
+        }
     }
 }
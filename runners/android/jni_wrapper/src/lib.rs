@@ -1,13 +1,20 @@
 // Android JNI wrapper for demo_game
-use jni::objects::JClass;
-use jni::sys::{jfloat, jint};
+use jni::objects::{JClass, JObject, JString};
+use jni::sys::{jboolean, jfloat, jint, JNI_TRUE};
 use jni::JNIEnv;
 use std::sync::Mutex;
 
+// When more than one is needed they are locked in this order: GAME, LOOP,
+// CTX
 static GAME: Mutex<Option<demo_game::DemoGame>> = Mutex::new(None);
+static LOOP: Mutex<Option<panpan::GameLoop>> = Mutex::new(None);
+static CTX: Mutex<Option<panpan::Context>> = Mutex::new(None);
 
 #[no_mangle]
-pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeInit(_env: JNIEnv, _class: JClass) {
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeInit(
+    mut env: JNIEnv,
+    activity: JObject,
+) {
     println!("[JNI] nativeInit called");
 
     // Initialize OpenGL context
@@ -22,14 +29,29 @@ pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeInit(_env: JNIEnv,
         })
     };
 
-    // Initialize panpan graphics
-    panpan::__internal_init_graphics(gl);
+    // Initialize panpan graphics. On a second call the old EGL context is
+    // gone and panpan rebuilds its GPU resources in the new one.
+    let mut game = GAME.lock().unwrap();
+    let mut game_loop = LOOP.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    let ctx = ctx.get_or_insert_with(panpan::Context::new);
+    panpan::__internal_init_graphics(ctx, gl);
     println!("[JNI] PanPan graphics initialized");
 
+    // The surface is recreated after the app returns from the background,
+    // the game itself survives
+    if game.is_some() {
+        println!("[JNI] Context restored, keeping game state");
+        return;
+    }
+
     // Create game instance
     use panpan::Game;
-    let game = demo_game::DemoGame::new();
-    *GAME.lock().unwrap() = Some(game);
+    let conf = demo_game::DemoGame::conf();
+    panpan::__internal_set_coordinate_space(ctx, conf.coordinate_space);
+    apply_conf(&mut env, &activity, &conf);
+    *game = Some(ctx.scope(demo_game::DemoGame::new));
+    *game_loop = Some(panpan::GameLoop::new(demo_game::DemoGame::timestep()));
     println!("[JNI] Game created");
 }
 
@@ -39,9 +61,13 @@ pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeResize(
     _class: JClass,
     width: jint,
     height: jint,
+    density: jfloat,
 ) {
-    println!("[JNI] nativeResize: {}x{}", width, height);
-    panpan::__internal_resize(width, height);
+    println!("[JNI] nativeResize: {}x{} @{}x", width, height, density);
+    if let Some(ctx) = CTX.lock().unwrap().as_mut() {
+        panpan::__internal_set_scale_factor(ctx, density);
+        panpan::__internal_resize(ctx, width, height);
+    }
 }
 
 #[no_mangle]
@@ -50,17 +76,94 @@ pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeUpdateTime(
     _class: JClass,
     dt: jfloat,
 ) {
-    use panpan::Game;
-    if let Some(game) = GAME.lock().unwrap().as_mut() {
-        game.update(dt);
+    let mut game = GAME.lock().unwrap();
+    let mut game_loop = LOOP.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    if let (Some(game), Some(game_loop), Some(ctx)) =
+        (game.as_mut(), game_loop.as_mut(), ctx.as_mut())
+    {
+        game_loop.update(game, ctx, dt);
     }
 }
 
 #[no_mangle]
-pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeRender(_env: JNIEnv, _class: JClass) {
-    use panpan::Game;
-    if let Some(game) = GAME.lock().unwrap().as_ref() {
-        game.render();
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeRender(
+    mut env: JNIEnv,
+    activity: JObject,
+) {
+    let game = GAME.lock().unwrap();
+    let game_loop = LOOP.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    if let (Some(game), Some(game_loop), Some(ctx)) =
+        (game.as_ref(), game_loop.as_ref(), ctx.as_mut())
+    {
+        game_loop.render(game, ctx);
+    }
+    if let Some(ctx) = ctx.as_mut() {
+        apply_platform_requests(&mut env, &activity, ctx);
+    }
+}
+
+/// Apply the settings of `Game::conf` that make sense on Android
+fn apply_conf(env: &mut JNIEnv, activity: &JObject, conf: &panpan::Conf) {
+    use jni::objects::JValue;
+    use panpan::Orientation;
+
+    // ActivityInfo.SCREEN_ORIENTATION_* constants
+    let orientation = match conf.orientation {
+        Orientation::Any => -1,
+        Orientation::Landscape => 6,
+        Orientation::Portrait => 7,
+    };
+    let target_fps = conf.target_fps.unwrap_or(0) as jint;
+
+    let result = env.call_method(
+        activity,
+        "applyConf",
+        "(IZI)V",
+        &[
+            JValue::Int(orientation),
+            JValue::Bool(conf.fullscreen as jboolean),
+            JValue::Int(target_fps),
+        ],
+    );
+    if let Err(e) = result {
+        println!("[JNI] applyConf failed: {:?}", e);
+    }
+}
+
+/// Carry out requests the game made this frame through the activity
+fn apply_platform_requests(env: &mut JNIEnv, activity: &JObject, ctx: &mut panpan::Context) {
+    use jni::objects::JValue;
+    use panpan::platform::PlatformRequest;
+    use panpan::WindowMode;
+
+    for request in panpan::__internal_take_platform_requests(ctx) {
+        let (method, signature, args) = match request {
+            PlatformRequest::StartTextInput => ("showSoftKeyboard", "()V", vec![]),
+            PlatformRequest::StopTextInput => ("hideSoftKeyboard", "()V", vec![]),
+            PlatformRequest::SetWindowMode(mode) => {
+                let fullscreen = mode != WindowMode::Windowed;
+                (
+                    "setFullscreen",
+                    "(Z)V",
+                    vec![JValue::Bool(fullscreen as jboolean)],
+                )
+            }
+            PlatformRequest::SetTargetFps(fps) => (
+                "setTargetFps",
+                "(I)V",
+                vec![JValue::Int(fps.unwrap_or(0) as jint)],
+            ),
+            // The soft keyboard positions itself, the surface always fills
+            // the screen and Android always presents with vsync
+            PlatformRequest::SetTextInputArea(_)
+            | PlatformRequest::SetWindowSize(..)
+            | PlatformRequest::SetVsync(_) => continue,
+        };
+        if let Err(e) = env.call_method(activity, method, signature, &args) {
+            println!("[JNI] {} failed: {:?}", method, e);
+        }
     }
 }
 
@@ -72,10 +175,7 @@ pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeTouchDown(
     x: jfloat,
     y: jfloat,
 ) {
-    use panpan::Game;
-    if let Some(game) = GAME.lock().unwrap().as_mut() {
-        game.on_touch_down(id, x, y);
-    }
+    dispatch_touch(id, x, y, panpan::TouchPhase::Started);
 }
 
 #[no_mangle]
@@ -86,10 +186,7 @@ pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeTouchMove(
     x: jfloat,
     y: jfloat,
 ) {
-    use panpan::Game;
-    if let Some(game) = GAME.lock().unwrap().as_mut() {
-        game.on_touch_move(id, x, y);
-    }
+    dispatch_touch(id, x, y, panpan::TouchPhase::Moved);
 }
 
 #[no_mangle]
@@ -97,9 +194,187 @@ pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeTouchUp(
     _env: JNIEnv,
     _class: JClass,
     id: jint,
+) {
+    // Android doesn't pass where the finger lifted, use its last position
+    let last = CTX
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|ctx| ctx.input().touch(id).copied());
+    let (x, y) = last.map_or((0.0, 0.0), |t| (t.x, t.y));
+    dispatch(panpan::InputEvent::Touch(panpan::Touch {
+        id,
+        x,
+        y,
+        phase: panpan::TouchPhase::Ended,
+    }));
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeKeyDown(
+    _env: JNIEnv,
+    _class: JClass,
+    key_code: jint,
+) {
+    dispatch(panpan::InputEvent::KeyDown(map_key(key_code)));
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeKeyUp(
+    _env: JNIEnv,
+    _class: JClass,
+    key_code: jint,
+) {
+    dispatch(panpan::InputEvent::KeyUp(map_key(key_code)));
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeTextInput(
+    mut env: JNIEnv,
+    _class: JClass,
+    text: JString,
+) {
+    let Ok(text) = env.get_string(&text) else {
+        return;
+    };
+    dispatch(panpan::InputEvent::Text(text.into()));
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeTextComposition(
+    mut env: JNIEnv,
+    _class: JClass,
+    text: JString,
+    cursor: jint,
+) {
+    let Ok(text) = env.get_string(&text) else {
+        return;
+    };
+    let text: String = text.into();
+
+    // Java reports the cursor in UTF-16 units, panpan uses byte offsets
+    let cursor = (cursor >= 0).then(|| {
+        let mut units = 0;
+        let byte = text
+            .char_indices()
+            .find(|(_, c)| {
+                let reached = units >= cursor as usize;
+                units += c.len_utf16();
+                reached
+            })
+            .map_or(text.len(), |(i, _)| i);
+        (byte, byte)
+    });
+
+    dispatch(panpan::InputEvent::Composition { text, cursor });
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativePause(_env: JNIEnv, _class: JClass) {
+    println!("[JNI] nativePause");
+    let mut game = GAME.lock().unwrap();
+    let mut game_loop = LOOP.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    if let (Some(game), Some(game_loop), Some(ctx)) =
+        (game.as_mut(), game_loop.as_mut(), ctx.as_mut())
+    {
+        ctx.scope(|_| game_loop.pause(game));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeResume(_env: JNIEnv, _class: JClass) {
+    println!("[JNI] nativeResume");
+    let mut game = GAME.lock().unwrap();
+    let mut game_loop = LOOP.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    if let (Some(game), Some(game_loop), Some(ctx)) =
+        (game.as_mut(), game_loop.as_mut(), ctx.as_mut())
+    {
+        ctx.scope(|_| game_loop.resume(game));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeFocusChanged(
+    _env: JNIEnv,
+    _class: JClass,
+    focused: jboolean,
 ) {
     use panpan::Game;
-    if let Some(game) = GAME.lock().unwrap().as_mut() {
-        game.on_touch_up(id);
+    with_game(|game| game.on_focus_changed(focused == JNI_TRUE));
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeLowMemory(
+    _env: JNIEnv,
+    _class: JClass,
+) {
+    println!("[JNI] nativeLowMemory");
+    use panpan::Game;
+    with_game(|game| game.on_low_memory());
+}
+
+#[no_mangle]
+pub extern "C" fn Java_com_lucidum_panpan_MainActivity_nativeQuit(_env: JNIEnv, _class: JClass) {
+    println!("[JNI] nativeQuit");
+    use panpan::Game;
+    // Dropping the game here means a relaunch in the same process starts fresh
+    with_game(|game| game.on_quit());
+    *GAME.lock().unwrap() = None;
+    *LOOP.lock().unwrap() = None;
+    *CTX.lock().unwrap() = None;
+}
+
+/// Touch positions arrive in physical pixels
+fn dispatch_touch(id: jint, x: jfloat, y: jfloat, phase: panpan::TouchPhase) {
+    let ratio = CTX
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(1.0, |ctx| ctx.pixel_ratio());
+    dispatch(panpan::InputEvent::Touch(panpan::Touch {
+        id,
+        x: x / ratio,
+        y: y / ratio,
+        phase,
+    }));
+}
+
+fn dispatch(event: panpan::InputEvent) {
+    let mut game = GAME.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    if let (Some(game), Some(ctx)) = (game.as_mut(), ctx.as_mut()) {
+        panpan::dispatch_event(game, ctx, &event);
+    }
+}
+
+/// Run a lifecycle callback with the context current, so requests the game
+/// makes from it reach the activity
+fn with_game(f: impl FnOnce(&mut demo_game::DemoGame)) {
+    let mut game = GAME.lock().unwrap();
+    let mut ctx = CTX.lock().unwrap();
+    if let (Some(game), Some(ctx)) = (game.as_mut(), ctx.as_mut()) {
+        ctx.scope(|_| f(game));
+    }
+}
+
+/// Map android.view.KeyEvent key codes to panpan keys
+fn map_key(key_code: jint) -> panpan::Key {
+    use panpan::Key;
+    match key_code {
+        51 => Key::W,
+        29 => Key::A,
+        47 => Key::S,
+        32 => Key::D,
+        19 => Key::Up,
+        20 => Key::Down,
+        21 => Key::Left,
+        22 => Key::Right,
+        62 => Key::Space,
+        66 => Key::Enter,
+        67 => Key::Backspace,
+        4 | 111 => Key::Escape,
+        _ => Key::Unknown,
     }
 }
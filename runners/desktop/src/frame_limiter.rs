@@ -0,0 +1,68 @@
+// runners/desktop/src/frame_limiter.rs
+// Frame rate cap that sleeps most of the wait and spins the last bit
+//
+// OS timers can wake up several milliseconds late, which is a large part of
+// a 16ms frame. The event loop sleeps until shortly before the deadline and
+// the remainder is spent yielding, which lands within microseconds.
+
+use std::time::{Duration, Instant};
+
+/// How long before the deadline to stop sleeping and start spinning
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+#[derive(Debug)]
+pub(crate) struct FrameLimiter {
+    interval: Option<Duration>,
+    next_frame: Instant,
+}
+
+/// What the runner should do before the next frame
+pub(crate) enum Wait {
+    /// Draw now
+    Ready,
+    /// Sleep in the event loop until this instant, then ask again
+    Until(Instant),
+}
+
+impl FrameLimiter {
+    pub fn new(target_fps: Option<u32>) -> Self {
+        let mut limiter = Self {
+            interval: None,
+            next_frame: Instant::now(),
+        };
+        limiter.set_target_fps(target_fps);
+        limiter
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: Option<u32>) {
+        self.interval = target_fps
+            .filter(|fps| *fps > 0)
+            .map(|fps| Duration::from_secs_f64(1.0 / fps as f64));
+        self.next_frame = Instant::now();
+    }
+
+    /// Check whether the next frame is due, spinning through the last
+    /// stretch so it starts on time
+    pub fn wait(&mut self) -> Wait {
+        let Some(interval) = self.interval else {
+            return Wait::Ready;
+        };
+
+        let now = Instant::now();
+        if now + SPIN_MARGIN < self.next_frame {
+            return Wait::Until(self.next_frame - SPIN_MARGIN);
+        }
+        while Instant::now() < self.next_frame {
+            std::thread::yield_now();
+        }
+
+        // Schedule from the deadline, not from now, so the average rate is
+        // exact. After a long stall start over instead of bursting frames.
+        self.next_frame += interval;
+        let now = Instant::now();
+        if self.next_frame < now {
+            self.next_frame = now + interval;
+        }
+        Wait::Ready
+    }
+}
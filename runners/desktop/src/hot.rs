@@ -0,0 +1,215 @@
+// runners/desktop/src/hot.rs
+// Hot reload: runs a game from a dylib and swaps in rebuilt versions
+//
+// `HotGame` implements `Game` by forwarding every call to the library's
+// `PANPAN_HOT_API` table (see `panpan::hot`), so the rest of the runner does
+// not know the difference. When the library file changes the game is
+// serialized with the old code and restored with the new one.
+//
+// Libraries are copied before loading, so the build can overwrite the
+// original, and are never unloaded: Rust libraries register thread-local
+// destructors that would crash after an unload.
+
+use panpan::hot::{HotApi, HotEvent};
+use panpan::platform;
+use panpan::{Conf, Context, Game, InputEvent, TimestepConfig};
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// How often the library file is checked, in seconds of game time
+const CHECK_INTERVAL: f32 = 0.5;
+
+/// Library loaded by `load` before the runner starts, picked up by `new`
+static INITIAL: Mutex<Option<Library>> = Mutex::new(None);
+
+#[derive(Clone)]
+struct Library {
+    api: &'static HotApi,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// Load the game library at `path` for the first time
+pub(crate) fn load(path: &Path) -> Result<(), String> {
+    let library = open(path)?;
+    *INITIAL.lock().unwrap() = Some(library);
+    Ok(())
+}
+
+fn initial() -> Library {
+    INITIAL
+        .lock()
+        .unwrap()
+        .clone()
+        .expect("hot::load must run before the game starts")
+}
+
+fn open(path: &Path) -> Result<Library, String> {
+    static LOADS: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+    let modified = modified(path);
+    let n = LOADS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let copy = std::env::temp_dir().join(format!(
+        "panpan-hot-{}-{}{}",
+        std::process::id(),
+        n,
+        std::env::consts::DLL_SUFFIX
+    ));
+    std::fs::copy(path, &copy).map_err(|e| format!("copy {}: {}", path.display(), e))?;
+
+    let api = unsafe {
+        let library = libloading::Library::new(&copy).map_err(|e| e.to_string())?;
+        let api = library
+            .get::<*const HotApi>(b"PANPAN_HOT_API\0")
+            .map(|symbol| &**symbol)
+            .map_err(|e| format!("{} (missing panpan::hot_reload!?)", e))?;
+        std::mem::forget(library);
+        api
+    };
+    if !api.is_compatible() {
+        return Err(format!(
+            "built against panpan {}, runner has {}",
+            api.version,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    Ok(Library {
+        api,
+        path: path.to_path_buf(),
+        modified,
+    })
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+pub(crate) struct HotGame {
+    library: Library,
+    game: *mut c_void,
+    since_check: f32,
+    /// Modification time seen at the last check. The library is reloaded
+    /// once it stays the same for a whole interval, so a file that is still
+    /// being written is not picked up.
+    pending: Option<SystemTime>,
+}
+
+impl HotGame {
+    fn reload_if_changed(&mut self, ctx: &mut Context) {
+        let modified = modified(&self.library.path);
+        if modified == self.library.modified {
+            self.pending = None;
+            return;
+        }
+        if modified != std::mem::replace(&mut self.pending, modified) {
+            return;
+        }
+
+        let library = match open(&self.library.path) {
+            Ok(library) => library,
+            Err(e) => {
+                println!("Hot reload failed: {}", e);
+                // Try again when the file changes next time
+                self.library.modified = modified;
+                return;
+            }
+        };
+
+        let old = self.library.api;
+        let state = unsafe { (old.serialize)(self.game) };
+        unsafe { (old.drop)(self.game) };
+        self.library = library;
+        self.pending = None;
+        self.game = ctx.__internal_unscoped(|ctx| (self.library.api.restore)(ctx, &state));
+        println!("Hot reloaded {}", self.library.path.display());
+    }
+
+    /// Callbacks without a `Context` still run inside the host's current
+    /// context, whose platform state the library borrows for the call
+    fn event(&mut self, event: HotEvent) -> bool {
+        platform::__internal_unscoped(|platform| unsafe {
+            (self.library.api.event)(self.game, platform, event)
+        })
+    }
+}
+
+impl Game for HotGame {
+    fn new(ctx: &mut Context) -> Self {
+        let library = initial();
+        let mut game = Self {
+            game: std::ptr::null_mut(),
+            library,
+            since_check: 0.0,
+            pending: None,
+        };
+        game.game = ctx.__internal_unscoped(|ctx| (game.library.api.new)(ctx));
+        game
+    }
+
+    fn conf() -> Conf {
+        (initial().api.conf)()
+    }
+
+    fn timestep() -> TimestepConfig {
+        (initial().api.timestep)()
+    }
+
+    fn update(&mut self, ctx: &mut Context, dt: f32) {
+        self.since_check += dt;
+        if self.since_check >= CHECK_INTERVAL {
+            self.since_check = 0.0;
+            self.reload_if_changed(ctx);
+        }
+
+        let update = self.library.api.update;
+        ctx.__internal_unscoped(|ctx| unsafe { update(self.game, ctx, dt) });
+    }
+
+    fn render(&self, ctx: &mut Context) {
+        self.render_interpolated(ctx, 1.0);
+    }
+
+    fn render_interpolated(&self, ctx: &mut Context, alpha: f32) {
+        let render = self.library.api.render;
+        ctx.__internal_unscoped(|ctx| unsafe { render(self.game, ctx, alpha) });
+    }
+
+    fn on_input(&mut self, event: &InputEvent) {
+        platform::__internal_unscoped(|platform| unsafe {
+            (self.library.api.input)(self.game, platform, event)
+        });
+    }
+
+    fn on_pause(&mut self) {
+        self.event(HotEvent::Pause);
+    }
+
+    fn on_resume(&mut self) {
+        self.event(HotEvent::Resume);
+    }
+
+    fn on_focus_changed(&mut self, focused: bool) {
+        self.event(HotEvent::FocusChanged(focused));
+    }
+
+    fn on_low_memory(&mut self) {
+        self.event(HotEvent::LowMemory);
+    }
+
+    fn on_close_requested(&mut self) -> bool {
+        self.event(HotEvent::CloseRequested)
+    }
+
+    fn on_quit(&mut self) {
+        self.event(HotEvent::Quit);
+    }
+}
+
+impl Drop for HotGame {
+    fn drop(&mut self) {
+        unsafe { (self.library.api.drop)(self.game) };
+    }
+}
@@ -6,39 +6,278 @@ use glutin::config::ConfigTemplateBuilder;
 use glutin::context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Version};
 use glutin::display::{Display, GetGlDisplay};
 use glutin::prelude::*;
-use glutin::surface::{Surface, SurfaceAttributesBuilder, WindowSurface};
+use glutin::surface::{Surface, SurfaceAttributesBuilder, SwapInterval, WindowSurface};
 use glutin_winit::DisplayBuilder;
 use raw_window_handle::HasWindowHandle;
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 use std::time::Instant;
 use winit::application::ApplicationHandler;
-use winit::event::{StartCause, WindowEvent};
+use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize, Size};
+use winit::event::{ElementState, Ime, StartCause, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::window::{Window, WindowAttributes, WindowId};
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+use winit::window::{Fullscreen, Icon, Window, WindowAttributes, WindowId};
+
+mod frame_limiter;
+mod hot;
+mod touch_sim;
 
 // Import the game crate
-use panpan::Game;
+use frame_limiter::{FrameLimiter, Wait};
+use panpan::platform::PlatformRequest;
+use panpan::replay::{Player, Recorder, Recording};
+use panpan::{Conf, Context, Game, GameLoop, InputEvent, Key, WindowMode};
+use touch_sim::TouchSimulator;
 
 struct DesktopRunner<G: Game> {
+    conf: Conf,
     window: Option<Window>,
     gl_context: Option<PossiblyCurrentContext>,
     gl_surface: Option<Surface<WindowSurface>>,
     gl_display: Option<Display>,
     game: Option<G>,
+    ctx: Context,
+    game_loop: GameLoop,
+    frame_limiter: FrameLimiter,
     last_frame: Instant,
     cursor_pos: (f32, f32),
+    modifiers: ModifiersState,
+    touch_sim: TouchSimulator,
+    recorder: Option<(Recorder, PathBuf)>,
+    player: Option<Player>,
+    quit_sent: bool,
+}
+
+/// Options for `run_with`
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Record input and frame times to this file, saved on exit
+    pub record: Option<PathBuf>,
+    /// Drive the game from this recording instead of live input
+    pub replay: Option<PathBuf>,
+    /// Run the game from this dylib and reload it whenever it is rebuilt,
+    /// instead of the game compiled into the runner
+    pub hot: Option<PathBuf>,
+}
+
+impl RunOptions {
+    /// Read `--record <file>`, `--replay <file>` and `--hot <lib>` from the
+    /// command line
+    pub fn from_args() -> Self {
+        let mut options = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => options.record = args.next().map(PathBuf::from),
+                "--replay" => options.replay = args.next().map(PathBuf::from),
+                "--hot" => options.hot = args.next().map(PathBuf::from),
+                _ => {}
+            }
+        }
+        options
+    }
 }
 
 impl<G: Game> DesktopRunner<G> {
-    fn new() -> Self {
+    fn new(options: RunOptions) -> Self {
+        let player = options
+            .replay
+            .and_then(|path| match Recording::load(&path) {
+                Ok(recording) => {
+                    println!(
+                        "Replaying {} frames from {}",
+                        recording.frames.len(),
+                        path.display()
+                    );
+                    Some(Player::new(recording))
+                }
+                Err(e) => {
+                    println!("Failed to load replay {}: {}", path.display(), e);
+                    None
+                }
+            });
+        let recorder = options.record.map(|path| (Recorder::new(), path));
+
+        let conf = G::conf();
+
         Self {
+            frame_limiter: FrameLimiter::new(conf.target_fps),
+            conf,
             window: None,
             gl_context: None,
             gl_surface: None,
             gl_display: None,
             game: None,
+            ctx: Context::new(),
+            game_loop: GameLoop::new(G::timestep()),
             last_frame: Instant::now(),
             cursor_pos: (0.0, 0.0),
+            modifiers: ModifiersState::empty(),
+            touch_sim: TouchSimulator::default(),
+            recorder,
+            player,
+            quit_sent: false,
+        }
+    }
+
+    /// Pause or resume the game loop. While paused the event loop sleeps
+    /// until the next event instead of spinning.
+    fn set_paused(&mut self, paused: bool, event_loop: &ActiveEventLoop) {
+        let Some(game) = &mut self.game else {
+            return;
+        };
+        // Replays pause and resume at the same frames, so the timestep
+        // accumulator is reset where it was live
+        if let Some((recorder, _)) = &mut self.recorder {
+            match (self.game_loop.is_paused(), paused) {
+                (false, true) => recorder.record_pause(),
+                (true, false) => recorder.record_resume(),
+                _ => {}
+            }
+        }
+        let game_loop = &mut self.game_loop;
+        if paused {
+            self.ctx.scope(|_| game_loop.pause(game));
+            event_loop.set_control_flow(ControlFlow::Wait);
+        } else if game_loop.is_paused() {
+            self.ctx.scope(|_| game_loop.resume(game));
+            event_loop.set_control_flow(ControlFlow::Poll);
+            self.last_frame = Instant::now();
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
+        }
+    }
+
+    /// Tell the game it is quitting, once, and save any recording
+    fn quit(&mut self) {
+        if !self.quit_sent {
+            self.quit_sent = true;
+            if let Some(game) = &mut self.game {
+                self.ctx.scope(|_| game.on_quit());
+            }
+        }
+        self.stop_recording();
+    }
+
+    /// Record and dispatch a live input event
+    fn send(&mut self, event: InputEvent) {
+        // Live input is ignored while a replay drives the game
+        if self.player.is_some() {
+            return;
+        }
+        let Some(game) = &mut self.game else {
+            return;
+        };
+        if let Some((recorder, _)) = &mut self.recorder {
+            recorder.record_event(&event);
+        }
+        panpan::dispatch_event(game, &mut self.ctx, &event);
+    }
+
+    /// Dispatch touches from window positions, which winit reports in
+    /// physical pixels, converted to the game's coordinate space
+    fn send_touches(&mut self, touches: Vec<panpan::Touch>) {
+        let ratio = self.ctx.pixel_ratio();
+        for mut touch in touches {
+            touch.x /= ratio;
+            touch.y /= ratio;
+            self.send(InputEvent::Touch(touch));
+        }
+    }
+
+    fn window_center(&self) -> (f32, f32) {
+        self.window.as_ref().map_or((0.0, 0.0), |window| {
+            let size = window.inner_size();
+            (size.width as f32 * 0.5, size.height as f32 * 0.5)
+        })
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some((recorder, path)) = self.recorder.take() {
+            match recorder.save(&path) {
+                Ok(()) => println!(
+                    "Saved {} recorded frames to {}",
+                    recorder.frame_count(),
+                    path.display()
+                ),
+                Err(e) => println!("Failed to save recording {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    fn window_attributes(&self) -> WindowAttributes {
+        let conf = &self.conf;
+        let size: Size = if conf.high_dpi {
+            LogicalSize::new(conf.width as f64, conf.height as f64).into()
+        } else {
+            PhysicalSize::new(conf.width, conf.height).into()
+        };
+
+        let mut attributes = WindowAttributes::default()
+            .with_title(conf.title.clone())
+            .with_inner_size(size)
+            .with_resizable(conf.resizable);
+
+        if conf.fullscreen {
+            attributes = attributes.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+
+        if let Some(icon) = &conf.icon {
+            match Icon::from_rgba(icon.rgba.clone(), icon.width, icon.height) {
+                Ok(icon) => attributes = attributes.with_window_icon(Some(icon)),
+                Err(e) => println!("Ignoring invalid window icon: {}", e),
+            }
+        }
+
+        attributes
+    }
+
+    fn set_vsync(&self, vsync: bool) {
+        let (Some(gl_surface), Some(gl_context)) = (&self.gl_surface, &self.gl_context) else {
+            return;
+        };
+        let interval = if vsync {
+            SwapInterval::Wait(NonZeroU32::new(1).unwrap())
+        } else {
+            SwapInterval::DontWait
+        };
+        if let Err(e) = gl_surface.set_swap_interval(gl_context, interval) {
+            println!("Failed to set vsync: {}", e);
+        }
+    }
+
+    fn apply_platform_requests(&mut self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        for request in panpan::__internal_take_platform_requests(&mut self.ctx) {
+            match request {
+                PlatformRequest::SetWindowMode(mode) => {
+                    window.set_fullscreen(fullscreen(window, mode))
+                }
+                PlatformRequest::SetVsync(vsync) => self.set_vsync(vsync),
+                PlatformRequest::SetTargetFps(fps) => self.frame_limiter.set_target_fps(fps),
+                PlatformRequest::SetWindowSize(width, height) => {
+                    let size: Size = if self.conf.high_dpi {
+                        LogicalSize::new(width as f64, height as f64).into()
+                    } else {
+                        PhysicalSize::new(width, height).into()
+                    };
+                    // Applied right away where possible, otherwise a Resized event follows
+                    let _ = window.request_inner_size(size);
+                }
+                PlatformRequest::StartTextInput => window.set_ime_allowed(true),
+                PlatformRequest::StopTextInput => window.set_ime_allowed(false),
+                PlatformRequest::SetTextInputArea(area) => {
+                    let ratio = self.ctx.pixel_ratio();
+                    window.set_ime_cursor_area(
+                        PhysicalPosition::new(area.x * ratio, area.y * ratio),
+                        PhysicalSize::new(area.width * ratio, area.height * ratio),
+                    )
+                }
+            }
         }
     }
 }
@@ -46,14 +285,14 @@ impl<G: Game> DesktopRunner<G> {
 impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_some() {
+            self.set_paused(false, event_loop);
             return;
         }
 
         println!("Creating window and OpenGL context...");
 
-        let window_attributes = WindowAttributes::default()
-            .with_title("PanPan Game")
-            .with_inner_size(winit::dpi::LogicalSize::new(800.0, 600.0));
+        let window_attributes = self.window_attributes();
+        let wanted_samples = self.conf.msaa_samples;
 
         let template = ConfigTemplateBuilder::new()
             .with_alpha_size(8)
@@ -63,14 +302,9 @@ impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
 
         let (window, gl_config) = display_builder
             .build(event_loop, template, |configs| {
+                // The config with the sample count closest to the requested one
                 configs
-                    .reduce(|accum, config| {
-                        if config.num_samples() > accum.num_samples() {
-                            config
-                        } else {
-                            accum
-                        }
-                    })
+                    .min_by_key(|config| config.num_samples().abs_diff(wanted_samples))
                     .unwrap()
             })
             .expect("Failed to create OpenGL config");
@@ -124,15 +358,17 @@ impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
         };
 
         // Initialize panpan graphics backend
-        panpan::__internal_init_graphics(gl);
+        panpan::__internal_init_graphics(&mut self.ctx, gl);
 
         println!("PanPan graphics initialized");
 
         // Set initial viewport
-        panpan::__internal_resize(size.width as i32, size.height as i32);
+        panpan::__internal_set_coordinate_space(&mut self.ctx, self.conf.coordinate_space);
+        panpan::__internal_set_scale_factor(&mut self.ctx, window.scale_factor() as f32);
+        panpan::__internal_resize(&mut self.ctx, size.width as i32, size.height as i32);
 
         // Create the game
-        let game = G::new();
+        let game = self.ctx.scope(G::new);
         println!("Game created");
 
         self.window = Some(window);
@@ -141,6 +377,7 @@ impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
         self.gl_display = Some(gl_display);
         self.game = Some(game);
         self.last_frame = Instant::now();
+        self.set_vsync(self.conf.vsync);
     }
 
     fn window_event(
@@ -151,8 +388,24 @@ impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
     ) {
         match event {
             WindowEvent::CloseRequested => {
-                println!("Close requested, exiting...");
-                event_loop.exit();
+                let close = self
+                    .game
+                    .as_mut()
+                    .is_none_or(|game| self.ctx.scope(|_| game.on_close_requested()));
+                if close {
+                    println!("Close requested, exiting...");
+                    self.quit();
+                    event_loop.exit();
+                }
+            }
+            WindowEvent::Focused(focused) => {
+                if let Some(game) = &mut self.game {
+                    self.ctx.scope(|_| game.on_focus_changed(focused));
+                }
+            }
+            WindowEvent::Occluded(occluded) => {
+                // Minimized or fully hidden, nothing to show
+                self.set_paused(occluded, event_loop);
             }
             WindowEvent::Resized(size) => {
                 if let (Some(gl_surface), Some(gl_context)) = (&self.gl_surface, &self.gl_context) {
@@ -160,49 +413,125 @@ impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
                     let height =
                         NonZeroU32::new(size.height).unwrap_or(NonZeroU32::new(1).unwrap());
                     gl_surface.resize(gl_context, width, height);
-                    panpan::__internal_resize(size.width as i32, size.height as i32);
+                    panpan::__internal_resize(&mut self.ctx, size.width as i32, size.height as i32);
                 }
             }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // winit follows up with a Resized event for the new framebuffer
+                // size, which rebuilds the projection
+                panpan::__internal_set_scale_factor(&mut self.ctx, scale_factor as f32);
+            }
             WindowEvent::RedrawRequested => {
+                if self.game_loop.is_paused() {
+                    return;
+                }
                 if let Some(game) = &mut self.game {
                     let now = Instant::now();
-                    let dt = now.duration_since(self.last_frame).as_secs_f32();
+                    let mut dt = now.duration_since(self.last_frame).as_secs_f32();
                     self.last_frame = now;
 
-                    // Update game
-                    game.update(dt);
+                    // A replay supplies both the events and the dt of each frame
+                    if let Some(player) = &mut self.player {
+                        match player.play_frame(game, &mut self.ctx, &mut self.game_loop) {
+                            Some(recorded) => dt = recorded,
+                            None => {
+                                println!("Replay finished, live input enabled");
+                                self.player = None;
+                            }
+                        }
+                    }
+                    if let Some((recorder, _)) = &mut self.recorder {
+                        // Record what the loop will actually simulate
+                        let simulated = if self.game_loop.skips_next_frame() {
+                            0.0
+                        } else {
+                            dt
+                        };
+                        recorder.end_frame(simulated);
+                    }
 
-                    // Render game
-                    game.render();
+                    // Update and render game
+                    self.game_loop.frame(game, &mut self.ctx, dt);
+
+                    self.apply_platform_requests();
 
                     // Swap buffers
                     if let (Some(gl_surface), Some(gl_context)) =
                         (&self.gl_surface, &self.gl_context)
                     {
+                        let start = Instant::now();
                         gl_surface.swap_buffers(gl_context).unwrap();
+                        panpan::__internal_record_swap(&mut self.ctx, start.elapsed());
                     }
                 }
-
-                // Request next frame
-                if let Some(window) = &self.window {
-                    window.request_redraw();
-                }
+                // The next frame is requested from about_to_wait, which
+                // honors the frame rate cap
             }
             WindowEvent::CursorMoved { position, .. } => {
                 self.cursor_pos = (position.x as f32, position.y as f32);
+                let touches = self.touch_sim.mouse_moved(self.cursor_pos);
+                self.send_touches(touches);
             }
             WindowEvent::MouseInput { state, .. } => {
-                if let Some(game) = &mut self.game {
-                    match state {
-                        winit::event::ElementState::Pressed => {
-                            game.on_touch_down(0, self.cursor_pos.0, self.cursor_pos.1);
+                let touches = match state {
+                    ElementState::Pressed => {
+                        let center = self.window_center();
+                        self.touch_sim
+                            .mouse_pressed(self.cursor_pos, self.modifiers, center)
+                    }
+                    ElementState::Released => self.touch_sim.mouse_released(self.cursor_pos),
+                };
+                self.send_touches(touches);
+            }
+            WindowEvent::Touch(touch) => {
+                let position = (touch.location.x as f32, touch.location.y as f32);
+                if let Some(touch) = self.touch_sim.real_touch(touch.id, position, touch.phase) {
+                    self.send_touches(vec![touch]);
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                if event.repeat {
+                    return;
+                }
+                if let PhysicalKey::Code(code) = event.physical_key {
+                    // F9 ends a `--record` session early instead of reaching
+                    // the game. Recording cannot start mid-game, because
+                    // playback always starts from `Game::new`.
+                    if code == KeyCode::F9 {
+                        if event.state == ElementState::Pressed {
+                            self.stop_recording();
                         }
-                        winit::event::ElementState::Released => {
-                            game.on_touch_up(0);
+                        return;
+                    }
+                    let key = map_key(code);
+                    match event.state {
+                        ElementState::Pressed => self.send(InputEvent::KeyDown(key)),
+                        ElementState::Released => self.send(InputEvent::KeyUp(key)),
+                    }
+                }
+                // Plain typing arrives here; composed text arrives as Ime::Commit
+                if event.state == ElementState::Pressed && self.ctx.is_text_input_active() {
+                    if let Some(text) = event.text {
+                        if text.chars().all(|c| !c.is_control()) {
+                            self.send(InputEvent::Text(text.to_string()));
                         }
                     }
                 }
             }
+            WindowEvent::Ime(ime) => match ime {
+                Ime::Preedit(text, cursor) => self.send(InputEvent::Composition { text, cursor }),
+                Ime::Commit(text) => {
+                    self.send(InputEvent::Composition {
+                        text: String::new(),
+                        cursor: None,
+                    });
+                    self.send(InputEvent::Text(text));
+                }
+                Ime::Enabled | Ime::Disabled => {}
+            },
             _ => {}
         }
     }
@@ -215,19 +544,100 @@ impl<G: Game + 'static> ApplicationHandler for DesktopRunner<G> {
         }
     }
 
-    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        if let Some(window) = &self.window {
-            window.request_redraw();
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.game_loop.is_paused() {
+            return;
+        }
+        match self.frame_limiter.wait() {
+            Wait::Until(deadline) => event_loop.set_control_flow(ControlFlow::WaitUntil(deadline)),
+            Wait::Ready => {
+                event_loop.set_control_flow(ControlFlow::Poll);
+                if let Some(window) = &self.window {
+                    window.request_redraw();
+                }
+            }
+        }
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        self.set_paused(true, event_loop);
+    }
+
+    fn memory_warning(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(game) = &mut self.game {
+            self.ctx.scope(|_| game.on_low_memory());
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.quit();
+    }
+}
+
+/// The winit fullscreen setting for a panpan window mode
+fn fullscreen(window: &Window, mode: WindowMode) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(None)),
+        WindowMode::ExclusiveFullscreen => {
+            // Largest resolution, then highest refresh rate
+            let best = window.current_monitor().and_then(|monitor| {
+                monitor.video_modes().max_by_key(|mode| {
+                    let size = mode.size();
+                    (size.width * size.height, mode.refresh_rate_millihertz())
+                })
+            });
+            Some(match best {
+                Some(mode) => Fullscreen::Exclusive(mode),
+                None => Fullscreen::Borderless(None),
+            })
         }
     }
 }
 
+fn map_key(code: KeyCode) -> Key {
+    match code {
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyD => Key::D,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::Space => Key::Space,
+        KeyCode::Enter | KeyCode::NumpadEnter => Key::Enter,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Escape => Key::Escape,
+        _ => Key::Unknown,
+    }
+}
+
+/// Run the game, taking `--record`, `--replay` and `--hot` from the
+/// command line
 pub fn run<G: Game + 'static>() {
+    run_with::<G>(RunOptions::from_args());
+}
+
+pub fn run_with<G: Game + 'static>(options: RunOptions) {
+    if let Some(path) = &options.hot {
+        // G is compiled in as well, but the library is what gets run
+        if let Err(e) = hot::load(path) {
+            eprintln!("Failed to load {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        println!("Hot reload enabled for {}", path.display());
+        return start::<hot::HotGame>(options);
+    }
+    start::<G>(options);
+}
+
+fn start<G: Game + 'static>(options: RunOptions) {
     println!("Starting PanPan Desktop Runner...");
 
     let event_loop = EventLoop::new().expect("Failed to create event loop");
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = DesktopRunner::<G>::new();
+    let mut app = DesktopRunner::<G>::new(options);
     event_loop.run_app(&mut app).expect("Event loop error");
 }
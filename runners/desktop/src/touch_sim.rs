@@ -0,0 +1,135 @@
+// runners/desktop/src/touch_sim.rs
+// Turns mouse input into multi-touch sequences and maps real touchscreen ids
+//
+// - Click and drag: finger 0
+// - Ctrl+click: place (or remove) an anchored finger 1, then drag finger 0
+//   around it to pinch and rotate
+// - Shift+drag: finger 1 mirrors finger 0 around the window center
+
+use panpan::{Touch, TouchPhase};
+use std::collections::HashMap;
+use winit::keyboard::ModifiersState;
+
+const MOUSE_ID: i32 = 0;
+const SECOND_ID: i32 = 1;
+
+#[derive(Debug, Default)]
+pub(crate) struct TouchSimulator {
+    mouse_down: bool,
+    anchor: Option<(f32, f32)>,
+    /// Center finger 1 is mirrored around while a Shift drag is active
+    mirror: Option<(f32, f32)>,
+    /// winit touch id -> panpan touch id
+    real: HashMap<u64, i32>,
+}
+
+impl TouchSimulator {
+    pub fn mouse_pressed(
+        &mut self,
+        position: (f32, f32),
+        modifiers: ModifiersState,
+        window_center: (f32, f32),
+    ) -> Vec<Touch> {
+        if self.mouse_down {
+            return Vec::new();
+        }
+
+        if modifiers.control_key() {
+            return match self.anchor.take() {
+                Some(anchor) => vec![touch(SECOND_ID, anchor, TouchPhase::Ended)],
+                None => {
+                    self.anchor = Some(position);
+                    vec![touch(SECOND_ID, position, TouchPhase::Started)]
+                }
+            };
+        }
+
+        self.mouse_down = true;
+        let mut touches = vec![touch(MOUSE_ID, position, TouchPhase::Started)];
+
+        if modifiers.shift_key() && self.anchor.is_none() {
+            self.mirror = Some(window_center);
+            touches.push(touch(
+                SECOND_ID,
+                mirrored(position, window_center),
+                TouchPhase::Started,
+            ));
+        }
+
+        touches
+    }
+
+    pub fn mouse_moved(&mut self, position: (f32, f32)) -> Vec<Touch> {
+        if !self.mouse_down {
+            return Vec::new();
+        }
+        let mut touches = vec![touch(MOUSE_ID, position, TouchPhase::Moved)];
+        if let Some(center) = self.mirror {
+            touches.push(touch(
+                SECOND_ID,
+                mirrored(position, center),
+                TouchPhase::Moved,
+            ));
+        }
+        touches
+    }
+
+    pub fn mouse_released(&mut self, position: (f32, f32)) -> Vec<Touch> {
+        if !self.mouse_down {
+            return Vec::new();
+        }
+        self.mouse_down = false;
+        let mut touches = vec![touch(MOUSE_ID, position, TouchPhase::Ended)];
+        if let Some(center) = self.mirror.take() {
+            touches.push(touch(
+                SECOND_ID,
+                mirrored(position, center),
+                TouchPhase::Ended,
+            ));
+        }
+        touches
+    }
+
+    /// Map a real touchscreen event to a small panpan id, like Android's
+    /// pointer ids. Cancelled touches are reported as ended.
+    pub fn real_touch(
+        &mut self,
+        id: u64,
+        position: (f32, f32),
+        phase: winit::event::TouchPhase,
+    ) -> Option<Touch> {
+        use winit::event::TouchPhase as Winit;
+        match phase {
+            Winit::Started => {
+                let panpan_id = self.free_id();
+                self.real.insert(id, panpan_id);
+                Some(touch(panpan_id, position, TouchPhase::Started))
+            }
+            Winit::Moved => {
+                let panpan_id = *self.real.get(&id)?;
+                Some(touch(panpan_id, position, TouchPhase::Moved))
+            }
+            Winit::Ended | Winit::Cancelled => {
+                let panpan_id = self.real.remove(&id)?;
+                Some(touch(panpan_id, position, TouchPhase::Ended))
+            }
+        }
+    }
+
+    fn free_id(&self) -> i32 {
+        let in_use = |id: i32| {
+            self.real.values().any(|v| *v == id)
+                || (id == MOUSE_ID && self.mouse_down)
+                || (id == SECOND_ID && (self.anchor.is_some() || self.mirror.is_some()))
+        };
+        (0..).find(|id| !in_use(*id)).unwrap_or(0)
+    }
+}
+
+fn touch(id: i32, (x, y): (f32, f32), phase: TouchPhase) -> Touch {
+    Touch { id, x, y, phase }
+}
+
+fn mirrored((x, y): (f32, f32), (cx, cy): (f32, f32)) -> (f32, f32) {
+    (2.0 * cx - x, 2.0 * cy - y)
+}
@@ -21,6 +21,18 @@ enum Commands {
         /// Path to the game crate (default: current directory)
         #[arg(default_value = ".")]
         game: String,
+
+        /// Record input to a file (recording starts with the game; F9 stops it early and saves)
+        #[arg(long, value_name = "FILE")]
+        record: Option<PathBuf>,
+
+        /// Play back a recorded input file
+        #[arg(long, value_name = "FILE", conflicts_with = "record")]
+        replay: Option<PathBuf>,
+
+        /// Build the game as a dylib and reload it whenever its source changes
+        #[arg(long)]
+        hot: bool,
     },
 
     /// Build for a platform
@@ -43,8 +55,13 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Run { game } => {
-            run_desktop(&game)?;
+        Commands::Run {
+            game,
+            record,
+            replay,
+            hot,
+        } => {
+            run_desktop(&game, record, replay, hot)?;
         }
         Commands::Build {
             platform,
@@ -60,7 +77,12 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_desktop(game_path: &str) -> Result<()> {
+fn run_desktop(
+    game_path: &str,
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    hot: bool,
+) -> Result<()> {
     println!("🚀 Running game on desktop...");
 
     let game_path = Path::new(game_path)
@@ -68,14 +90,48 @@ fn run_desktop(game_path: &str) -> Result<()> {
         .context("Failed to find game directory")?;
 
     // Just use cargo run in demo_runner for now
-    let runner_path = find_project_root()?.join("demo_runner");
+    let root = find_project_root()?;
+    let runner_path = root.join("demo_runner");
 
     println!("   Building and running from: {}", runner_path.display());
 
-    let status = Command::new("cargo")
-        .arg("run")
-        .current_dir(&runner_path)
-        .status()?;
+    let mut cmd = Command::new("cargo");
+    cmd.arg("run").current_dir(&runner_path);
+
+    // The runner starts in its own directory, so hand it absolute paths
+    let mut runner_args: Vec<std::ffi::OsString> = Vec::new();
+    if let Some(record) = record {
+        let record = std::env::current_dir()?.join(record);
+        println!("   Recording input to: {}", record.display());
+        runner_args.extend(["--record".into(), record.into()]);
+    } else if let Some(replay) = replay {
+        let replay = replay
+            .canonicalize()
+            .context("Failed to find replay file")?;
+        println!("   Replaying: {}", replay.display());
+        runner_args.extend(["--replay".into(), replay.into()]);
+    }
+
+    let hot_library = if hot {
+        let library = build_hot_library(&root, &game_path)?;
+        println!("   Hot reloading: {}", library.display());
+        runner_args.extend(["--hot".into(), library.clone().into()]);
+        Some(library)
+    } else {
+        None
+    };
+
+    if !runner_args.is_empty() {
+        cmd.arg("--").args(runner_args);
+    }
+
+    let status = match hot_library {
+        Some(_) => {
+            let mut child = cmd.spawn()?;
+            watch_and_rebuild(&root, &game_path, &mut child)?
+        }
+        None => cmd.status()?,
+    };
 
     if !status.success() {
         anyhow::bail!("Failed to run game");
@@ -84,6 +140,97 @@ fn run_desktop(game_path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Build the game crate as a dylib for `--hot` and return its path
+fn build_hot_library(root: &Path, game_path: &Path) -> Result<PathBuf> {
+    // A separate target dir keeps the cdylib build from invalidating the
+    // normal one
+    let target_dir = root.join("target").join("hot");
+    let status = Command::new("cargo")
+        .arg("rustc")
+        .arg("--manifest-path")
+        .arg(game_path.join("Cargo.toml"))
+        .arg("--lib")
+        .arg("--crate-type")
+        .arg("cdylib")
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .status()?;
+
+    if !status.success() {
+        anyhow::bail!("Game library build failed");
+    }
+
+    let name = package_name(game_path)?.replace('-', "_");
+    Ok(target_dir.join("debug").join(format!(
+        "{}{}{}",
+        std::env::consts::DLL_PREFIX,
+        name,
+        std::env::consts::DLL_SUFFIX
+    )))
+}
+
+/// Rebuild the game library whenever a source file changes, until the
+/// runner exits. The runner picks up the new library by itself.
+fn watch_and_rebuild(
+    root: &Path,
+    game_path: &Path,
+    child: &mut std::process::Child,
+) -> Result<std::process::ExitStatus> {
+    let src = game_path.join("src");
+    let mut last = newest_modification(&src);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(300));
+
+        let newest = newest_modification(&src);
+        if newest > last {
+            last = newest;
+            println!("🔁 Source changed, rebuilding game library...");
+            // A failed build keeps the old library running
+            if let Err(e) = build_hot_library(root, game_path) {
+                println!("   {}", e);
+            }
+        }
+    }
+}
+
+fn newest_modification(dir: &Path) -> Option<std::time::SystemTime> {
+    let mut newest = None;
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        let modified = if path.is_dir() {
+            newest_modification(&path)
+        } else {
+            entry.metadata().and_then(|m| m.modified()).ok()
+        };
+        newest = newest.max(modified);
+    }
+    newest
+}
+
+/// Read `name` from the `[package]` section of a crate's Cargo.toml
+fn package_name(game_path: &Path) -> Result<String> {
+    let manifest = std::fs::read_to_string(game_path.join("Cargo.toml"))
+        .context("Failed to read game Cargo.toml")?;
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(value) = line.strip_prefix("name") {
+                let value = value.trim_start().trim_start_matches('=').trim();
+                return Ok(value.trim_matches('"').to_string());
+            }
+        }
+    }
+    anyhow::bail!(
+        "No package name in {}",
+        game_path.join("Cargo.toml").display()
+    )
+}
+
 fn build_desktop(release: bool) -> Result<()> {
     println!("🔨 Building for desktop...");
 